@@ -0,0 +1,115 @@
+// Measures how fast `M6502::clock` and `FastCpu::step` run a loop of
+// common instructions out of flat RAM, against the `do_step` engine that
+// `M6502` used before its microcode: `cargo bench --bench clock`.
+
+use std::{hint::black_box, time::Instant};
+
+use m6502::{
+    Bus, M6502, asm,
+    core::{Core, P},
+    fast::FastCpu,
+    memory::Memory,
+};
+
+// Copied whole, reset and all, though the bench only runs programs.
+#[allow(dead_code)]
+mod do_step;
+
+const CYCLES: u64 = 50_000_000;
+const RUNS: usize = 5;
+
+const PROGRAM: &[&str] = &[
+    "LDX #$00",
+    "LDA $1000,X", // $0202
+    "ADC $10",
+    "STA $1100,X",
+    "ROL $11",
+    "LDY $12",
+    "LDA ($20),Y",
+    "EOR #$5A",
+    "INX",
+    "BNE $0202",
+    "JSR $021B",
+    "JMP $0200",
+    "PHA", // $021B
+    "PLA",
+    "RTS",
+];
+
+fn main() {
+    let mut ram = Box::new([0; 65536]);
+    let mut pc = 0x0200;
+    for line in PROGRAM {
+        let bytes = asm::assemble(line, pc, None).unwrap();
+        ram[pc as usize..][..bytes.len()].copy_from_slice(&bytes);
+        pc += bytes.len() as u16;
+    }
+    ram[0x20..0x22].copy_from_slice(&[0xF0, 0x10]);
+
+    let start = Core {
+        a: 0,
+        p: P::new(),
+        pc: 0x0200,
+        s: 0xFF,
+        x: 0,
+        y: 0,
+    };
+    // The two engines must agree on every cycle for the times to compare.
+    let mut new = (M6502::new(start), Bus::new(), ram.clone());
+    let mut old = (do_step::M6502::new(start), Bus::new(), ram.clone());
+    for cycle in 0..100_000 {
+        new.0.clock(&mut new.1);
+        new.2.access(&mut new.1);
+        old.0.clock(&mut old.1);
+        old.2.access(&mut old.1);
+        assert_eq!(
+            (new.1, new.0.core()),
+            (old.1, old.0.core()),
+            "the engines disagree at cycle {cycle}"
+        );
+    }
+
+    report("M6502 (do_step)", || {
+        let mut cpu = do_step::M6502::new(start);
+        let mut bus = Bus::new();
+        let mut ram = ram.clone();
+        for _ in 0..CYCLES {
+            cpu.clock(&mut bus);
+            ram.access(&mut bus);
+        }
+        black_box(cpu);
+    });
+    report("M6502", || {
+        let mut cpu = M6502::new(start);
+        let mut bus = Bus::new();
+        let mut ram = ram.clone();
+        for _ in 0..CYCLES {
+            cpu.clock(&mut bus);
+            ram.access(&mut bus);
+        }
+        black_box(cpu);
+    });
+    report("FastCpu", || {
+        let mut cpu = FastCpu::new(start);
+        let mut ram = ram.clone();
+        let mut cycles = 0;
+        while cycles < CYCLES {
+            cycles += cpu.step(&mut ram) as u64;
+        }
+        black_box(cpu);
+    });
+}
+
+// Runs at least CYCLES cycles.
+fn report(name: &str, run: impl Fn()) {
+    let mut best = f64::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!(
+        "{name}: {CYCLES} cycles in {best:.3}s, {:.1} MHz",
+        CYCLES as f64 / best / 1e6
+    );
+}
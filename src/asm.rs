@@ -0,0 +1,133 @@
+use std::fmt;
+
+use crate::{
+    disasm,
+    instr::{self, Am, Op},
+    symbols::SymbolTable,
+};
+
+// Where an opcode has aliases, the documented one.
+const PREFERRED: [u8; 2] = [0xEA, 0xE9];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmError {
+    UnknownMnemonic(String),
+    BadOperand(String),
+    UndefinedSymbol(String),
+    NoSuchMode,
+    BranchOutOfRange,
+}
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownMnemonic(name) => write!(f, "unknown mnemonic {name}"),
+            Self::BadOperand(text) => write!(f, "cannot parse operand {text}"),
+            Self::UndefinedSymbol(name) => write!(f, "undefined symbol {name}"),
+            Self::NoSuchMode => write!(f, "addressing mode not available"),
+            Self::BranchOutOfRange => write!(f, "branch target out of range"),
+        }
+    }
+}
+impl std::error::Error for AsmError {}
+
+// Assembles one instruction to be placed at `pc`. Numbers are decimal,
+// `$` hex or `%` binary, or names from `symbols`; `*` is `pc`. A value
+// below $100 picks zero page unless it is written with more than two hex
+// digits.
+pub fn assemble(text: &str, pc: u16, symbols: Option<&SymbolTable>) -> Result<Vec<u8>, AsmError> {
+    let text = text.trim();
+    let (mnemonic, operand) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let op = (0..=255)
+        .map(|opcode| instr::decode(opcode).0)
+        .find(|op| format!("{op:?}").eq_ignore_ascii_case(mnemonic))
+        .ok_or_else(|| AsmError::UnknownMnemonic(mnemonic.to_string()))?;
+
+    let operand: String = operand.chars().filter(|c| !c.is_whitespace()).collect();
+    let upper = operand.to_ascii_uppercase();
+    let value = |text: &str| value(text, pc, symbols);
+    let modes = |narrow: Am, wide: Am, text: &str| -> Result<(Vec<Am>, u16), AsmError> {
+        let (v, forced_wide) = value(text)?;
+        if v > 0xFF || forced_wide {
+            Ok((vec![wide], v))
+        } else {
+            Ok((vec![narrow, wide], v))
+        }
+    };
+
+    let (candidates, v) = if operand.is_empty() {
+        (vec![Am::Implied, Am::Accumulator], 0)
+    } else if upper == "A" {
+        (vec![Am::Accumulator], 0)
+    } else if let Some(imm) = operand.strip_prefix('#') {
+        (vec![Am::Immediate], value(imm)?.0)
+    } else if let Some(inner) = upper.strip_suffix(",X)").and_then(|s| s.strip_prefix('(')) {
+        (
+            vec![Am::IndexedIndirect],
+            value(&operand[1..=inner.len()])?.0,
+        )
+    } else if let Some(inner) = upper.strip_suffix("),Y").and_then(|s| s.strip_prefix('(')) {
+        (
+            vec![Am::IndirectIndexed],
+            value(&operand[1..=inner.len()])?.0,
+        )
+    } else if let Some(inner) = upper.strip_suffix(')').and_then(|s| s.strip_prefix('(')) {
+        (vec![Am::Indirect], value(&operand[1..=inner.len()])?.0)
+    } else if let Some(base) = upper.strip_suffix(",X") {
+        modes(Am::ZeroX, Am::AbsoluteX, &operand[..base.len()])?
+    } else if let Some(base) = upper.strip_suffix(",Y") {
+        modes(Am::ZeroY, Am::AbsoluteY, &operand[..base.len()])?
+    } else {
+        let (mut candidates, v) = modes(Am::Zero, Am::Absolute, &operand)?;
+        candidates.insert(0, Am::Relative);
+        (candidates, v)
+    };
+
+    let (opcode, am) = candidates
+        .into_iter()
+        .find_map(|am| Some((opcode(op, am)?, am)))
+        .ok_or(AsmError::NoSuchMode)?;
+    let mut bytes = vec![opcode];
+    match disasm::size(am) {
+        1 => (),
+        2 if am == Am::Relative => {
+            let offset = v.wrapping_sub(pc.wrapping_add(2)) as i16;
+            let offset = i8::try_from(offset).map_err(|_| AsmError::BranchOutOfRange)?;
+            bytes.push(offset as u8);
+        }
+        2 => bytes.push(u8::try_from(v).map_err(|_| AsmError::BadOperand(operand))?),
+        _ => bytes.extend_from_slice(&v.to_le_bytes()),
+    }
+    Ok(bytes)
+}
+
+pub fn opcode(op: Op, am: Am) -> Option<u8> {
+    let mut opcodes = (0..=255u8).filter(|&opcode| instr::decode(opcode) == (op, am));
+    let first = opcodes.next()?;
+    Some(
+        opcodes
+            .find(|opcode| PREFERRED.contains(opcode))
+            .unwrap_or(first),
+    )
+}
+
+// The value and whether it was written wider than a byte.
+fn value(text: &str, pc: u16, symbols: Option<&SymbolTable>) -> Result<(u16, bool), AsmError> {
+    let bad = || AsmError::BadOperand(text.to_string());
+    if text == "*" {
+        Ok((pc, false))
+    } else if let Some(hex) = text.strip_prefix('$') {
+        let v = u16::from_str_radix(hex, 16).map_err(|_| bad())?;
+        Ok((v, hex.len() > 2))
+    } else if let Some(bin) = text.strip_prefix('%') {
+        let v = u16::from_str_radix(bin, 2).map_err(|_| bad())?;
+        Ok((v, bin.len() > 8))
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        Ok((text.parse().map_err(|_| bad())?, false))
+    } else if !text.is_empty() {
+        let symbol = symbols.and_then(|s| s.get(text));
+        let symbol = symbol.ok_or_else(|| AsmError::UndefinedSymbol(text.to_string()))?;
+        Ok((symbol.addr, false))
+    } else {
+        Err(bad())
+    }
+}
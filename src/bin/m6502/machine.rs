@@ -0,0 +1,184 @@
+use std::{cell::RefCell, rc::Rc};
+
+use m6502::{
+    Bus, M6502,
+    core::Core,
+    disasm,
+    instr::{self, Op},
+    memory::Memory,
+    mos_sim::{MosSim, Outcome},
+    sim65::Sim65,
+};
+
+const BRK: u8 = 0x00;
+
+// The host side of the few systems with a way to end a program.
+pub enum System {
+    Plain,
+    Sim65(Sim65),
+    MosSim(Rc<RefCell<MosSim>>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Exit(u8),
+    ExitAddress(u16),
+    Jam(u16),
+    Brk(u16),
+    TrapLoop(u16),
+    Limit,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Stops {
+    pub exit: Option<u16>,
+    pub brk: bool,
+    pub limit: Option<u64>,
+}
+
+// An instruction as it ran: where it was fetched, the registers before it,
+// the bytes the CPU read as opcode and operand, and the cycle it began on.
+#[derive(Clone, Debug)]
+pub struct Retired {
+    pub pc: u16,
+    pub core: Core,
+    pub bytes: Vec<u8>,
+    pub cycles: u64,
+}
+
+// A CPU and its memory, stopped between instructions: the last cycle run
+// fetched the opcode at `pc()`.
+pub struct Machine {
+    pub cpu: M6502,
+    pub bus: Bus,
+    pub mem: Box<dyn Memory>,
+    pub system: System,
+    pub cycles: u64,
+    last_pc: Option<u16>,
+    log: Vec<Bus>,
+}
+impl Machine {
+    pub fn new(cpu: M6502, mem: Box<dyn Memory>, system: System) -> Self {
+        let mut machine = Self {
+            cpu,
+            bus: Bus::new(),
+            mem,
+            system,
+            cycles: 0,
+            last_pc: None,
+            log: Vec::new(),
+        };
+        machine.fetch();
+        machine
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.bus.addr
+    }
+    pub fn core(&self) -> Core {
+        let mut core = self.cpu.core();
+        core.pc = self.pc();
+        core
+    }
+    // Restarts from `core`, fetching the opcode at its PC again. Needed
+    // after changing the registers or the memory under the PC.
+    pub fn set_core(&mut self, core: Core) {
+        self.cpu = M6502::new(core);
+        self.cycles -= 1;
+        self.last_pc = None;
+        self.fetch();
+    }
+    pub fn jammed(&self) -> bool {
+        instr::decode(self.bus.data).0 == Op::Jam
+    }
+    // Every cycle of the last instruction stepped, its opcode fetch first.
+    pub fn last_cycles(&self) -> &[Bus] {
+        &self.log
+    }
+    pub fn exit_code(&self) -> Option<u8> {
+        match &self.system {
+            System::Plain => None,
+            System::Sim65(sim) => sim.exit_code(),
+            System::MosSim(sim) => match sim.borrow().outcome()? {
+                Outcome::Exit(code) => Some(code),
+                Outcome::Abort => Some(0xFF),
+            },
+        }
+    }
+
+    // Why the machine should not run the instruction it has fetched, if it
+    // should not.
+    pub fn check(&self, stops: &Stops) -> Option<Stop> {
+        let (pc, opcode) = (self.pc(), self.bus.data);
+        if let Some(code) = self.exit_code() {
+            Some(Stop::Exit(code))
+        } else if stops.exit == Some(pc) {
+            Some(Stop::ExitAddress(pc))
+        } else if self.jammed() {
+            Some(Stop::Jam(pc))
+        } else if stops.brk && opcode == BRK {
+            Some(Stop::Brk(pc))
+        } else if self.last_pc == Some(pc) {
+            Some(Stop::TrapLoop(pc))
+        } else if stops.limit.is_some_and(|limit| self.cycles >= limit) {
+            Some(Stop::Limit)
+        } else {
+            None
+        }
+    }
+
+    // Runs the fetched instruction up to the next opcode fetch.
+    pub fn step(&mut self) -> Retired {
+        let pc = self.pc();
+        let mut retired = Retired {
+            pc,
+            core: self.core(),
+            bytes: vec![self.bus.data],
+            cycles: self.cycles - 1,
+        };
+        let size = disasm::size(instr::decode(self.bus.data).1) as usize;
+        self.log.clear();
+        self.log.push(self.bus);
+        loop {
+            self.clock();
+            if self.bus.sync() {
+                break;
+            }
+            self.log.push(self.bus);
+            let next = pc.wrapping_add(retired.bytes.len() as u16);
+            if self.bus.rw() && self.bus.addr == next && retired.bytes.len() < size {
+                retired.bytes.push(self.bus.data);
+            }
+        }
+        self.last_pc = Some(pc);
+        retired
+    }
+
+    pub fn run(&mut self, stops: &Stops, mut trace: impl FnMut(&Self, &Retired)) -> Stop {
+        loop {
+            if let Some(stop) = self.check(stops) {
+                return stop;
+            }
+            let retired = self.step();
+            trace(self, &retired);
+        }
+    }
+
+    fn fetch(&mut self) {
+        self.clock();
+        while !self.bus.sync() {
+            self.clock();
+        }
+    }
+    fn clock(&mut self) {
+        self.cpu.clock(&mut self.bus);
+        let trapped = match &mut self.system {
+            System::Sim65(sim) => sim.trap(&mut self.cpu, &mut self.bus, &mut self.mem),
+            _ => false,
+        };
+        if !trapped {
+            self.mem.access(&mut self.bus);
+        }
+        self.cycles += 1;
+    }
+}
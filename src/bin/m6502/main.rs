@@ -0,0 +1,393 @@
+use std::{
+    cell::RefCell, collections::HashMap, env, error::Error, fs, io, path::Path, process::ExitCode,
+    rc::Rc,
+};
+
+use m6502::{
+    M6502,
+    core::{Core, P},
+    debug_info::DebugInfo,
+    disasm::Instruction,
+    elf::Elf,
+    ines::Rom,
+    loader::Image,
+    memory::Memory,
+    mos_sim::MosSim,
+    o65::O65,
+    sim65::{Header, Sim65},
+    symbols::{self, Symbol, SymbolKind, SymbolTable},
+};
+
+use machine::{Machine, Retired, Stop, Stops, System};
+use monitor::Monitor;
+#[cfg(unix)]
+use tui::Tui;
+
+mod machine;
+mod monitor;
+// The debugger puts the terminal in raw mode through stty(1).
+#[cfg(unix)]
+mod tui;
+
+const USAGE: &str = "usage: m6502 [options] FILE [ARGS...]
+       m6502 --monitor [options] [FILE [ARGS...]]
+       m6502 --tui [options] [FILE [ARGS...]]
+
+Runs a 6502 program until it exits, jams, loops on itself or hits a stop,
+or opens a machine-language monitor or a full-screen debugger on it.
+
+options:
+  -f, --format FORMAT  raw, prg, hex, srec, nes, o65, elf, sim65 or mos-sim
+                       (default: from the file's header or extension)
+  -l, --load ADDR      load address of a raw image (default 0)
+  -r, --reset ADDR     store ADDR in the reset vector
+  -s, --start ADDR     start at ADDR instead of through the reset vector
+  -e, --exit ADDR      stop with success when the CPU fetches from ADDR
+  -b, --brk            stop at BRK
+  -c, --cycles N       stop after N cycles
+  -t, --trace          print each instruction as it runs
+  -y, --symbols FILE   read labels from a ca65 .dbg, VICE or `name = $addr` file
+  -m, --monitor        start the monitor instead of running
+  -u, --tui            start the full-screen debugger instead of running
+                       (unix terminals only)
+
+ADDR is decimal, $hex, 0xhex or a symbol name. ARGS are passed to sim65
+programs. The exit status is the program's own for sim65 and mos-sim, 0
+at the exit address and 2 for any other stop.";
+
+#[derive(Default)]
+struct Options {
+    format: Option<String>,
+    load: Option<String>,
+    reset: Option<String>,
+    start: Option<String>,
+    exit: Option<String>,
+    brk: bool,
+    cycles: Option<u64>,
+    trace: bool,
+    symbols: Option<String>,
+    monitor: bool,
+    tui: bool,
+    file: Option<String>,
+    args: Vec<String>,
+}
+
+// A loaded program, ready to run.
+struct Session {
+    machine: Machine,
+    symbols: SymbolTable,
+    debug_info: Option<DebugInfo>,
+    stops: Stops,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("m6502: {err}\n\n{USAGE}");
+            return ExitCode::from(1);
+        }
+    };
+    match run(options) {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("m6502: {err}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+// None if only help was asked for.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "-f" | "--format" => options.format = Some(value()?),
+            "-l" | "--load" => options.load = Some(value()?),
+            "-r" | "--reset" => options.reset = Some(value()?),
+            "-s" | "--start" => options.start = Some(value()?),
+            "-e" | "--exit" => options.exit = Some(value()?),
+            "-b" | "--brk" => options.brk = true,
+            "-c" | "--cycles" => {
+                let cycles = value()?;
+                options.cycles = Some(cycles.parse().map_err(|_| format!("bad count {cycles}"))?);
+            }
+            "-t" | "--trace" => options.trace = true,
+            "-y" | "--symbols" => options.symbols = Some(value()?),
+            "-m" | "--monitor" => options.monitor = true,
+            "-u" | "--tui" => options.tui = true,
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option {arg}"));
+            }
+            _ => {
+                options.file = Some(arg);
+                options.args = args.collect();
+                return Ok(Some(options));
+            }
+        }
+    }
+    if !options.monitor && !options.tui {
+        return Err("no program given".to_string());
+    }
+    Ok(Some(options))
+}
+
+fn run(options: Options) -> Result<u8, Box<dyn Error>> {
+    let session = load(&options)?;
+    if options.monitor {
+        Monitor::new(session).run(io::stdin().lock(), io::stdout())?;
+        return Ok(0);
+    }
+    #[cfg(unix)]
+    if options.tui {
+        Tui::new(session).run()?;
+        return Ok(0);
+    }
+    #[cfg(not(unix))]
+    if options.tui {
+        return Err("the full-screen debugger needs a unix terminal".into());
+    }
+
+    let Session {
+        mut machine,
+        symbols,
+        debug_info,
+        stops,
+    } = session;
+
+    let stop = machine.run(&stops, |_, retired| {
+        if options.trace {
+            eprintln!("{}", trace(retired, &symbols, debug_info.as_ref()));
+        }
+    });
+    let code = match stop {
+        Stop::Exit(code) => code,
+        Stop::ExitAddress(_) => 0,
+        _ => 2,
+    };
+    eprintln!("{}", stop_reason(stop, &symbols));
+    eprintln!("{}", registers(machine.core()));
+    eprintln!("cycles: {}", machine.cycles);
+    Ok(code)
+}
+
+fn load(options: &Options) -> Result<Session, Box<dyn Error>> {
+    let (path, file) = match &options.file {
+        Some(path) => (
+            path.as_str(),
+            fs::read(path).map_err(|err| format!("{path}: {err}"))?,
+        ),
+        None => ("", Vec::new()),
+    };
+    let format = options
+        .format
+        .clone()
+        .unwrap_or_else(|| detect(path, &file).to_string());
+    let text = || std::str::from_utf8(&file).map_err(|_| "file is not text");
+
+    let mut symbols = SymbolTable::new();
+    let mut debug_info = None;
+    if let Some(path) = &options.symbols {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        if path.ends_with(".dbg") {
+            let info = DebugInfo::parse_dbg(&text)?;
+            symbols.extend(info.symbols.iter().cloned());
+            debug_info = Some(info);
+        } else if text.trim_start().starts_with("al ") {
+            symbols.extend(symbols::parse_vice(&text)?.iter().cloned());
+        } else {
+            symbols.extend(symbols::parse_labels(&text)?.iter().cloned());
+        }
+    }
+
+    let mut image = Image::default();
+    let mut cpu = None;
+    let (mut mem, system): (Box<dyn Memory>, System) = match format.as_str() {
+        "sim65" => {
+            let (header, _) = Header::parse(&file).ok_or("not a sim65 version 2 image")?;
+            if header.cpu != Header::CPU_6502 {
+                return Err("sim65 image is not for the 6502".into());
+            }
+            let (sim, sim_cpu, ram) = Sim65::load(&file).ok_or("sim65 image runs past $FFFF")?;
+            let args = [path.to_string()].into_iter().chain(options.args.clone());
+            cpu = Some(sim_cpu);
+            (ram, System::Sim65(sim.with_args(args)))
+        }
+        "mos-sim" => {
+            let mut sim = MosSim::new();
+            sim.load(&file).ok_or("mos-sim image is truncated")?;
+            let sim = Rc::new(RefCell::new(sim));
+            (Box::new(sim.clone()), System::MosSim(sim))
+        }
+        "nes" => {
+            let (map, _) = Rom::parse(&file)?.memory_map()?;
+            (Box::new(map.build()), System::Plain)
+        }
+        format => {
+            image = match format {
+                "raw" => {
+                    let load = address(options.load.as_deref().unwrap_or("0"), &symbols)?;
+                    Image::raw(&file, load)?
+                }
+                "prg" => Image::prg(&file)?,
+                "hex" => Image::intel_hex(text()?)?,
+                "srec" => Image::srec(text()?)?,
+                "elf" => {
+                    let elf = Elf::parse(&file)?;
+                    symbols.extend(elf.symbols.iter().cloned());
+                    elf.image
+                }
+                "o65" => {
+                    let o65 = O65::parse(&file)?;
+                    let relocated = o65.relocate(o65.placement, &HashMap::new())?;
+                    symbols.extend(relocated.exports.into_iter().map(|(name, addr)| Symbol {
+                        name,
+                        addr,
+                        size: 0,
+                        kind: SymbolKind::Label,
+                    }));
+                    Image {
+                        start: Some(o65.placement.text),
+                        ..relocated.image
+                    }
+                }
+                _ => return Err(format!("unknown format {format}").into()),
+            };
+            (Box::new([0u8; 0x10000]), System::Plain)
+        }
+    };
+
+    image.install(&mut mem);
+    if let Some(reset) = &options.reset {
+        let [lo, hi] = address(reset, &symbols)?.to_le_bytes();
+        mem.poke(0xFFFC, lo);
+        mem.poke(0xFFFD, hi);
+    }
+    let start = options.start.as_deref().map(|s| address(s, &symbols));
+    let cpu = match start.transpose()?.or(image.start) {
+        Some(pc) => M6502::new(Core {
+            a: 0,
+            p: P::new().with_i(true),
+            pc,
+            s: 0xFD,
+            x: 0,
+            y: 0,
+        }),
+        None => cpu.unwrap_or_else(M6502::start),
+    };
+    let stops = Stops {
+        exit: options
+            .exit
+            .as_deref()
+            .map(|s| address(s, &symbols))
+            .transpose()?,
+        brk: options.brk,
+        limit: options.cycles,
+    };
+
+    Ok(Session {
+        machine: Machine::new(cpu, mem, system),
+        symbols,
+        debug_info,
+        stops,
+    })
+}
+
+fn detect(path: &str, file: &[u8]) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    if file.starts_with(b"NES\x1A") {
+        "nes"
+    } else if file.starts_with(b"\x7FELF") {
+        "elf"
+    } else if file.starts_with(b"sim65") {
+        "sim65"
+    } else if file.starts_with(b"\x01\x00o65") {
+        "o65"
+    } else {
+        match extension.as_deref() {
+            Some("prg") => "prg",
+            Some("hex" | "ihx" | "ihex") => "hex",
+            Some("srec" | "s19" | "s28" | "s37" | "mot") => "srec",
+            _ => "raw",
+        }
+    }
+}
+
+fn stop_reason(stop: Stop, symbols: &SymbolTable) -> String {
+    match stop {
+        Stop::Exit(code) => format!("exit with status {code}"),
+        Stop::ExitAddress(pc) => format!("reached exit address {}", at(pc, symbols)),
+        Stop::Jam(pc) => format!("jammed at {}", at(pc, symbols)),
+        Stop::Brk(pc) => format!("BRK at {}", at(pc, symbols)),
+        Stop::TrapLoop(pc) => format!("trapped in a loop at {}", at(pc, symbols)),
+        Stop::Limit => "cycle limit reached".to_string(),
+    }
+}
+fn at(addr: u16, symbols: &SymbolTable) -> String {
+    match symbols.describe(addr) {
+        Some(name) => format!("${addr:04X} ({name})"),
+        None => format!("${addr:04X}"),
+    }
+}
+
+fn address(text: &str, symbols: &SymbolTable) -> Result<u16, String> {
+    let value = if let Some(hex) = text.strip_prefix('$') {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16).ok()
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        symbols.get(text).map(|symbol| symbol.addr)
+    };
+    value.ok_or(format!("bad address {text}"))
+}
+
+fn registers(core: Core) -> String {
+    let flags: String = "NV-BDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, name)| match core.p.0 & 0x80 >> i {
+            0 => name.to_ascii_lowercase(),
+            _ if name == '-' => '-',
+            _ => name,
+        })
+        .collect();
+    format!(
+        "PC={:04X} A={:02X} X={:02X} Y={:02X} S={:02X} P={:02X} {flags}",
+        core.pc, core.a, core.x, core.y, core.s, core.p.0
+    )
+}
+
+fn trace(retired: &Retired, symbols: &SymbolTable, debug_info: Option<&DebugInfo>) -> String {
+    let instruction = Instruction::decode(&retired.bytes);
+    let bytes: Vec<_> = retired.bytes.iter().map(|b| format!("{b:02X}")).collect();
+    let core = retired.core;
+    let mut line = format!(
+        "{:04X}  {:<8}  {:<16}  A={:02X} X={:02X} Y={:02X} S={:02X} P={:02X}  {}",
+        retired.pc,
+        bytes.join(" "),
+        instruction.format(retired.pc, Some(symbols)),
+        core.a,
+        core.x,
+        core.y,
+        core.s,
+        core.p.0,
+        retired.cycles,
+    );
+    if let Some(source) = debug_info.and_then(|info| info.line_at(retired.pc)) {
+        line += &format!("  ; {}:{}", source.file, source.line);
+    }
+    if let Some((symbol, 0)) = symbols.lookup(retired.pc) {
+        line = format!("{}:\n{line}", symbol.name);
+    }
+    line
+}
@@ -0,0 +1,519 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use m6502::{asm, core::P, disasm::Instruction, loader::Image, memory::Memory};
+
+use crate::{Session, at, registers, stop_reason, trace};
+
+const HELP: &str = "commands (numbers are hex, .name is a symbol):
+  r [REG=VALUE...]           show or set A, X, Y, S, P and PC
+  m [START [END]]            dump memory
+  d [START [END]]            disassemble
+  a ADDR [INSTRUCTION]       assemble one line, or lines until a blank one
+  g [ADDR]                   run until a breakpoint, watchpoint or stop
+  z [COUNT]                  step instructions
+  break [ADDR]               list checkpoints, or break at ADDR
+  watch [load|store] START [END]
+                             stop when the CPU accesses memory
+  delete [N]                 remove checkpoint N, or all of them
+  l FILE [ADDR]              load a file, raw at ADDR or else as a PRG
+  s FILE START END           save memory
+  cycles                     show cycles run, and since last asked
+  x                          quit";
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+enum Checkpoint {
+    Break(u16),
+    Watch {
+        start: u16,
+        end: u16,
+        load: bool,
+        store: bool,
+    },
+}
+
+// A VICE-style monitor over a loaded program. Everything it prints goes to
+// one writer so that it can be scripted through a pipe.
+pub struct Monitor {
+    session: Session,
+    checkpoints: Vec<Checkpoint>,
+    dump_at: u16,
+    disassemble_at: u16,
+    marked_cycles: u64,
+}
+impl Monitor {
+    pub fn new(session: Session) -> Self {
+        let pc = session.machine.pc();
+        let cycles = session.machine.cycles;
+        Self {
+            session,
+            checkpoints: Vec::new(),
+            dump_at: pc,
+            disassemble_at: pc,
+            marked_cycles: cycles,
+        }
+    }
+
+    pub fn run(mut self, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        self.show_next(&mut out)?;
+        loop {
+            write!(out, "({:04X}) ", self.session.machine.pc())?;
+            out.flush()?;
+            let Some(line) = lines.next() else {
+                writeln!(out)?;
+                return Ok(());
+            };
+            match self.command(&line?, &mut lines, &mut out) {
+                Ok(true) => return Ok(()),
+                Ok(false) => (),
+                Err(err) => writeln!(out, "? {err}")?,
+            }
+        }
+    }
+
+    // Returns whether to quit.
+    fn command(
+        &mut self,
+        line: &str,
+        lines: &mut impl Iterator<Item = io::Result<String>>,
+        out: &mut impl Write,
+    ) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Ok(false);
+        };
+        let args: Vec<&str> = words.collect();
+        match name {
+            "r" => self.registers(&args, out)?,
+            "m" => self.dump(&args, out)?,
+            "d" => self.disassemble(&args, out)?,
+            "a" => self.assemble(line, lines, out)?,
+            "g" => self.go(&args, out)?,
+            "z" => self.step(&args, out)?,
+            "break" => self.add_break(&args, out)?,
+            "watch" => self.add_watch(&args)?,
+            "delete" => self.delete(&args)?,
+            "l" => self.load(&args, out)?,
+            "s" => self.save(&args, out)?,
+            "cycles" => {
+                let cycles = self.session.machine.cycles;
+                let delta = cycles - self.marked_cycles;
+                writeln!(out, "cycles: {cycles} (+{delta})")?;
+                self.marked_cycles = cycles;
+            }
+            "x" | "q" => return Ok(true),
+            "?" | "help" => writeln!(out, "{HELP}")?,
+            _ => return Err(format!("unknown command {name}, try help").into()),
+        }
+        Ok(false)
+    }
+
+    fn registers(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        let machine = &mut self.session.machine;
+        if args.is_empty() {
+            writeln!(out, "{}", registers(machine.core()))?;
+            return Ok(());
+        }
+        let mut core = machine.core();
+        for arg in args {
+            let (name, value) = arg.split_once('=').ok_or("expected REG=VALUE")?;
+            let value = self.value(value)?;
+            let byte = || u8::try_from(value).map_err(|_| format!("{value:X} is not a byte"));
+            match name.to_ascii_lowercase().as_str() {
+                "a" => core.a = byte()?,
+                "x" => core.x = byte()?,
+                "y" => core.y = byte()?,
+                "s" | "sp" => core.s = byte()?,
+                "p" => core.p = P(byte()?),
+                "pc" => core.pc = value,
+                _ => return Err(format!("no register {name}").into()),
+            }
+        }
+        self.session.machine.set_core(core);
+        self.disassemble_at = core.pc;
+        writeln!(out, "{}", registers(core))?;
+        Ok(())
+    }
+
+    fn dump(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        let (start, end) = self.range(args, self.dump_at, 0x7F)?;
+        let mut addr = start;
+        loop {
+            let row: Vec<_> = (0..16)
+                .map(|i| self.session.machine.mem.peek(addr.wrapping_add(i)))
+                .collect();
+            let hex: Vec<_> = row
+                .iter()
+                .map(|b| b.map_or("--".to_string(), |b| format!("{b:02X}")))
+                .collect();
+            let text: String = row
+                .iter()
+                .map(|b| match b {
+                    Some(b @ 0x20..=0x7E) => *b as char,
+                    _ => '.',
+                })
+                .collect();
+            writeln!(out, "{addr:04X}  {}  {text}", hex.join(" "))?;
+
+            let next = addr.wrapping_add(16);
+            if end.wrapping_sub(addr) < 16 {
+                self.dump_at = next;
+                return Ok(());
+            }
+            addr = next;
+        }
+    }
+
+    fn disassemble(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        let start = match args.first() {
+            Some(arg) => self.value(arg)?,
+            None => self.disassemble_at,
+        };
+        let end = args.get(1).map(|arg| self.value(arg)).transpose()?;
+        let mut addr = start;
+        for count in 0.. {
+            match end {
+                Some(end) if addr.wrapping_sub(start) > end.wrapping_sub(start) => break,
+                None if count == 16 => break,
+                _ => (),
+            }
+            let (line, size) = self.line_at(addr);
+            writeln!(out, "{line}")?;
+            addr = addr.wrapping_add(size);
+        }
+        self.disassemble_at = addr;
+        Ok(())
+    }
+
+    fn assemble(
+        &mut self,
+        line: &str,
+        lines: &mut impl Iterator<Item = io::Result<String>>,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        let rest = line.trim_start()[1..].trim_start();
+        let (addr, text) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if addr.is_empty() {
+            return Err("a needs an address".into());
+        }
+        let mut addr = self.value(addr)?;
+        if !text.trim().is_empty() {
+            self.assemble_at(addr, text, out)?;
+            return Ok(());
+        }
+        // Lines until a blank one, each placed after the last.
+        loop {
+            write!(out, "{addr:04X}: ")?;
+            out.flush()?;
+            let Some(text) = lines.next().transpose()? else {
+                return Ok(());
+            };
+            if text.trim().is_empty() {
+                return Ok(());
+            }
+            match self.assemble_at(addr, &text, out) {
+                Ok(next) => addr = next,
+                Err(err) => writeln!(out, "? {err}")?,
+            }
+        }
+    }
+    fn assemble_at(&mut self, addr: u16, text: &str, out: &mut impl Write) -> Result<u16> {
+        let bytes = asm::assemble(&to_asm(text), addr, Some(&self.session.symbols))?;
+        self.write(addr, &bytes);
+        let (line, size) = self.line_at(addr);
+        writeln!(out, "{line}")?;
+        Ok(addr.wrapping_add(size))
+    }
+
+    fn go(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        if let Some(addr) = args.first() {
+            let mut core = self.session.machine.core();
+            core.pc = self.value(addr)?;
+            self.session.machine.set_core(core);
+        }
+        let reason = loop {
+            if self.session.machine.jammed() {
+                break format!("jammed at {}", self.at(self.session.machine.pc()));
+            }
+            self.session.machine.step();
+            if let Some(reason) = self.checkpoint_hit() {
+                break reason;
+            }
+            if let Some(stop) = self.session.machine.check(&self.session.stops) {
+                break stop_reason(stop, &self.session.symbols);
+            }
+        };
+        writeln!(out, "{reason}")?;
+        writeln!(out, "{}", registers(self.session.machine.core()))?;
+        self.show_next(out)?;
+        Ok(())
+    }
+
+    fn step(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        let count = match args.first() {
+            Some(arg) => self.value(arg)?,
+            None => 1,
+        };
+        for _ in 0..count {
+            let machine = &mut self.session.machine;
+            if machine.jammed() || machine.exit_code().is_some() {
+                break;
+            }
+            let retired = machine.step();
+            let symbols = &self.session.symbols;
+            writeln!(
+                out,
+                "{}",
+                trace(&retired, symbols, self.session.debug_info.as_ref())
+            )?;
+        }
+        self.show_next(out)?;
+        Ok(())
+    }
+
+    fn add_break(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        if let Some(addr) = args.first() {
+            let addr = self.value(addr)?;
+            self.checkpoints.push(Checkpoint::Break(addr));
+            return Ok(());
+        }
+        for (i, checkpoint) in self.checkpoints.iter().enumerate() {
+            let text = match *checkpoint {
+                Checkpoint::Break(addr) => format!("break {}", self.at(addr)),
+                Checkpoint::Watch {
+                    start,
+                    end,
+                    load,
+                    store,
+                } => {
+                    let kind = match (load, store) {
+                        (true, false) => "load ",
+                        (false, true) => "store ",
+                        _ => "",
+                    };
+                    format!("watch {kind}{} to {}", self.at(start), self.at(end))
+                }
+            };
+            writeln!(out, "{}: {text}", i + 1)?;
+        }
+        Ok(())
+    }
+    fn add_watch(&mut self, args: &[&str]) -> Result<()> {
+        let (load, store, args) = match args.first() {
+            Some(&"load") => (true, false, &args[1..]),
+            Some(&"store") => (false, true, &args[1..]),
+            _ => (true, true, args),
+        };
+        let start = self.value(args.first().ok_or("watch needs an address")?)?;
+        let end = args.get(1).map(|arg| self.value(arg)).transpose()?;
+        self.checkpoints.push(Checkpoint::Watch {
+            start,
+            end: end.unwrap_or(start),
+            load,
+            store,
+        });
+        Ok(())
+    }
+    fn delete(&mut self, args: &[&str]) -> Result<()> {
+        let Some(n) = args.first() else {
+            self.checkpoints.clear();
+            return Ok(());
+        };
+        let n: usize = n
+            .parse()
+            .map_err(|_| format!("bad checkpoint number {n}"))?;
+        if n == 0 || n > self.checkpoints.len() {
+            return Err(format!("no checkpoint {n}").into());
+        }
+        self.checkpoints.remove(n - 1);
+        Ok(())
+    }
+    // Breakpoints at the fetched instruction, or watchpoints on the cycles
+    // of the one just run.
+    fn checkpoint_hit(&self) -> Option<String> {
+        let machine = &self.session.machine;
+        for checkpoint in &self.checkpoints {
+            match *checkpoint {
+                Checkpoint::Break(addr) if addr == machine.pc() => {
+                    return Some(format!("break at {}", self.at(addr)));
+                }
+                Checkpoint::Watch {
+                    start,
+                    end,
+                    load,
+                    store,
+                } => {
+                    let hit = machine.last_cycles()[1..].iter().find(|bus| {
+                        (start..=end).contains(&bus.addr) && if bus.rw() { load } else { store }
+                    });
+                    if let Some(bus) = hit {
+                        let kind = if bus.rw() { "load" } else { "store" };
+                        return Some(format!("{kind} {} = ${:02X}", self.at(bus.addr), bus.data));
+                    }
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    fn load(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        let path = unquote(args.first().ok_or("l needs a file name")?);
+        let file = fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+        let image = match args.get(1) {
+            Some(addr) => Image::raw(&file, self.value(addr)?)?,
+            None => Image::prg(&file)?,
+        };
+        for segment in &image.segments {
+            self.write(segment.addr, &segment.data);
+            if segment.data.is_empty() {
+                writeln!(out, "loaded nothing at ${:04X}", segment.addr)?;
+                continue;
+            }
+            let end = segment.addr as usize + segment.data.len() - 1;
+            writeln!(out, "loaded ${:04X} to ${end:04X}", segment.addr)?;
+        }
+        Ok(())
+    }
+    fn save(&mut self, args: &[&str], out: &mut impl Write) -> Result<()> {
+        let [path, start, end] = args else {
+            return Err("s needs a file name, start and end".into());
+        };
+        let (path, start, end) = (unquote(path), self.value(start)?, self.value(end)?);
+        if end < start {
+            return Err("end is before start".into());
+        }
+        let mem = &mut self.session.machine.mem;
+        let data: Vec<u8> = (start..=end)
+            .map(|addr| mem.peek(addr).unwrap_or(0))
+            .collect();
+        fs::write(path, &data).map_err(|err| format!("{path}: {err}"))?;
+        writeln!(out, "saved {} bytes", data.len())?;
+        Ok(())
+    }
+
+    // Stores bytes and, if they cover the fetched opcode, fetches it again.
+    fn write(&mut self, addr: u16, data: &[u8]) {
+        let machine = &mut self.session.machine;
+        for (i, &byte) in data.iter().enumerate() {
+            machine.mem.poke(addr.wrapping_add(i as u16), byte);
+        }
+        if (machine.pc().wrapping_sub(addr) as usize) < data.len() {
+            machine.set_core(machine.core());
+        }
+    }
+
+    fn show_next(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let pc = self.session.machine.pc();
+        let (line, size) = self.line_at(pc);
+        self.disassemble_at = pc.wrapping_add(size);
+        writeln!(out, "{line}")
+    }
+    // One line of disassembly, marked if it is at the PC, and its size.
+    fn line_at(&mut self, addr: u16) -> (String, u16) {
+        let mem = &mut self.session.machine.mem;
+        let bytes: Vec<u8> = (0..3)
+            .map(|i| mem.peek(addr.wrapping_add(i)).unwrap_or(0))
+            .collect();
+        let instruction = Instruction::decode(&bytes);
+        let size = instruction.size();
+        let hex: Vec<_> = bytes[..size as usize]
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        let mark = if addr == self.session.machine.pc() {
+            '>'
+        } else {
+            ' '
+        };
+        let symbols = &self.session.symbols;
+        let mut line = format!(
+            "{mark}{addr:04X}  {:<8}  {}",
+            hex.join(" "),
+            instruction.format(addr, Some(symbols))
+        );
+        if let Some((symbol, 0)) = symbols.lookup(addr) {
+            line = format!(" {}:\n{line}", symbol.name);
+        }
+        if let Some(info) = &self.session.debug_info
+            && let Some(source) = info.line_at(addr)
+        {
+            line += &format!("  ; {}:{}", source.file, source.line);
+        }
+        (line, size)
+    }
+
+    fn range(&self, args: &[&str], default: u16, len: u16) -> Result<(u16, u16)> {
+        let start = match args.first() {
+            Some(arg) => self.value(arg)?,
+            None => default,
+        };
+        let end = match args.get(1) {
+            Some(arg) => self.value(arg)?,
+            None => start.saturating_add(len),
+        };
+        Ok((start, end))
+    }
+    fn value(&self, text: &str) -> Result<u16> {
+        let symbols = &self.session.symbols;
+        let symbol = |name: &str| {
+            symbols
+                .get(name)
+                .map(|symbol| symbol.addr)
+                .ok_or_else(|| format!("no symbol {name}").into())
+        };
+        if let Some(name) = text.strip_prefix('.') {
+            return symbol(name);
+        }
+        let hex = text.strip_prefix('$').unwrap_or(text);
+        match u16::from_str_radix(hex, 16) {
+            Ok(value) => Ok(value),
+            Err(_) if symbols.get(text).is_some() => symbol(text),
+            Err(_) => Err(format!("bad number {text}").into()),
+        }
+    }
+    fn at(&self, addr: u16) -> String {
+        at(addr, &self.session.symbols)
+    }
+}
+
+// The assembler takes symbols by their bare names and reads bare numbers as
+// decimal, so numbers in the operand get a `$` as everywhere else in the
+// monitor. A lone `a` is still the accumulator.
+fn to_asm(text: &str) -> String {
+    let text = text.trim();
+    let (mnemonic, operand) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    if operand.trim().eq_ignore_ascii_case("a") {
+        return text.to_string();
+    }
+    let mut out = format!("{mnemonic} ");
+    let mut chars = operand.chars().peekable();
+    let mut prev = ' ';
+    while let Some(c) = chars.next() {
+        if !(c.is_alphanumeric() || c == '_') {
+            if c != '.' {
+                out.push(c);
+            }
+            prev = c;
+            continue;
+        }
+        let mut word = String::from(c);
+        while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_') {
+            word.push(c);
+        }
+        if !matches!(prev, '.' | '$' | '%') && word.chars().all(|c| c.is_ascii_hexdigit()) {
+            out.push('$');
+        }
+        out.push_str(&word);
+        prev = ' ';
+    }
+    out
+}
+fn unquote(text: &str) -> &str {
+    text.trim_matches('"')
+}
@@ -0,0 +1,538 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use m6502::{core::Core, disasm::Instruction, memory::Memory};
+
+use crate::{
+    Session, address, at,
+    machine::{Stop, Stops},
+    stop_reason,
+};
+
+const HELP: &str = "s step  o step over  g run  b break  m memory  j/k/PgUp/PgDn scroll  q quit";
+
+// Written addresses that stay highlighted, instructions shown above the
+// PC, and the instructions run between looks at the keyboard.
+const RECENT_WRITES: usize = 32;
+const HISTORY: usize = 4;
+const KEY_POLL: u32 = 1000;
+
+const MEMORY_ROWS: usize = 8;
+const DISASSEMBLY_WIDTH: usize = 38;
+const REGISTERS_X: usize = 39;
+const REGISTERS_WIDTH: usize = 25;
+const BUS_X: usize = 65;
+
+const JSR: u8 = 0x20;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Style {
+    Plain,
+    Title,
+    Current,
+    Changed,
+    Dim,
+    Mark,
+}
+impl Style {
+    fn sgr(self) -> &'static str {
+        match self {
+            Self::Plain => "\x1b[0m",
+            Self::Title => "\x1b[0;7m",
+            Self::Current => "\x1b[0;30;46m",
+            Self::Changed => "\x1b[0;1;33m",
+            Self::Dim => "\x1b[0;2m",
+            Self::Mark => "\x1b[0;1;31m",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
+// A frame of text drawn off screen and sent in one go, clipped at the edges.
+struct Screen {
+    width: usize,
+    height: usize,
+    cells: Vec<(char, Style)>,
+}
+impl Screen {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![(' ', Style::Plain); width * height],
+        }
+    }
+
+    // Returns the column after the text.
+    fn put(&mut self, x: usize, y: usize, text: &str, style: Style) -> usize {
+        let mut end = x;
+        for c in text.chars() {
+            if y < self.height && end < self.width {
+                self.cells[y * self.width + end] = (c, style);
+            }
+            end += 1;
+        }
+        end
+    }
+    fn title(&mut self, x: usize, y: usize, width: usize, text: &str) {
+        self.put(x, y, &format!(" {text:<width$}"), Style::Title);
+    }
+
+    fn render(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut text = String::from("\x1b[H");
+        for (y, row) in self.cells.chunks(self.width.max(1)).enumerate() {
+            let mut style = None;
+            for &(c, cell_style) in row {
+                if style != Some(cell_style) {
+                    text += cell_style.sgr();
+                    style = Some(cell_style);
+                }
+                text.push(c);
+            }
+            text += Style::Plain.sgr();
+            if y + 1 < self.height {
+                text += "\r\n";
+            }
+        }
+        out.write_all(text.as_bytes())?;
+        out.flush()
+    }
+}
+
+// The terminal in raw mode on the alternate screen for as long as this
+// lives. `stty` does the switching so that no terminal library is needed.
+struct Terminal {
+    saved: String,
+}
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        io::stdout().flush()?;
+        Ok(Self { saved })
+    }
+    // Columns and rows.
+    fn size(&self) -> (usize, usize) {
+        stty(&["size"])
+            .ok()
+            .and_then(|size| {
+                let (rows, columns) = size.trim().split_once(' ')?;
+                Some((columns.parse().ok()?, rows.parse().ok()?))
+            })
+            .unwrap_or((80, 24))
+    }
+}
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("{}\x1b[?25h\x1b[?1049l", Style::Plain.sgr());
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("the debugger needs a terminal"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Blocks for the next key, or None once input has ended. Escape sequences
+// for the arrow and page keys are told apart from a lone Escape by how
+// quickly the rest of them follows.
+fn key(keys: &Receiver<u8>) -> Option<Key> {
+    let next = || keys.recv_timeout(Duration::from_millis(20)).ok();
+    Some(match keys.recv().ok()? {
+        0x1B => match (next(), next()) {
+            (Some(b'['), Some(b'A')) => Key::Up,
+            (Some(b'['), Some(b'B')) => Key::Down,
+            (Some(b'['), Some(b'5')) if next() == Some(b'~') => Key::PageUp,
+            (Some(b'['), Some(b'6')) if next() == Some(b'~') => Key::PageDown,
+            _ => Key::Escape,
+        },
+        b'\r' | b'\n' => Key::Enter,
+        0x08 | 0x7F => Key::Backspace,
+        byte => Key::Char(byte as char),
+    })
+}
+
+// A full-screen debugger over a loaded program, driven from the keyboard.
+pub struct Tui {
+    session: Session,
+    breakpoints: BTreeSet<u16>,
+    memory_at: u16,
+    writes: VecDeque<u16>,
+    history: VecDeque<u16>,
+    previous: Core,
+    status: String,
+}
+impl Tui {
+    pub fn new(session: Session) -> Self {
+        let previous = session.machine.core();
+        Self {
+            session,
+            breakpoints: BTreeSet::new(),
+            memory_at: 0,
+            writes: VecDeque::new(),
+            history: VecDeque::new(),
+            previous,
+            status: String::new(),
+        }
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        let terminal = Terminal::enter()?;
+        let (send, keys) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in io::stdin().lock().bytes() {
+                if byte.ok().is_none_or(|byte| send.send(byte).is_err()) {
+                    break;
+                }
+            }
+        });
+        loop {
+            self.draw(&terminal, HELP)?;
+            match key(&keys) {
+                None | Some(Key::Char('q' | '\x03')) => return Ok(()),
+                Some(Key::Char('s' | ' ')) => {
+                    self.status = self.step().err().unwrap_or_default();
+                }
+                Some(Key::Char('o')) => {
+                    let machine = &self.session.machine;
+                    if machine.bus.data == JSR {
+                        self.go(Some(machine.pc().wrapping_add(3)), &terminal, &keys)?;
+                    } else {
+                        self.status = self.step().err().unwrap_or_default();
+                    }
+                }
+                Some(Key::Char('g')) => self.go(None, &terminal, &keys)?,
+                Some(Key::Char('b')) => {
+                    if let Some(addr) = self.ask("break at (blank for PC): ", &terminal, &keys)?
+                        && !self.breakpoints.remove(&addr)
+                    {
+                        self.breakpoints.insert(addr);
+                    }
+                }
+                Some(Key::Char('m')) => {
+                    if let Some(addr) = self.ask("memory at (blank for PC): ", &terminal, &keys)? {
+                        self.memory_at = addr & !0xF;
+                    }
+                }
+                Some(Key::Up | Key::Char('k')) => self.scroll(-0x10),
+                Some(Key::Down | Key::Char('j')) => self.scroll(0x10),
+                Some(Key::PageUp) => self.scroll(-0x80),
+                Some(Key::PageDown) => self.scroll(0x80),
+                Some(_) => (),
+            }
+        }
+    }
+
+    // Runs one instruction, remembering what the panes highlight, or says
+    // why it cannot.
+    fn step(&mut self) -> Result<(), String> {
+        let machine = &mut self.session.machine;
+        if let Some(stop @ (Stop::Exit(_) | Stop::Jam(_))) = machine.check(&Stops::default()) {
+            return Err(stop_reason(stop, &self.session.symbols));
+        }
+        self.previous = machine.core();
+        let retired = machine.step();
+        self.history.push_back(retired.pc);
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+        for bus in machine.last_cycles().iter().filter(|bus| !bus.rw()) {
+            self.writes.retain(|&addr| addr != bus.addr);
+            self.writes.push_back(bus.addr);
+        }
+        while self.writes.len() > RECENT_WRITES {
+            self.writes.pop_front();
+        }
+        Ok(())
+    }
+
+    // Runs until a breakpoint, a stop, `until` or a key press.
+    fn go(
+        &mut self,
+        until: Option<u16>,
+        terminal: &Terminal,
+        keys: &Receiver<u8>,
+    ) -> io::Result<()> {
+        self.draw(terminal, "running, press any key to stop")?;
+        let mut count = 0;
+        self.status = loop {
+            if let Err(reason) = self.step() {
+                break reason;
+            }
+            let machine = &self.session.machine;
+            let pc = machine.pc();
+            if until == Some(pc) {
+                break String::new();
+            }
+            if self.breakpoints.contains(&pc) {
+                break format!("breakpoint at {}", at(pc, &self.session.symbols));
+            }
+            if let Some(stop) = machine.check(&self.session.stops) {
+                break stop_reason(stop, &self.session.symbols);
+            }
+            count += 1;
+            if count % KEY_POLL == 0 && keys.try_recv().is_ok() {
+                break "interrupted".to_string();
+            }
+        };
+        Ok(())
+    }
+
+    // Reads an address on the bottom line; None if Escape cancels it.
+    fn ask(
+        &mut self,
+        prompt: &str,
+        terminal: &Terminal,
+        keys: &Receiver<u8>,
+    ) -> io::Result<Option<u16>> {
+        let mut text = String::new();
+        loop {
+            self.draw(terminal, &format!("{prompt}{text}_"))?;
+            match key(keys) {
+                None | Some(Key::Escape) => return Ok(None),
+                Some(Key::Enter) => break,
+                Some(Key::Backspace) => {
+                    text.pop();
+                }
+                Some(Key::Char(c)) if c.is_ascii_graphic() => text.push(c),
+                Some(_) => (),
+            }
+        }
+        if text.is_empty() {
+            return Ok(Some(self.session.machine.pc()));
+        }
+        match address(&text, &self.session.symbols) {
+            Ok(addr) => Ok(Some(addr)),
+            Err(err) => {
+                self.status = err;
+                Ok(None)
+            }
+        }
+    }
+
+    fn scroll(&mut self, by: i16) {
+        self.memory_at = self.memory_at.wrapping_add_signed(by);
+    }
+
+    fn draw(&mut self, terminal: &Terminal, footer: &str) -> io::Result<()> {
+        let (width, height) = terminal.size();
+        let mut screen = Screen::new(width, height);
+        let machine = &self.session.machine;
+        let title = format!("m6502  {} cycles  {}", machine.cycles, self.status);
+        screen.title(0, 0, width, &title);
+        screen.put(0, height.saturating_sub(1), footer, Style::Dim);
+
+        let memory_y = height.saturating_sub(MEMORY_ROWS + 2);
+        self.draw_disassembly(&mut screen, 1, memory_y);
+        self.draw_registers(&mut screen, 1);
+        self.draw_stack(&mut screen, 7, memory_y);
+        self.draw_bus(&mut screen, width.saturating_sub(BUS_X), memory_y);
+        self.draw_memory(&mut screen, memory_y);
+        screen.render(&mut io::stdout())
+    }
+
+    // The last few instructions run, dimmed, then the code from the PC on.
+    fn draw_disassembly(&mut self, screen: &mut Screen, top: usize, bottom: usize) {
+        screen.title(0, top, DISASSEMBLY_WIDTH - 1, "Disassembly");
+        let pc = self.session.machine.pc();
+        let mut y = top + 1;
+        for addr in self.history.clone() {
+            let (line, _) = self.line_at(addr);
+            screen.put(1, y, &line, Style::Dim);
+            y += 1;
+        }
+        let mut addr = pc;
+        while y < bottom {
+            if let Some((symbol, 0)) = self.session.symbols.lookup(addr) {
+                screen.put(1, y, &format!("{}:", symbol.name), Style::Dim);
+                y += 1;
+            }
+            let (line, size) = self.line_at(addr);
+            let style = if addr == pc {
+                Style::Current
+            } else {
+                Style::Plain
+            };
+            screen.put(
+                1,
+                y,
+                &format!("{line:<width$}", width = DISASSEMBLY_WIDTH - 2),
+                style,
+            );
+            if self.breakpoints.contains(&addr) {
+                screen.put(0, y, "*", Style::Mark);
+            }
+            addr = addr.wrapping_add(size);
+            y += 1;
+        }
+    }
+    fn line_at(&mut self, addr: u16) -> (String, u16) {
+        let mem = &mut self.session.machine.mem;
+        let bytes: Vec<u8> = (0..3)
+            .map(|i| mem.peek(addr.wrapping_add(i)).unwrap_or(0))
+            .collect();
+        let instruction = Instruction::decode(&bytes);
+        let size = instruction.size();
+        let hex: Vec<_> = bytes[..size as usize]
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        let text = instruction.format(addr, Some(&self.session.symbols));
+        (format!("{addr:04X}  {:<8}  {text}", hex.join(" ")), size)
+    }
+
+    // Registers changed by the last step are highlighted, and P is shown a
+    // bit at a time.
+    fn draw_registers(&self, screen: &mut Screen, top: usize) {
+        screen.title(REGISTERS_X, top, REGISTERS_WIDTH - 1, "Registers");
+        let (core, previous) = (self.session.machine.core(), self.previous);
+        let changed = |changed: bool| {
+            if changed {
+                Style::Changed
+            } else {
+                Style::Plain
+            }
+        };
+        let mut x = screen.put(REGISTERS_X, top + 1, "PC ", Style::Plain);
+        x = screen.put(x, top + 1, &format!("{:04X}", core.pc), Style::Plain);
+        if let Some(name) = self.session.symbols.describe(core.pc) {
+            screen.put(x + 1, top + 1, &name, Style::Dim);
+        }
+        let mut x = REGISTERS_X;
+        for (name, value, before) in [
+            ("A", core.a, previous.a),
+            ("X", core.x, previous.x),
+            ("Y", core.y, previous.y),
+            ("S", core.s, previous.s),
+        ] {
+            x = screen.put(x, top + 2, &format!("{name} "), Style::Plain);
+            x = screen.put(
+                x,
+                top + 2,
+                &format!("{value:02X}"),
+                changed(value != before),
+            );
+            x += 2;
+        }
+        screen.put(REGISTERS_X, top + 3, "N V - B D I Z C   P", Style::Dim);
+        for bit in 0..8 {
+            let mask = 0x80 >> bit;
+            let value = (core.p.0 & mask != 0) as u8;
+            let style = changed((core.p.0 ^ previous.p.0) & mask != 0);
+            screen.put(REGISTERS_X + bit * 2, top + 4, &value.to_string(), style);
+        }
+        let style = changed(core.p.0 != previous.p.0);
+        screen.put(
+            REGISTERS_X + 18,
+            top + 4,
+            &format!("{:02X}", core.p.0),
+            style,
+        );
+    }
+
+    // The stack from the top down. A pair of bytes that a JSR could have
+    // pushed is shown with the address it returns to.
+    fn draw_stack(&mut self, screen: &mut Screen, top: usize, bottom: usize) {
+        screen.title(REGISTERS_X, top, REGISTERS_WIDTH - 1, "Stack");
+        let s = self.session.machine.core().s;
+        if s == 0xFF {
+            screen.put(REGISTERS_X, top + 1, "(empty)", Style::Dim);
+        }
+        let mem = &mut self.session.machine.mem;
+        for (y, addr) in (top + 1..bottom).zip(0x101 + s as u16..=0x1FF) {
+            let byte = mem.peek(addr);
+            let text = byte.map_or("--".to_string(), |byte| format!("{byte:02X}"));
+            screen.put(REGISTERS_X, y, &format!("{addr:04X} {text}"), Style::Plain);
+            if addr < 0x1FF
+                && let (Some(low), Some(high)) = (byte, mem.peek(addr + 1))
+            {
+                let pushed = u16::from_le_bytes([low, high]);
+                if mem.peek(pushed.wrapping_sub(2)) == Some(JSR) {
+                    let back = pushed.wrapping_add(1);
+                    let name = self
+                        .session
+                        .symbols
+                        .describe(back)
+                        .unwrap_or_else(|| format!("${back:04X}"));
+                    screen.put(REGISTERS_X + 9, y, &format!("ret {name}"), Style::Dim);
+                }
+            }
+        }
+    }
+
+    // Every cycle of the last instruction, then the fetch the CPU is
+    // stopped on.
+    fn draw_bus(&self, screen: &mut Screen, width: usize, bottom: usize) {
+        if width < 2 {
+            return;
+        }
+        screen.title(BUS_X, 1, width - 1, "Bus");
+        let machine = &self.session.machine;
+        let cycles = machine.last_cycles().iter().map(|&bus| (bus, Style::Plain));
+        for ((bus, style), y) in cycles.chain([(machine.bus, Style::Dim)]).zip(2..bottom) {
+            let (kind, style) = if bus.rw() {
+                ("R", style)
+            } else {
+                ("W", Style::Changed)
+            };
+            let sync = if bus.sync() { " sync" } else { "" };
+            let text = format!("{kind} {:04X} {:02X}{sync}", bus.addr, bus.data);
+            screen.put(BUS_X, y, &text, style);
+        }
+    }
+
+    // Hex and ASCII, with the addresses written lately highlighted.
+    fn draw_memory(&mut self, screen: &mut Screen, top: usize) {
+        screen.title(0, top, screen.width.saturating_sub(1), "Memory");
+        let mem = &mut self.session.machine.mem;
+        for row in 0..MEMORY_ROWS {
+            let y = top + 1 + row;
+            let start = self.memory_at.wrapping_add(row as u16 * 0x10);
+            let mut x = screen.put(0, y, &format!("{start:04X} "), Style::Plain);
+            let mut ascii = String::new();
+            for i in 0..0x10 {
+                let addr = start.wrapping_add(i);
+                let byte = mem.peek(addr);
+                let style = if self.writes.contains(&addr) {
+                    Style::Changed
+                } else {
+                    Style::Plain
+                };
+                let text = byte.map_or("--".to_string(), |byte| format!("{byte:02X}"));
+                x = screen.put(x + 1, y, &text, style);
+                ascii.push(match byte {
+                    Some(byte @ 0x20..=0x7E) => byte as char,
+                    _ => '.',
+                });
+            }
+            screen.put(x + 2, y, &ascii, Style::Dim);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+
+use crate::{
+    loader::LoadError,
+    symbols::{Symbol, SymbolKind, SymbolTable},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LineKind {
+    Assembly,
+    External,
+    Macro,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: u32,
+    pub kind: LineKind,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Range {
+    start: u16,
+    end: u16,
+}
+impl Range {
+    fn contains(self, addr: u16) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+    fn len(self) -> u16 {
+        self.end - self.start
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Line {
+    file: usize,
+    line: u32,
+    kind: LineKind,
+    ranges: Vec<Range>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Scope {
+    name: String,
+    ranges: Vec<Range>,
+}
+
+// Where code came from: symbols for every format, and for cc65 debug files
+// also source lines and scopes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub symbols: SymbolTable,
+    files: Vec<String>,
+    lines: Vec<Line>,
+    scopes: Vec<Scope>,
+    by_addr: HashMap<u16, Vec<usize>>,
+}
+impl DebugInfo {
+    pub fn new(symbols: SymbolTable) -> Self {
+        Self {
+            symbols,
+            ..Self::default()
+        }
+    }
+
+    // The file written by `ld65 --dbgfile`.
+    pub fn parse_dbg(text: &str) -> Result<Self, LoadError> {
+        let mut records: HashMap<&str, HashMap<usize, Record>> = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let syntax = LoadError::Syntax { line: i + 1 };
+            let (kind, fields) = line.split_once(char::is_whitespace).ok_or(syntax.clone())?;
+            let record = Record::parse(fields.trim()).ok_or(syntax.clone())?;
+            if let Some(id) = record.get("id") {
+                let id = parse_int(id).ok_or(syntax)? as usize;
+                records.entry(kind).or_default().insert(id, record);
+            }
+        }
+        let table = |kind| records.get(kind).cloned().unwrap_or_default();
+        let (segs, spans) = (table("seg"), table("span"));
+
+        // Span ids resolve to absolute address ranges through their segment.
+        let mut span_ranges = HashMap::new();
+        for (&id, span) in &spans {
+            let seg = segs.get(&span.int("seg")?).ok_or(LoadError::BadHeader)?;
+            let start = seg.int("start")? + span.int("start")?;
+            let size = span.int("size")?;
+            if size == 0 || start + size > 0x10000 {
+                continue;
+            }
+            let end = start + size - 1;
+            span_ranges.insert(
+                id,
+                Range {
+                    start: start as u16,
+                    end: end as u16,
+                },
+            );
+        }
+        let ranges = |record: &Record| -> Vec<Range> {
+            record
+                .list("span")
+                .filter_map(|id| span_ranges.get(&id).copied())
+                .collect()
+        };
+
+        let mut info = DebugInfo::default();
+        let mut file_index = HashMap::new();
+        let mut files: Vec<_> = table("file").into_iter().collect();
+        files.sort_by_key(|(id, _)| *id);
+        for (id, file) in files {
+            file_index.insert(id, info.files.len());
+            info.files.push(file.string("name")?);
+        }
+
+        for line in table("line").values() {
+            let file = *file_index
+                .get(&line.int("file")?)
+                .ok_or(LoadError::BadHeader)?;
+            let kind = match line.get("type").map(parse_int) {
+                None | Some(Some(0)) => LineKind::Assembly,
+                Some(Some(1)) => LineKind::External,
+                _ => LineKind::Macro,
+            };
+            let ranges = ranges(line);
+            if !ranges.is_empty() {
+                info.lines.push(Line {
+                    file,
+                    line: line.int("line")? as u32,
+                    kind,
+                    ranges,
+                });
+            }
+        }
+
+        for scope in table("scope").values() {
+            let name = scope.string("name")?;
+            let ranges = ranges(scope);
+            if !name.is_empty() && !ranges.is_empty() {
+                info.scopes.push(Scope { name, ranges });
+            }
+        }
+
+        // Names are qualified by their scopes, as in `proc::loop`, and
+        // cheap locals by the label they follow, as in `start@skip`.
+        let (scopes, syms) = (table("scope"), table("sym"));
+        let scope_path = |mut id: usize| {
+            let mut names = Vec::new();
+            for _ in 0..scopes.len() {
+                let Some(scope) = scopes.get(&id) else {
+                    break;
+                };
+                names.extend(scope.get("name").filter(|name| !name.is_empty()));
+                match scope.get("parent").and_then(parse_int) {
+                    Some(parent) => id = parent as usize,
+                    None => break,
+                }
+            }
+            names.reverse();
+            names.join("::")
+        };
+        let qualified = |sym: &Record| -> Result<String, LoadError> {
+            let name = sym.string("name")?;
+            let path = sym.get("scope").and_then(parse_int);
+            let path = path.map_or(String::new(), |id| scope_path(id as usize));
+            Ok(if path.is_empty() {
+                name
+            } else {
+                format!("{path}::{name}")
+            })
+        };
+
+        for sym in syms.values() {
+            let is_label = sym.get("type") == Some("lab");
+            let is_equate = sym.get("type") == Some("equ");
+            let Some(value) = sym.get("val").and_then(parse_int) else {
+                continue;
+            };
+            if !(is_label || is_equate) || value > 0xFFFF {
+                continue;
+            }
+            let name = match sym.get("parent").and_then(parse_int) {
+                Some(parent) => {
+                    let parent = syms.get(&(parent as usize)).ok_or(LoadError::BadHeader)?;
+                    qualified(parent)? + &sym.string("name")?
+                }
+                None => qualified(sym)?,
+            };
+            info.symbols.insert(Symbol {
+                name,
+                addr: value as u16,
+                size: sym.get("size").and_then(parse_int).unwrap_or(0) as u16,
+                kind: SymbolKind::Label,
+            });
+        }
+
+        info.index();
+        Ok(info)
+    }
+
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    // The line that produced `addr`. Assembly source wins over C source and
+    // macro bodies, then the line covering the fewest bytes.
+    pub fn line_at(&self, addr: u16) -> Option<SourceLine> {
+        let &best = self.by_addr.get(&addr)?.first()?;
+        Some(self.source_line(best))
+    }
+    pub fn lines_at(&self, addr: u16) -> Vec<SourceLine> {
+        let lines = self.by_addr.get(&addr).map_or(&[][..], Vec::as_slice);
+        lines.iter().map(|&i| self.source_line(i)).collect()
+    }
+    // The first address of a line. `file` may be just the end of the path.
+    pub fn addr_of_line(&self, file: &str, line: u32) -> Option<u16> {
+        self.lines
+            .iter()
+            .filter(|l| l.line == line && self.files[l.file].ends_with(file))
+            .flat_map(|l| &l.ranges)
+            .map(|r| r.start)
+            .min()
+    }
+    pub fn scope_at(&self, addr: u16) -> Option<&str> {
+        self.scopes
+            .iter()
+            .filter_map(|s| {
+                let range = s.ranges.iter().find(|r| r.contains(addr))?;
+                Some((range.len(), s.name.as_str()))
+            })
+            .min()
+            .map(|(_, name)| name)
+    }
+
+    fn source_line(&self, i: usize) -> SourceLine {
+        let line = &self.lines[i];
+        SourceLine {
+            file: self.files[line.file].clone(),
+            line: line.line,
+            kind: line.kind,
+        }
+    }
+    fn index(&mut self) {
+        let size = |line: &Line| line.ranges.iter().map(|r| r.len() as u32 + 1).sum::<u32>();
+        let mut order: Vec<usize> = (0..self.lines.len()).collect();
+        order.sort_by_key(|&i| (self.lines[i].kind, size(&self.lines[i]), i));
+        for i in order {
+            for range in &self.lines[i].ranges {
+                for addr in range.start..=range.end {
+                    self.by_addr.entry(addr).or_default().push(i);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Record(HashMap<String, String>);
+impl Record {
+    fn parse(fields: &str) -> Option<Self> {
+        let mut record = HashMap::new();
+        let mut rest = fields;
+        while !rest.is_empty() {
+            let (key, value) = rest.split_once('=')?;
+            let (value, tail) = if let Some(quoted) = value.strip_prefix('"') {
+                let end = quoted.find('"')?;
+                (
+                    &quoted[..end],
+                    quoted[end + 1..].strip_prefix(',').unwrap_or(""),
+                )
+            } else {
+                value.split_once(',').unwrap_or((value, ""))
+            };
+            record.insert(key.trim().to_string(), value.to_string());
+            rest = tail;
+        }
+        Some(Self(record))
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+    fn int(&self, key: &str) -> Result<usize, LoadError> {
+        self.get(key)
+            .and_then(parse_int)
+            .map(|v| v as usize)
+            .ok_or(LoadError::BadHeader)
+    }
+    fn string(&self, key: &str) -> Result<String, LoadError> {
+        self.get(key)
+            .map(str::to_string)
+            .ok_or(LoadError::BadHeader)
+    }
+    fn list(&self, key: &str) -> impl Iterator<Item = usize> + '_ {
+        self.get(key)
+            .into_iter()
+            .flat_map(|list| list.split('+'))
+            .filter_map(|id| id.parse().ok())
+    }
+}
+
+fn parse_int(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,5 @@
+pub mod acia;
+pub mod cia;
+pub mod pia;
+pub mod riot;
+pub mod via;
@@ -0,0 +1,256 @@
+use crate::{irq::IrqSource, memory::Memory, serial::SerialHost};
+
+const ST_PARITY: u8 = 0x01;
+const ST_FRAMING: u8 = 0x02;
+const ST_OVERRUN: u8 = 0x04;
+const ST_RDRF: u8 = 0x08;
+const ST_TDRE: u8 = 0x10;
+const ST_DCD: u8 = 0x20;
+const ST_DSR: u8 = 0x40;
+const ST_IRQ: u8 = 0x80;
+
+const CMD_DTR: u8 = 0x01;
+const CMD_RX_IRQ_OFF: u8 = 0x02;
+const CMD_TX: u8 = 0x0C;
+const CMD_TX_IRQ: u8 = 0x04;
+const CMD_ECHO: u8 = 0x10;
+const CMD_PARITY: u8 = 0x20;
+
+const CRYSTAL: u64 = 1_843_200;
+// Divisors of the crystal for the 16x baud clock; 0 selects the external
+// clock, which we treat as "as fast as the host can go".
+const DIVISORS: [u64; 16] = [
+    0, 2304, 1536, 1048, 856, 768, 384, 192, 96, 64, 48, 32, 24, 16, 12, 6,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AciaModel {
+    Mos6551,
+    // TDRE is stuck high, transmit interrupts never fire and a write to the
+    // data register replaces whatever the shifter was sending.
+    Wdc65C51,
+}
+
+pub struct Acia {
+    model: AciaModel,
+    host: Option<Box<dyn SerialHost>>,
+    cpu_hz: u64,
+
+    command: u8,
+    control: u8,
+    status: u8,
+    irq_flag: bool,
+
+    rdr: u8,
+    tdr: u8,
+    tdr_full: bool,
+    tx_shift: Option<u8>,
+    tx_elapsed: u64,
+    rx_shift: Option<u8>,
+    rx_elapsed: u64,
+
+    irq: Option<IrqSource>,
+}
+impl Acia {
+    pub fn new(model: AciaModel) -> Self {
+        Self {
+            model,
+            host: None,
+            cpu_hz: 1_000_000,
+
+            command: 0,
+            control: 0,
+            status: ST_TDRE,
+            irq_flag: false,
+
+            rdr: 0,
+            tdr: 0,
+            tdr_full: false,
+            tx_shift: None,
+            tx_elapsed: 0,
+            rx_shift: None,
+            rx_elapsed: 0,
+
+            irq: None,
+        }
+    }
+    pub fn with_host(mut self, host: impl SerialHost + 'static) -> Self {
+        self.host = Some(Box::new(host));
+        self
+    }
+    pub fn with_clock(mut self, cpu_hz: u64) -> Self {
+        assert!(cpu_hz > 0, "the CPU clock cannot be 0Hz");
+        self.cpu_hz = cpu_hz;
+        self
+    }
+    pub fn with_irq(mut self, irq: IrqSource) -> Self {
+        self.irq = Some(irq);
+        self.update_irq();
+        self
+    }
+
+    pub fn model(&self) -> AciaModel {
+        self.model
+    }
+    pub fn irq(&self) -> bool {
+        self.irq_flag
+    }
+    pub fn status(&self) -> u8 {
+        let mut status = self.status;
+        if self.model == AciaModel::Wdc65C51 {
+            status |= ST_TDRE;
+        }
+        if self.irq_flag {
+            status |= ST_IRQ;
+        }
+        status
+    }
+
+    pub fn set_dcd(&mut self, level: bool) {
+        self.set_modem(ST_DCD, level);
+    }
+    pub fn set_dsr(&mut self, level: bool) {
+        self.set_modem(ST_DSR, level);
+    }
+
+    pub fn clock(&mut self) {
+        self.clock_tx();
+        self.clock_rx();
+    }
+
+    fn clock_tx(&mut self) {
+        if let Some(byte) = self.tx_shift {
+            self.tx_elapsed += CRYSTAL;
+            if self.frame_done(self.tx_elapsed) {
+                self.tx_shift = None;
+                self.send(byte);
+            }
+        }
+
+        if self.tx_shift.is_none() && self.tdr_full && self.command & CMD_TX != 0 {
+            self.tx_shift = Some(self.tdr);
+            self.tx_elapsed = 0;
+            self.tdr_full = false;
+            self.status |= ST_TDRE;
+            if self.command & CMD_TX == CMD_TX_IRQ && self.model == AciaModel::Mos6551 {
+                self.raise_irq();
+            }
+        }
+    }
+    fn clock_rx(&mut self) {
+        if self.command & CMD_DTR == 0 {
+            return;
+        }
+        if self.rx_shift.is_none() {
+            self.rx_shift = self.host.as_mut().and_then(|host| host.poll());
+            self.rx_elapsed = 0;
+        }
+
+        let Some(byte) = self.rx_shift else {
+            return;
+        };
+        self.rx_elapsed += CRYSTAL;
+        if self.frame_done(self.rx_elapsed) {
+            self.rx_shift = None;
+            self.receive(byte);
+        }
+    }
+
+    fn receive(&mut self, byte: u8) {
+        let byte = byte & self.word_mask();
+        if self.status & ST_RDRF != 0 {
+            self.status |= ST_OVERRUN;
+        } else {
+            self.rdr = byte;
+            self.status |= ST_RDRF;
+        }
+        if self.command & CMD_RX_IRQ_OFF == 0 {
+            self.raise_irq();
+        }
+        if self.command & (CMD_ECHO | CMD_TX) == CMD_ECHO {
+            self.send(byte);
+        }
+    }
+    fn send(&mut self, byte: u8) {
+        let byte = byte & self.word_mask();
+        if let Some(host) = &mut self.host {
+            host.send(byte);
+        }
+    }
+
+    fn word_mask(&self) -> u8 {
+        0xFF >> ((self.control >> 5) & 3)
+    }
+    fn frame_done(&self, elapsed: u64) -> bool {
+        let divisor = DIVISORS[self.control as usize & 0x0F];
+        let data = 8 - ((self.control as u64 >> 5) & 3);
+        let parity = (self.command & CMD_PARITY != 0) as u64;
+        let stop = 1 + (self.control >> 7) as u64;
+        let ticks = 16 * divisor * (1 + data + parity + stop);
+        elapsed >= ticks * self.cpu_hz
+    }
+
+    fn set_modem(&mut self, bit: u8, level: bool) {
+        if (self.status & bit != 0) == level {
+            return;
+        }
+        self.status ^= bit;
+        if self.command & CMD_RX_IRQ_OFF == 0 {
+            self.raise_irq();
+        }
+    }
+    fn raise_irq(&mut self) {
+        if self.command & CMD_DTR != 0 {
+            self.irq_flag = true;
+            self.update_irq();
+        }
+    }
+    fn update_irq(&self) {
+        if let Some(irq) = &self.irq {
+            irq.set(self.irq_flag);
+        }
+    }
+}
+impl Memory for Acia {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let data = match addr & 3 {
+            0 => {
+                self.status &= !(ST_RDRF | ST_OVERRUN | ST_PARITY | ST_FRAMING);
+                self.rdr
+            }
+            1 => {
+                let status = self.status();
+                self.irq_flag = false;
+                self.update_irq();
+                status
+            }
+            2 => self.command,
+            _ => self.control,
+        };
+        Some(data)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr & 3 {
+            0 => {
+                if self.model == AciaModel::Wdc65C51 {
+                    self.tx_shift = None;
+                }
+                self.tdr = data;
+                self.tdr_full = true;
+                self.status &= !ST_TDRE;
+            }
+            1 => {
+                self.command &= 0xE0;
+                self.status &= !ST_OVERRUN;
+            }
+            2 => {
+                self.command = data;
+                let tx_irq = data & CMD_TX == CMD_TX_IRQ;
+                if tx_irq && self.status & ST_TDRE != 0 && self.model == AciaModel::Mos6551 {
+                    self.raise_irq();
+                }
+            }
+            _ => self.control = data,
+        }
+    }
+}
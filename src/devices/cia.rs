@@ -0,0 +1,483 @@
+use crate::{irq::IrqSource, memory::Memory};
+
+const ICR_TA: u8 = 0x01;
+const ICR_TB: u8 = 0x02;
+const ICR_ALARM: u8 = 0x04;
+const ICR_SP: u8 = 0x08;
+const ICR_FLAG: u8 = 0x10;
+
+const CR_START: u8 = 0x01;
+const CR_PBON: u8 = 0x02;
+const CR_TOGGLE: u8 = 0x04;
+const CR_ONESHOT: u8 = 0x08;
+const CR_LOAD: u8 = 0x10;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CiaModel {
+    Mos6526,
+    Mos8521,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Timer {
+    counter: u16,
+    latch: u16,
+    control: u8,
+    delay: u8,
+    output: bool,
+}
+impl Timer {
+    fn new() -> Self {
+        Self {
+            counter: 0xFFFF,
+            latch: 0xFFFF,
+            control: 0,
+            delay: 0,
+            output: false,
+        }
+    }
+
+    fn running(self) -> bool {
+        self.control & CR_START != 0
+    }
+
+    fn write_control(&mut self, data: u8) {
+        if data & CR_START != 0 && !self.running() {
+            self.delay = 1;
+            self.output = true;
+        }
+        if data & CR_LOAD != 0 {
+            self.counter = self.latch;
+        }
+        self.control = data & !CR_LOAD;
+    }
+    fn write_hi(&mut self, data: u8) {
+        self.latch = self.latch & 0x00FF | (data as u16) << 8;
+        if !self.running() {
+            self.counter = self.latch;
+            if self.control & CR_ONESHOT != 0 {
+                self.write_control(self.control | CR_START);
+            }
+        }
+    }
+
+    fn tick(&mut self) -> bool {
+        if self.counter != 0 {
+            self.counter -= 1;
+            return false;
+        }
+
+        self.counter = self.latch;
+        if self.control & CR_ONESHOT != 0 {
+            self.control &= !CR_START;
+        }
+        if self.control & CR_TOGGLE != 0 {
+            self.output = !self.output;
+        }
+        true
+    }
+    fn pin(self, pulse: bool) -> bool {
+        if self.control & CR_TOGGLE != 0 {
+            self.output
+        } else {
+            pulse
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Tod {
+    tenths: u8,
+    sec: u8,
+    min: u8,
+    hr: u8,
+}
+impl Tod {
+    fn bytes(self) -> [u8; 4] {
+        [self.tenths, self.sec, self.min, self.hr]
+    }
+    fn set(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => self.tenths = data & 0x0F,
+            1 => self.sec = data & 0x7F,
+            2 => self.min = data & 0x7F,
+            _ => self.hr = data & 0x9F,
+        }
+    }
+    fn advance(&mut self) {
+        self.tenths = (self.tenths + 1) % 10;
+        if self.tenths != 0 {
+            return;
+        }
+        self.sec = bcd_inc(self.sec, 0x60);
+        if self.sec != 0 {
+            return;
+        }
+        self.min = bcd_inc(self.min, 0x60);
+        if self.min != 0 {
+            return;
+        }
+
+        let pm = self.hr & 0x80;
+        let hr = self.hr & 0x1F;
+        self.hr = match hr {
+            0x11 => 0x12 | (pm ^ 0x80),
+            0x12 => 0x01 | pm,
+            _ => bcd_inc(hr, 0x13) | pm,
+        };
+    }
+}
+
+fn bcd_inc(value: u8, modulo: u8) -> u8 {
+    let mut value = value + 1;
+    if value & 0x0F == 0x0A {
+        value += 6;
+    }
+    if value >= modulo { 0 } else { value }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cia {
+    model: CiaModel,
+
+    pra: u8,
+    prb: u8,
+    ddra: u8,
+    ddrb: u8,
+    pins_a: u8,
+    pins_b: u8,
+
+    ta: Timer,
+    tb: Timer,
+    ta_pulse: bool,
+    tb_pulse: bool,
+
+    tod: Tod,
+    alarm: Tod,
+    tod_latch: Option<Tod>,
+    tod_running: bool,
+    tod_ticks: u8,
+
+    sdr: u8,
+    shift: u8,
+    shift_count: u8,
+    shift_pending: bool,
+    sp: bool,
+    cnt: bool,
+    cnt_out: bool,
+
+    icr: u8,
+    mask: u8,
+    pending: [u8; 2],
+    irq_next: bool,
+    irq_out: bool,
+    flag: bool,
+
+    irq: Option<IrqSource>,
+}
+impl Cia {
+    pub fn new(model: CiaModel) -> Self {
+        Self {
+            model,
+
+            pra: 0,
+            prb: 0,
+            ddra: 0,
+            ddrb: 0,
+            pins_a: 0xFF,
+            pins_b: 0xFF,
+
+            ta: Timer::new(),
+            tb: Timer::new(),
+            ta_pulse: false,
+            tb_pulse: false,
+
+            tod: Tod {
+                tenths: 0,
+                sec: 0,
+                min: 0,
+                hr: 0x01,
+            },
+            alarm: Tod {
+                tenths: 0,
+                sec: 0,
+                min: 0,
+                hr: 0,
+            },
+            tod_latch: None,
+            tod_running: true,
+            tod_ticks: 0,
+
+            sdr: 0,
+            shift: 0,
+            shift_count: 0,
+            shift_pending: false,
+            sp: true,
+            cnt: true,
+            cnt_out: true,
+
+            icr: 0,
+            mask: 0,
+            pending: [0; 2],
+            irq_next: false,
+            irq_out: false,
+            flag: true,
+
+            irq: None,
+        }
+    }
+    pub fn with_irq(mut self, irq: IrqSource) -> Self {
+        self.irq = Some(irq);
+        self.update_irq();
+        self
+    }
+
+    pub fn model(&self) -> CiaModel {
+        self.model
+    }
+    pub fn irq(&self) -> bool {
+        self.irq_out
+    }
+
+    pub fn port_a(&self) -> u8 {
+        self.pra & self.ddra | self.pins_a & !self.ddra
+    }
+    pub fn port_b(&self) -> u8 {
+        let mut port = self.prb & self.ddrb | self.pins_b & !self.ddrb;
+        if self.ta.control & CR_PBON != 0 {
+            port = port & !0x40 | (self.ta.pin(self.ta_pulse) as u8) << 6;
+        }
+        if self.tb.control & CR_PBON != 0 {
+            port = port & !0x80 | (self.tb.pin(self.tb_pulse) as u8) << 7;
+        }
+        port
+    }
+    pub fn sp(&self) -> bool {
+        self.sp
+    }
+    pub fn cnt(&self) -> bool {
+        if self.serial_output() {
+            self.cnt_out
+        } else {
+            self.cnt
+        }
+    }
+
+    pub fn set_port_a(&mut self, pins: u8) {
+        self.pins_a = pins;
+    }
+    pub fn set_port_b(&mut self, pins: u8) {
+        self.pins_b = pins;
+    }
+    pub fn set_flag(&mut self, level: bool) {
+        if self.flag && !level {
+            self.pending[0] |= ICR_FLAG;
+        }
+        self.flag = level;
+    }
+    pub fn set_sp(&mut self, level: bool) {
+        self.sp = level;
+    }
+    pub fn set_cnt(&mut self, level: bool) {
+        let rising = !self.cnt && level;
+        self.cnt = level;
+        if !rising {
+            return;
+        }
+
+        if self.ta.running() && self.ta.control & 0x20 != 0 && self.ta.tick() {
+            self.timer_a_underflow();
+        }
+        if self.tb.running() && self.tb.control & 0x60 == 0x20 && self.tb.tick() {
+            self.timer_b_underflow();
+        }
+        if !self.serial_output() {
+            self.shift = self.shift << 1 | self.sp as u8;
+            self.shift_count += 1;
+            if self.shift_count == 8 {
+                self.shift_count = 0;
+                self.sdr = self.shift;
+                self.pending[0] |= ICR_SP;
+            }
+        }
+    }
+
+    // One pulse of the 50/60 Hz TOD input.
+    pub fn tod_tick(&mut self) {
+        if !self.tod_running {
+            return;
+        }
+        let divider = if self.ta.control & 0x80 != 0 { 5 } else { 6 };
+        self.tod_ticks += 1;
+        if self.tod_ticks < divider {
+            return;
+        }
+        self.tod_ticks = 0;
+        self.tod.advance();
+        if self.tod == self.alarm {
+            self.pending[0] |= ICR_ALARM;
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.irq_out = self.irq_next;
+        self.icr |= self.pending[0];
+        self.pending = [self.pending[1], 0];
+        self.ta_pulse = false;
+        self.tb_pulse = false;
+
+        if self.ta.delay != 0 {
+            self.ta.delay -= 1;
+        } else if self.ta.running() && self.ta.control & 0x20 == 0 && self.ta.tick() {
+            self.timer_a_underflow();
+        }
+        if self.tb.delay != 0 {
+            self.tb.delay -= 1;
+        } else if self.tb.running() && self.tb.control & 0x60 == 0 && self.tb.tick() {
+            self.timer_b_underflow();
+        }
+
+        self.irq_next = self.icr & self.mask != 0;
+        self.update_irq();
+    }
+
+    fn timer_a_underflow(&mut self) {
+        self.ta_pulse = true;
+        self.pending[0] |= ICR_TA;
+
+        if self.serial_output() && (self.shift_count != 0 || self.shift_pending) {
+            self.clock_serial_out();
+        }
+
+        let cascade = match self.tb.control & 0x60 {
+            0x40 => true,
+            0x60 => self.cnt,
+            _ => false,
+        };
+        if cascade && self.tb.running() && self.tb.tick() {
+            self.timer_b_underflow();
+        }
+    }
+    fn timer_b_underflow(&mut self) {
+        self.tb_pulse = true;
+        match self.model {
+            CiaModel::Mos6526 => self.pending[1] |= ICR_TB,
+            CiaModel::Mos8521 => self.pending[0] |= ICR_TB,
+        }
+    }
+    fn clock_serial_out(&mut self) {
+        self.cnt_out = !self.cnt_out;
+        if !self.cnt_out {
+            if self.shift_count == 0 {
+                self.shift = self.sdr;
+                self.shift_pending = false;
+            }
+            self.sp = self.shift & 0x80 != 0;
+            self.shift <<= 1;
+            self.shift_count += 1;
+            return;
+        }
+
+        if self.shift_count == 8 {
+            self.shift_count = 0;
+            self.pending[0] |= ICR_SP;
+        }
+    }
+    fn serial_output(&self) -> bool {
+        self.ta.control & 0x40 != 0
+    }
+
+    fn update_irq(&self) {
+        if let Some(irq) = &self.irq {
+            irq.set(self.irq_out);
+        }
+    }
+
+    fn read_icr(&mut self) -> u8 {
+        let data = self.icr | (self.irq_out as u8) << 7;
+        // Reading in the same cycle an interrupt is raised clears the
+        // flag before it reaches the IRQ pin, so that interrupt is lost.
+        self.icr = 0;
+        self.irq_next = false;
+        self.irq_out = false;
+        self.update_irq();
+        data
+    }
+}
+impl Memory for Cia {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let data = match addr & 0xF {
+            0x0 => self.port_a(),
+            0x1 => self.port_b(),
+            0x2 => self.ddra,
+            0x3 => self.ddrb,
+            0x4 => self.ta.counter as u8,
+            0x5 => (self.ta.counter >> 8) as u8,
+            0x6 => self.tb.counter as u8,
+            0x7 => (self.tb.counter >> 8) as u8,
+            reg @ 0x8..=0xB => {
+                let reg = reg - 0x8;
+                if reg == 3 {
+                    self.tod_latch = Some(self.tod);
+                }
+                let tod = self.tod_latch.unwrap_or(self.tod);
+                if reg == 0 {
+                    self.tod_latch = None;
+                }
+                tod.bytes()[reg as usize]
+            }
+            0xC => self.sdr,
+            0xD => self.read_icr(),
+            0xE => self.ta.control,
+            _ => self.tb.control,
+        };
+        Some(data)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr & 0xF {
+            0x0 => self.pra = data,
+            0x1 => self.prb = data,
+            0x2 => self.ddra = data,
+            0x3 => self.ddrb = data,
+            0x4 => self.ta.latch = self.ta.latch & 0xFF00 | data as u16,
+            0x5 => self.ta.write_hi(data),
+            0x6 => self.tb.latch = self.tb.latch & 0xFF00 | data as u16,
+            0x7 => self.tb.write_hi(data),
+            reg @ 0x8..=0xB => {
+                let reg = reg - 0x8;
+                if self.tb.control & 0x80 != 0 {
+                    self.alarm.set(reg, data);
+                } else {
+                    self.tod.set(reg, data);
+                    match reg {
+                        0 => self.tod_running = true,
+                        3 => self.tod_running = false,
+                        _ => (),
+                    }
+                }
+            }
+            0xC => {
+                self.sdr = data;
+                if self.serial_output() {
+                    self.shift_pending = true;
+                }
+            }
+            0xD => {
+                if data & 0x80 != 0 {
+                    self.mask |= data & 0x1F;
+                } else {
+                    self.mask &= !data;
+                }
+                self.irq_next = self.icr & self.mask != 0;
+            }
+            0xE => {
+                if (self.ta.control ^ data) & 0x40 != 0 {
+                    self.shift_count = 0;
+                    self.cnt_out = true;
+                }
+                self.ta.write_control(data);
+            }
+            _ => self.tb.write_control(data),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{irq::IrqSource, memory::Memory};
+
+const CR_C1_IRQ: u8 = 0x01;
+const CR_C1_RISING: u8 = 0x02;
+const CR_PORT: u8 = 0x04;
+const CR_C2_IRQ: u8 = 0x08;
+const CR_C2_RISING: u8 = 0x10;
+const CR_C2_OUTPUT: u8 = 0x20;
+const CR_IRQ2: u8 = 0x40;
+const CR_IRQ1: u8 = 0x80;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Side {
+    or: u8,
+    ddr: u8,
+    cr: u8,
+    pins: u8,
+
+    c1: bool,
+    c2_in: bool,
+    c2_out: bool,
+    pulse: bool,
+}
+impl Side {
+    fn new() -> Self {
+        Self {
+            or: 0,
+            ddr: 0,
+            cr: 0,
+            pins: 0xFF,
+
+            c1: true,
+            c2_in: true,
+            c2_out: true,
+            pulse: false,
+        }
+    }
+
+    fn port(self) -> u8 {
+        self.or & self.ddr | self.pins & !self.ddr
+    }
+    fn c2(self) -> bool {
+        if self.cr & CR_C2_OUTPUT != 0 {
+            self.c2_out
+        } else {
+            self.c2_in
+        }
+    }
+    fn irq(self) -> bool {
+        let c1 = self.cr & (CR_IRQ1 | CR_C1_IRQ) == CR_IRQ1 | CR_C1_IRQ;
+        let c2 = self.cr & (CR_IRQ2 | CR_C2_IRQ) == CR_IRQ2 | CR_C2_IRQ;
+        c1 || c2
+    }
+
+    fn set_c1(&mut self, level: bool) {
+        if level == self.c1 {
+            return;
+        }
+        self.c1 = level;
+        if level != (self.cr & CR_C1_RISING != 0) {
+            return;
+        }
+
+        self.cr |= CR_IRQ1;
+        if self.cr & (CR_C2_OUTPUT | CR_C2_RISING | CR_C2_IRQ) == CR_C2_OUTPUT {
+            self.c2_out = true;
+        }
+    }
+    fn set_c2(&mut self, level: bool) {
+        if level == self.c2_in {
+            return;
+        }
+        self.c2_in = level;
+        if self.cr & CR_C2_OUTPUT == 0 && level == (self.cr & CR_C2_RISING != 0) {
+            self.cr |= CR_IRQ2;
+        }
+    }
+
+    // A read of PRA or a write of PRB drops C2 in the handshake and pulse
+    // output modes.
+    fn strobe(&mut self) {
+        if self.cr & (CR_C2_OUTPUT | CR_C2_RISING) == CR_C2_OUTPUT {
+            self.c2_out = false;
+            self.pulse = self.cr & CR_C2_IRQ != 0;
+        }
+    }
+    fn clock(&mut self) {
+        if self.pulse {
+            self.pulse = false;
+            self.c2_out = true;
+        }
+    }
+
+    fn read_data(&mut self) -> u8 {
+        if self.cr & CR_PORT == 0 {
+            return self.ddr;
+        }
+        self.cr &= !(CR_IRQ1 | CR_IRQ2);
+        self.port()
+    }
+    fn write_data(&mut self, data: u8) {
+        if self.cr & CR_PORT == 0 {
+            self.ddr = data;
+        } else {
+            self.or = data;
+        }
+    }
+    fn write_control(&mut self, data: u8) {
+        self.cr = self.cr & (CR_IRQ1 | CR_IRQ2) | data & 0x3F;
+        if data & CR_C2_OUTPUT != 0 {
+            self.cr &= !CR_IRQ2;
+        }
+        if data & (CR_C2_OUTPUT | CR_C2_RISING) == CR_C2_OUTPUT | CR_C2_RISING {
+            self.c2_out = data & CR_C2_IRQ != 0;
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Pia {
+    a: Side,
+    b: Side,
+    irq_a: Option<IrqSource>,
+    irq_b: Option<IrqSource>,
+}
+impl Pia {
+    pub fn new() -> Self {
+        Self {
+            a: Side::new(),
+            b: Side::new(),
+            irq_a: None,
+            irq_b: None,
+        }
+    }
+    pub fn with_irq_a(mut self, irq: IrqSource) -> Self {
+        self.irq_a = Some(irq);
+        self.update_irq();
+        self
+    }
+    pub fn with_irq_b(mut self, irq: IrqSource) -> Self {
+        self.irq_b = Some(irq);
+        self.update_irq();
+        self
+    }
+
+    pub fn irq_a(&self) -> bool {
+        self.a.irq()
+    }
+    pub fn irq_b(&self) -> bool {
+        self.b.irq()
+    }
+    pub fn port_a(&self) -> u8 {
+        self.a.port()
+    }
+    pub fn port_b(&self) -> u8 {
+        self.b.port()
+    }
+    pub fn ca2(&self) -> bool {
+        self.a.c2()
+    }
+    pub fn cb2(&self) -> bool {
+        self.b.c2()
+    }
+
+    pub fn set_port_a(&mut self, pins: u8) {
+        self.a.pins = pins;
+    }
+    pub fn set_port_b(&mut self, pins: u8) {
+        self.b.pins = pins;
+    }
+    pub fn set_ca1(&mut self, level: bool) {
+        self.a.set_c1(level);
+        self.update_irq();
+    }
+    pub fn set_ca2(&mut self, level: bool) {
+        self.a.set_c2(level);
+        self.update_irq();
+    }
+    pub fn set_cb1(&mut self, level: bool) {
+        self.b.set_c1(level);
+        self.update_irq();
+    }
+    pub fn set_cb2(&mut self, level: bool) {
+        self.b.set_c2(level);
+        self.update_irq();
+    }
+
+    pub fn clock(&mut self) {
+        self.a.clock();
+        self.b.clock();
+    }
+
+    fn update_irq(&self) {
+        if let Some(irq) = &self.irq_a {
+            irq.set(self.a.irq());
+        }
+        if let Some(irq) = &self.irq_b {
+            irq.set(self.b.irq());
+        }
+    }
+}
+impl Memory for Pia {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let data = match addr & 3 {
+            0 => {
+                let port = self.a.cr & CR_PORT != 0;
+                let data = self.a.read_data();
+                if port {
+                    self.a.strobe();
+                }
+                data
+            }
+            1 => self.a.cr,
+            2 => self.b.read_data(),
+            _ => self.b.cr,
+        };
+        self.update_irq();
+        Some(data)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr & 3 {
+            0 => self.a.write_data(data),
+            1 => self.a.write_control(data),
+            2 => {
+                self.b.write_data(data);
+                if self.b.cr & CR_PORT != 0 {
+                    self.b.strobe();
+                }
+            }
+            _ => self.b.write_control(data),
+        }
+        self.update_irq();
+    }
+}
+impl Default for Pia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
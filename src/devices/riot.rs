@@ -0,0 +1,179 @@
+use crate::{irq::IrqSource, memory::Memory};
+
+const FLAG_TIMER: u8 = 0x80;
+const FLAG_PA7: u8 = 0x40;
+
+#[derive(Clone, Debug)]
+pub struct Riot {
+    ram: [u8; 128],
+
+    ora: u8,
+    orb: u8,
+    ddra: u8,
+    ddrb: u8,
+    pins_a: u8,
+    pins_b: u8,
+
+    timer: u8,
+    interval: u16,
+    prescale: u16,
+    expired: bool,
+    timer_irq: bool,
+
+    pa7: bool,
+    pa7_positive: bool,
+    pa7_irq: bool,
+
+    flags: u8,
+    irq: Option<IrqSource>,
+}
+impl Riot {
+    // The RAM select pin, wired to A9 as on the Atari 2600.
+    pub const RS: u16 = 0x200;
+
+    pub fn new() -> Self {
+        Self {
+            ram: [0; 128],
+
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            pins_a: 0xFF,
+            pins_b: 0xFF,
+
+            timer: 0xFF,
+            interval: 1024,
+            prescale: 1023,
+            expired: false,
+            timer_irq: false,
+
+            pa7: true,
+            pa7_positive: false,
+            pa7_irq: false,
+
+            flags: 0,
+            irq: None,
+        }
+    }
+    pub fn with_irq(mut self, irq: IrqSource) -> Self {
+        self.irq = Some(irq);
+        self.update_irq();
+        self
+    }
+
+    pub fn irq(&self) -> bool {
+        (self.flags & FLAG_TIMER != 0 && self.timer_irq)
+            || (self.flags & FLAG_PA7 != 0 && self.pa7_irq)
+    }
+    pub fn ram(&self) -> &[u8; 128] {
+        &self.ram
+    }
+
+    pub fn port_a(&self) -> u8 {
+        self.ora & self.ddra | self.pins_a & !self.ddra
+    }
+    pub fn port_b(&self) -> u8 {
+        self.orb & self.ddrb | self.pins_b & !self.ddrb
+    }
+    pub fn set_port_a(&mut self, pins: u8) {
+        self.pins_a = pins;
+        self.check_pa7();
+    }
+    pub fn set_port_b(&mut self, pins: u8) {
+        self.pins_b = pins;
+    }
+
+    pub fn clock(&mut self) {
+        if self.expired {
+            self.timer = self.timer.wrapping_sub(1);
+            return;
+        }
+        if self.prescale != 0 {
+            self.prescale -= 1;
+            return;
+        }
+
+        self.timer = self.timer.wrapping_sub(1);
+        if self.timer == 0xFF {
+            self.expired = true;
+            self.flags |= FLAG_TIMER;
+            self.update_irq();
+        }
+        self.prescale = self.interval - 1;
+    }
+
+    fn check_pa7(&mut self) {
+        let pa7 = self.port_a() & 0x80 != 0;
+        if pa7 != self.pa7 && pa7 == self.pa7_positive {
+            self.flags |= FLAG_PA7;
+            self.update_irq();
+        }
+        self.pa7 = pa7;
+    }
+    fn update_irq(&self) {
+        if let Some(irq) = &self.irq {
+            irq.set(self.irq());
+        }
+    }
+}
+impl Memory for Riot {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        if addr & Self::RS == 0 {
+            return Some(self.ram[addr as usize & 0x7F]);
+        }
+
+        let data = if addr & 0x04 == 0 {
+            match addr & 3 {
+                0 => self.port_a(),
+                1 => self.ddra,
+                2 => self.port_b(),
+                _ => self.ddrb,
+            }
+        } else if addr & 0x01 == 0 {
+            self.timer_irq = addr & 0x08 != 0;
+            self.flags &= !FLAG_TIMER;
+            self.update_irq();
+            self.timer
+        } else {
+            let flags = self.flags;
+            self.flags &= !FLAG_PA7;
+            self.update_irq();
+            flags
+        };
+        Some(data)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        if addr & Self::RS == 0 {
+            self.ram[addr as usize & 0x7F] = data;
+            return;
+        }
+
+        if addr & 0x04 == 0 {
+            match addr & 3 {
+                0 => self.ora = data,
+                1 => self.ddra = data,
+                2 => self.orb = data,
+                _ => self.ddrb = data,
+            }
+            self.check_pa7();
+        } else if addr & 0x10 != 0 {
+            self.interval = [1, 8, 64, 1024][addr as usize & 3];
+            self.timer = data;
+            self.prescale = 0;
+            self.expired = false;
+            self.timer_irq = addr & 0x08 != 0;
+            self.flags &= !FLAG_TIMER;
+            self.update_irq();
+        } else {
+            self.pa7_positive = addr & 0x01 != 0;
+            self.pa7_irq = addr & 0x02 != 0;
+            self.update_irq();
+        }
+    }
+}
+impl Default for Riot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
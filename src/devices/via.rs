@@ -0,0 +1,509 @@
+use crate::{irq::IrqSource, memory::Memory};
+
+const IFR_CA2: u8 = 0x01;
+const IFR_CA1: u8 = 0x02;
+const IFR_SR: u8 = 0x04;
+const IFR_CB2: u8 = 0x08;
+const IFR_CB1: u8 = 0x10;
+const IFR_T2: u8 = 0x20;
+const IFR_T1: u8 = 0x40;
+
+#[derive(Clone, Debug)]
+pub struct Via {
+    ora: u8,
+    orb: u8,
+    ddra: u8,
+    ddrb: u8,
+    ira: u8,
+    irb: u8,
+    pins_a: u8,
+    pins_b: u8,
+
+    t1: u16,
+    t1_latch: u16,
+    t1_load: bool,
+    t1_reload: bool,
+    t1_armed: bool,
+    pb7: bool,
+
+    t2: u16,
+    t2_latch: u8,
+    t2_load: bool,
+    t2_armed: bool,
+    pb6: bool,
+
+    sr: u8,
+    sr_count: u8,
+    sr_running: bool,
+    sr_tick: u8,
+
+    acr: u8,
+    pcr: u8,
+    ifr: u8,
+    ier: u8,
+
+    ca1: bool,
+    ca2: bool,
+    ca2_out: bool,
+    ca2_pulse: bool,
+    cb1: bool,
+    cb1_out: bool,
+    cb2: bool,
+    cb2_out: bool,
+    cb2_pulse: bool,
+
+    irq: Option<IrqSource>,
+}
+impl Via {
+    pub fn new() -> Self {
+        Self {
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            ira: 0,
+            irb: 0,
+            pins_a: 0xFF,
+            pins_b: 0xFF,
+
+            t1: 0xFFFF,
+            t1_latch: 0xFFFF,
+            t1_load: false,
+            t1_reload: false,
+            t1_armed: false,
+            pb7: true,
+
+            t2: 0xFFFF,
+            t2_latch: 0xFF,
+            t2_load: false,
+            t2_armed: false,
+            pb6: true,
+
+            sr: 0,
+            sr_count: 0,
+            sr_running: false,
+            sr_tick: 0,
+
+            acr: 0,
+            pcr: 0,
+            ifr: 0,
+            ier: 0,
+
+            ca1: true,
+            ca2: true,
+            ca2_out: true,
+            ca2_pulse: false,
+            cb1: true,
+            cb1_out: true,
+            cb2: true,
+            cb2_out: true,
+            cb2_pulse: false,
+
+            irq: None,
+        }
+    }
+    pub fn with_irq(mut self, irq: IrqSource) -> Self {
+        self.irq = Some(irq);
+        self.update_irq();
+        self
+    }
+
+    pub fn irq(&self) -> bool {
+        self.ifr & self.ier & 0x7F != 0
+    }
+
+    pub fn port_a(&self) -> u8 {
+        self.ora & self.ddra | self.pins_a & !self.ddra
+    }
+    pub fn port_b(&self) -> u8 {
+        let port = self.orb & self.ddrb | self.pins_b & !self.ddrb;
+        if self.acr & 0x80 != 0 {
+            port & 0x7F | (self.pb7 as u8) << 7
+        } else {
+            port
+        }
+    }
+    pub fn ca2(&self) -> bool {
+        if self.ca2_is_output() {
+            self.ca2_out
+        } else {
+            self.ca2
+        }
+    }
+    pub fn cb1(&self) -> bool {
+        if self.sr_mode_clocks_cb1() {
+            self.cb1_out
+        } else {
+            self.cb1
+        }
+    }
+    pub fn cb2(&self) -> bool {
+        if self.cb2_is_output() {
+            self.cb2_out
+        } else {
+            self.cb2
+        }
+    }
+
+    pub fn set_port_a(&mut self, pins: u8) {
+        self.pins_a = pins;
+    }
+    pub fn set_port_b(&mut self, pins: u8) {
+        let pb6 = pins & 0x40 != 0;
+        if self.pb6 && !pb6 && self.acr & 0x20 != 0 {
+            self.count_t2();
+        }
+        self.pb6 = pb6;
+        self.pins_b = pins;
+    }
+    pub fn set_ca1(&mut self, level: bool) {
+        let active = self.pcr & 0x01 != 0;
+        if self.ca1 != level && level == active {
+            if self.acr & 0x01 != 0 {
+                self.ira = self.pins_a;
+            }
+            if self.pcr & 0x0E == 0x08 {
+                self.ca2_out = true;
+            }
+            self.set_ifr(IFR_CA1);
+        }
+        self.ca1 = level;
+    }
+    pub fn set_ca2(&mut self, level: bool) {
+        if !self.ca2_is_output() {
+            let active = self.pcr & 0x04 != 0;
+            if self.ca2 != level && level == active {
+                self.set_ifr(IFR_CA2);
+            }
+        }
+        self.ca2 = level;
+    }
+    pub fn set_cb1(&mut self, level: bool) {
+        let active = self.pcr & 0x10 != 0;
+        if self.cb1 != level && level == active {
+            if self.acr & 0x02 != 0 {
+                self.irb = self.pins_b;
+            }
+            if self.pcr & 0xE0 == 0x80 {
+                self.cb2_out = true;
+            }
+            self.set_ifr(IFR_CB1);
+        }
+        if !self.cb1 && level && self.sr_mode() & 3 == 3 {
+            self.shift();
+        }
+        self.cb1 = level;
+    }
+    pub fn set_cb2(&mut self, level: bool) {
+        if !self.cb2_is_output() {
+            let active = self.pcr & 0x40 != 0;
+            if self.cb2 != level && level == active {
+                self.set_ifr(IFR_CB2);
+            }
+        }
+        self.cb2 = level;
+    }
+
+    pub fn clock(&mut self) {
+        if self.ca2_pulse {
+            self.ca2_pulse = false;
+            self.ca2_out = true;
+        }
+        if self.cb2_pulse {
+            self.cb2_pulse = false;
+            self.cb2_out = true;
+        }
+
+        self.clock_t1();
+        self.clock_t2();
+        self.clock_sr();
+    }
+
+    fn clock_t1(&mut self) {
+        if self.t1_load {
+            self.t1_load = false;
+            return;
+        }
+        if self.t1_reload {
+            self.t1_reload = false;
+            self.t1 = self.t1_latch;
+            return;
+        }
+
+        self.t1 = self.t1.wrapping_sub(1);
+        if self.t1 == 0xFFFF {
+            self.t1_reload = true;
+            if self.t1_armed {
+                self.set_ifr(IFR_T1);
+                if self.acr & 0x40 != 0 {
+                    self.pb7 = !self.pb7;
+                } else {
+                    self.t1_armed = false;
+                    self.pb7 = true;
+                }
+            }
+        }
+    }
+    fn clock_t2(&mut self) {
+        if self.t2_load {
+            self.t2_load = false;
+            return;
+        }
+
+        if self.acr & 0x20 == 0 {
+            self.count_t2();
+        }
+
+        if matches!(self.sr_mode(), 1 | 4 | 5) {
+            if self.sr_tick == 0 {
+                self.sr_tick = self.t2_latch;
+                self.toggle_sr_clock();
+            } else {
+                self.sr_tick -= 1;
+            }
+        }
+    }
+    fn count_t2(&mut self) {
+        self.t2 = self.t2.wrapping_sub(1);
+        if self.t2 == 0xFFFF && self.t2_armed {
+            self.t2_armed = false;
+            self.set_ifr(IFR_T2);
+        }
+    }
+    fn clock_sr(&mut self) {
+        if self.sr_mode() & 3 == 2 {
+            self.toggle_sr_clock();
+            self.toggle_sr_clock();
+        }
+    }
+    fn toggle_sr_clock(&mut self) {
+        if !self.sr_running {
+            return;
+        }
+        self.cb1_out = !self.cb1_out;
+        if self.cb1_out {
+            self.shift();
+        }
+    }
+    fn shift(&mut self) {
+        if !self.sr_running {
+            return;
+        }
+
+        let mode = self.sr_mode();
+        if mode & 4 != 0 {
+            let out = self.sr & 0x80 != 0;
+            self.sr = self.sr.rotate_left(1);
+            self.cb2_out = out;
+        } else {
+            self.sr = self.sr << 1 | self.cb2 as u8;
+        }
+
+        self.sr_count += 1;
+        if self.sr_count == 8 {
+            self.sr_count = 0;
+            if mode != 4 {
+                self.sr_running = false;
+                self.cb1_out = true;
+                self.set_ifr(IFR_SR);
+            }
+        }
+    }
+
+    fn sr_mode(&self) -> u8 {
+        (self.acr >> 2) & 7
+    }
+    fn sr_mode_clocks_cb1(&self) -> bool {
+        matches!(self.sr_mode(), 1 | 2 | 4 | 5 | 6)
+    }
+    fn ca2_is_output(&self) -> bool {
+        self.pcr & 0x08 != 0
+    }
+    fn cb2_is_output(&self) -> bool {
+        self.pcr & 0x80 != 0 || self.sr_mode() & 4 != 0
+    }
+
+    fn set_ifr(&mut self, bits: u8) {
+        self.ifr |= bits;
+        self.update_irq();
+    }
+    fn clear_ifr(&mut self, bits: u8) {
+        self.ifr &= !bits;
+        self.update_irq();
+    }
+    fn update_irq(&self) {
+        if let Some(irq) = &self.irq {
+            irq.set(self.irq());
+        }
+    }
+
+    fn port_a_access(&mut self) {
+        let mut clear = IFR_CA1;
+        if self.pcr & 0x0A != 0x02 {
+            clear |= IFR_CA2;
+        }
+        self.clear_ifr(clear);
+
+        match self.pcr & 0x0E {
+            0x08 => self.ca2_out = false,
+            0x0A => {
+                self.ca2_out = false;
+                self.ca2_pulse = true;
+            }
+            _ => (),
+        }
+    }
+    fn port_b_access(&mut self, write: bool) {
+        let mut clear = IFR_CB1;
+        if self.pcr & 0xA0 != 0x20 {
+            clear |= IFR_CB2;
+        }
+        self.clear_ifr(clear);
+
+        if write {
+            match self.pcr & 0xE0 {
+                0x80 => self.cb2_out = false,
+                0xA0 => {
+                    self.cb2_out = false;
+                    self.cb2_pulse = true;
+                }
+                _ => (),
+            }
+        }
+    }
+    fn sr_access(&mut self) {
+        self.clear_ifr(IFR_SR);
+        self.sr_count = 0;
+        self.sr_running = self.sr_mode() != 0;
+        self.sr_tick = self.t2_latch;
+    }
+
+    fn read_port_a(&self) -> u8 {
+        if self.acr & 0x01 != 0 {
+            self.ira
+        } else {
+            self.port_a()
+        }
+    }
+    fn read_port_b(&self) -> u8 {
+        let pins = if self.acr & 0x02 != 0 {
+            self.irb
+        } else {
+            self.pins_b
+        };
+        let port = self.orb & self.ddrb | pins & !self.ddrb;
+        if self.acr & 0x80 != 0 {
+            port & 0x7F | (self.pb7 as u8) << 7
+        } else {
+            port
+        }
+    }
+}
+impl Memory for Via {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let data = match addr & 0xF {
+            0x0 => {
+                self.port_b_access(false);
+                self.read_port_b()
+            }
+            0x1 => {
+                self.port_a_access();
+                self.read_port_a()
+            }
+            0x2 => self.ddrb,
+            0x3 => self.ddra,
+            0x4 => {
+                self.clear_ifr(IFR_T1);
+                self.t1 as u8
+            }
+            0x5 => (self.t1 >> 8) as u8,
+            0x6 => self.t1_latch as u8,
+            0x7 => (self.t1_latch >> 8) as u8,
+            0x8 => {
+                self.clear_ifr(IFR_T2);
+                self.t2 as u8
+            }
+            0x9 => (self.t2 >> 8) as u8,
+            0xA => {
+                self.sr_access();
+                self.sr
+            }
+            0xB => self.acr,
+            0xC => self.pcr,
+            0xD => self.ifr | (self.irq() as u8) << 7,
+            0xE => self.ier | 0x80,
+            _ => self.read_port_a(),
+        };
+        Some(data)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr & 0xF {
+            0x0 => {
+                self.port_b_access(true);
+                self.orb = data;
+            }
+            0x1 => {
+                self.port_a_access();
+                self.ora = data;
+            }
+            0x2 => self.ddrb = data,
+            0x3 => self.ddra = data,
+            0x4 | 0x6 => self.t1_latch = self.t1_latch & 0xFF00 | data as u16,
+            0x5 => {
+                self.t1_latch = self.t1_latch & 0x00FF | (data as u16) << 8;
+                self.t1 = self.t1_latch;
+                self.t1_load = true;
+                self.t1_reload = false;
+                self.t1_armed = true;
+                if self.acr & 0x80 != 0 {
+                    self.pb7 = false;
+                }
+                self.clear_ifr(IFR_T1);
+            }
+            0x7 => {
+                self.t1_latch = self.t1_latch & 0x00FF | (data as u16) << 8;
+                self.clear_ifr(IFR_T1);
+            }
+            0x8 => self.t2_latch = data,
+            0x9 => {
+                self.t2 = u16::from_le_bytes([self.t2_latch, data]);
+                self.t2_load = true;
+                self.t2_armed = true;
+                self.clear_ifr(IFR_T2);
+            }
+            0xA => {
+                self.sr = data;
+                self.sr_access();
+            }
+            0xB => self.acr = data,
+            0xC => {
+                self.pcr = data;
+                match data & 0x0E {
+                    0x0C => self.ca2_out = false,
+                    0x0E => self.ca2_out = true,
+                    _ => (),
+                }
+                match data & 0xE0 {
+                    0xC0 => self.cb2_out = false,
+                    0xE0 => self.cb2_out = true,
+                    _ => (),
+                }
+            }
+            0xD => self.clear_ifr(data & 0x7F),
+            0xE => {
+                if data & 0x80 != 0 {
+                    self.ier |= data & 0x7F;
+                } else {
+                    self.ier &= !data;
+                }
+                self.update_irq();
+            }
+            _ => self.ora = data,
+        }
+    }
+}
+impl Default for Via {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    instr::{self, Am, Op},
+    symbols::SymbolTable,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub op: Op,
+    pub am: Am,
+    pub operand: u16,
+}
+impl Instruction {
+    // Bytes past the end of `bytes` read as zero, so a short slice at the
+    // top of memory still decodes.
+    pub fn decode(bytes: &[u8]) -> Self {
+        let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+        let (op, am) = instr::decode(byte(0));
+        let operand = match size(am) {
+            1 => 0,
+            2 => byte(1) as u16,
+            _ => u16::from_le_bytes([byte(1), byte(2)]),
+        };
+        Self { op, am, operand }
+    }
+
+    pub fn size(self) -> u16 {
+        size(self.am)
+    }
+    pub fn mnemonic(self) -> String {
+        format!("{:?}", self.op).to_uppercase()
+    }
+
+    // The address the operand names, before indexing. Branches give their
+    // destination when placed at `pc`.
+    pub fn target(self, pc: u16) -> Option<u16> {
+        match self.am {
+            Am::Implied | Am::Accumulator | Am::Immediate => None,
+            Am::Relative => {
+                let offset = self.operand as u8 as i8 as u16;
+                Some(pc.wrapping_add(2).wrapping_add(offset))
+            }
+            _ => Some(self.operand),
+        }
+    }
+
+    // Assembler syntax, naming targets that fall exactly on a symbol.
+    pub fn format(self, pc: u16, symbols: Option<&SymbolTable>) -> String {
+        let name = |addr: u16, digits: usize| {
+            symbols
+                .and_then(|s| s.lookup(addr))
+                .filter(|&(_, offset)| offset == 0)
+                .map(|(symbol, _)| symbol.name.clone())
+                .unwrap_or_else(|| format!("${addr:0digits$X}"))
+        };
+        let v = self.operand;
+        let operand = match self.am {
+            Am::Implied => String::new(),
+            Am::Accumulator => "A".to_string(),
+            Am::Immediate => format!("#${v:02X}"),
+            Am::Zero => name(v, 2),
+            Am::ZeroX => format!("{},X", name(v, 2)),
+            Am::ZeroY => format!("{},Y", name(v, 2)),
+            Am::IndexedIndirect => format!("({},X)", name(v, 2)),
+            Am::IndirectIndexed => format!("({}),Y", name(v, 2)),
+            Am::Absolute => name(v, 4),
+            Am::AbsoluteX => format!("{},X", name(v, 4)),
+            Am::AbsoluteY => format!("{},Y", name(v, 4)),
+            Am::Indirect => format!("({})", name(v, 4)),
+            Am::Relative => name(self.target(pc).unwrap(), 4),
+        };
+        if operand.is_empty() {
+            self.mnemonic()
+        } else {
+            format!("{} {operand}", self.mnemonic())
+        }
+    }
+}
+
+pub fn size(am: Am) -> u16 {
+    match am {
+        Am::Implied | Am::Accumulator => 1,
+        Am::Absolute | Am::AbsoluteX | Am::AbsoluteY | Am::Indirect => 3,
+        _ => 2,
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    loader::{Image, LoadError},
+    symbols::{Symbol, SymbolKind, SymbolTable},
+};
+
+const MAGIC: &[u8] = b"\x7FELF";
+const CLASS32: u8 = 1;
+const LITTLE_ENDIAN: u8 = 1;
+const EM_MOS: u16 = 6502;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+const SHN_UNDEF: u16 = 0;
+
+// An executable as linked by llvm-mos. Segments are placed at their
+// physical (load) addresses, which is where a ROM image would hold them.
+// llvm-mos keeps bank bits above the 16-bit address, in those and in the
+// symbols alike; they are dropped, so banks that share a window load over
+// each other in file order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Elf {
+    pub image: Image,
+    pub symbols: SymbolTable,
+}
+impl Elf {
+    pub fn parse(file: &[u8]) -> Result<Self, LoadError> {
+        if !file.starts_with(MAGIC) || file.get(4) != Some(&CLASS32) {
+            return Err(LoadError::BadHeader);
+        }
+        if file.get(5) != Some(&LITTLE_ENDIAN) || half(file, 18)? != EM_MOS {
+            return Err(LoadError::BadHeader);
+        }
+
+        let mut image = Image {
+            start: Some(word(file, 24)? as u16),
+            ..Image::default()
+        };
+        let (phoff, phentsize, phnum) = (word(file, 28)?, half(file, 42)?, half(file, 44)?);
+        for i in 0..phnum as usize {
+            let ph = phoff as usize + i * phentsize as usize;
+            if word(file, ph)? != PT_LOAD {
+                continue;
+            }
+            let (offset, paddr) = (word(file, ph + 4)? as usize, word(file, ph + 12)?);
+            let (filesz, memsz) = (word(file, ph + 16)? as usize, word(file, ph + 20)? as usize);
+
+            let mut data = bytes(file, offset, filesz)?.to_vec();
+            data.resize(memsz.max(filesz), 0);
+            if !data.is_empty() {
+                image
+                    .segments
+                    .extend(Image::raw(&data, paddr as u16)?.segments);
+            }
+        }
+
+        let symbols = symbols(file)?;
+        Ok(Self { image, symbols })
+    }
+}
+
+fn symbols(file: &[u8]) -> Result<SymbolTable, LoadError> {
+    let mut table = SymbolTable::new();
+    let (shoff, shentsize, shnum) = (word(file, 32)?, half(file, 46)?, half(file, 48)?);
+    let section = |i: usize| shoff as usize + i * shentsize as usize;
+
+    for i in 0..shnum as usize {
+        let sh = section(i);
+        if word(file, sh + 4)? != SHT_SYMTAB {
+            continue;
+        }
+        let (offset, size) = (word(file, sh + 16)? as usize, word(file, sh + 20)? as usize);
+        let entsize = (word(file, sh + 36)? as usize).max(16);
+        let strtab = section(word(file, sh + 24)? as usize);
+        let strings = bytes(
+            file,
+            word(file, strtab + 16)? as usize,
+            word(file, strtab + 20)? as usize,
+        )?;
+
+        for sym in (offset..offset + size).step_by(entsize) {
+            let info = *file.get(sym + 12).ok_or(LoadError::Truncated)?;
+            let kind = match info & 0x0F {
+                STT_SECTION | STT_FILE => continue,
+                STT_FUNC => SymbolKind::Function,
+                STT_OBJECT => SymbolKind::Object,
+                _ => SymbolKind::Label,
+            };
+            let name = strings
+                .get(word(file, sym)? as usize..)
+                .and_then(|s| s.split(|&b| b == 0).next())
+                .ok_or(LoadError::Truncated)?;
+            if name.is_empty() || half(file, sym + 14)? == SHN_UNDEF {
+                continue;
+            }
+            table.insert(Symbol {
+                name: String::from_utf8_lossy(name).into_owned(),
+                addr: word(file, sym + 4)? as u16,
+                size: word(file, sym + 8)?.min(0xFFFF) as u16,
+                kind,
+            });
+        }
+    }
+    Ok(table)
+}
+
+fn bytes(file: &[u8], offset: usize, len: usize) -> Result<&[u8], LoadError> {
+    file.get(offset..offset + len).ok_or(LoadError::Truncated)
+}
+fn half(file: &[u8], offset: usize) -> Result<u16, LoadError> {
+    let b = bytes(file, offset, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+fn word(file: &[u8], offset: usize) -> Result<u32, LoadError> {
+    let b = bytes(file, offset, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
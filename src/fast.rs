@@ -0,0 +1,363 @@
+use crate::{
+    M6502, UNSTABLE_MAGIC,
+    core::{Core, P},
+    instr::{self, Am, Op},
+    memory::Memory,
+    microcode::{self, Micro},
+};
+
+// Runs whole instructions straight against a `Memory`, for programs that
+// need the results but not the bus: no dummy reads or writes, interrupts
+// taken only between instructions, and the cycles of each instruction
+// taken from the microcode of `M6502` instead of being run.
+//
+// It converts to and from `M6502` at instruction boundaries, carrying over
+// the registers, the data latch, the delayed I mask of CLI, SEI and PLP,
+// the interrupt lines and whether it is jammed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FastCpu {
+    core: Core,
+    irq_masked: bool,
+    irq: bool,
+    nmi: bool,
+    nmi_pending: bool,
+    jammed: bool,
+    // What a read that nobody answers returns.
+    data: u8,
+}
+impl FastCpu {
+    pub fn new(core: Core) -> Self {
+        Self {
+            core,
+            irq_masked: core.p.i(),
+            irq: false,
+            nmi: false,
+            nmi_pending: false,
+            jammed: false,
+            data: 0,
+        }
+    }
+
+    pub fn core(self) -> Core {
+        self.core
+    }
+    pub fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+    pub fn jammed(self) -> bool {
+        self.jammed
+    }
+
+    pub fn set_irq(&mut self, to: bool) {
+        self.irq = to;
+    }
+    /// NMI is taken on the rising edge.
+    pub fn set_nmi(&mut self, to: bool) {
+        self.nmi_pending |= !self.nmi && to;
+        self.nmi = to;
+    }
+
+    /// Runs one instruction, or enters a pending interrupt, and returns the
+    /// cycles it took. After JAM, as on `M6502`, interrupts are ignored and
+    /// every step is one more cycle of doing nothing.
+    pub fn step(&mut self, memory: &mut impl Memory) -> u8 {
+        if self.jammed {
+            return 1;
+        }
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            return self.interrupt(0xFFFA, false, memory);
+        }
+        if self.irq && !self.irq_masked {
+            return self.interrupt(0xFFFE, false, memory);
+        }
+
+        let opcode = self.fetch(memory);
+        let (op, am) = instr::decode(opcode);
+        let mut cycles = microcode::cycles(opcode);
+        // CLI, SEI and PLP poll for interrupts before they update I, so
+        // their new mask only applies after the next instruction.
+        if op.delays_irq_mask() {
+            self.irq_masked = self.core.p.i();
+        }
+        match op {
+            Op::Brk => {
+                self.core.pc = self.core.pc.wrapping_add(1);
+                self.interrupt(0xFFFE, true, memory);
+            }
+            Op::Jam => self.jammed = true,
+            Op::Jsr => {
+                let lo = self.fetch(memory);
+                self.push_word(self.core.pc, memory);
+                let hi = self.read(self.core.pc, memory);
+                self.core.pc = u16::from_le_bytes([lo, hi]);
+            }
+            Op::Rts => self.core.pc = self.pull_word(memory).wrapping_add(1),
+            Op::Rti => {
+                self.core.p = P::from_pull_byte(self.pull(memory));
+                self.core.pc = self.pull_word(memory);
+            }
+            Op::Pha => self.push(self.core.a, memory),
+            Op::Php => self.push(self.core.p.to_push_byte(true), memory),
+            Op::Pla => {
+                let data = self.pull(memory);
+                self.core.exec_pla(data);
+            }
+            Op::Plp => {
+                let data = self.pull(memory);
+                self.core.exec_plp(data);
+            }
+            Op::Jmp => self.core.pc = self.address(am, memory).0,
+            _ if am == Am::Relative => {
+                let offset = self.fetch(memory) as i8 as i16;
+                if self.branch(op) {
+                    let new = self.core.pc.wrapping_add_signed(offset);
+                    cycles += 1 + (new & 0xFF00 != self.core.pc & 0xFF00) as u8;
+                    self.core.pc = new;
+                }
+            }
+            _ if am == Am::Implied => self.implied(op),
+            _ if am == Am::Accumulator => self.core.a = self.modify(op, self.core.a),
+            _ if am == Am::Immediate => {
+                let data = self.fetch(memory);
+                self.load(op, data);
+            }
+            _ => {
+                let (addr, wrap) = self.address(am, memory);
+                if op.is_rmw() {
+                    let data = self.read(addr, memory);
+                    let data = self.modify(op, data);
+                    self.write(addr, data, memory);
+                } else if op.reads_operand() {
+                    cycles += wrap as u8;
+                    let data = self.read(addr, memory);
+                    self.load(op, data);
+                } else {
+                    let (data, addr) = self.store(op, addr, wrap);
+                    self.write(addr, data, memory);
+                }
+            }
+        }
+        if !op.delays_irq_mask() {
+            self.irq_masked = self.core.p.i();
+        }
+        cycles
+    }
+
+    fn interrupt(&mut self, vector: u16, b: bool, memory: &mut impl Memory) -> u8 {
+        self.push_word(self.core.pc, memory);
+        self.push(self.core.p.to_push_byte(b), memory);
+        self.core.p.set_i(true);
+        self.irq_masked = true;
+        let lo = self.read(vector, memory);
+        let hi = self.read(vector + 1, memory);
+        self.core.pc = u16::from_le_bytes([lo, hi]);
+        microcode::cycles(0x00)
+    }
+
+    // The operand's address, and whether indexing it crossed a page.
+    fn address(&mut self, am: Am, memory: &mut impl Memory) -> (u16, bool) {
+        let indexed = |base: u16, index: u8| {
+            let addr = base.wrapping_add(index as u16);
+            (addr, addr & 0xFF00 != base & 0xFF00)
+        };
+        match am {
+            Am::Zero => (self.fetch(memory) as u16, false),
+            Am::ZeroX => (self.fetch(memory).wrapping_add(self.core.x) as u16, false),
+            Am::ZeroY => (self.fetch(memory).wrapping_add(self.core.y) as u16, false),
+            Am::Absolute => (self.fetch_word(memory), false),
+            Am::AbsoluteX => indexed(self.fetch_word(memory), self.core.x),
+            Am::AbsoluteY => indexed(self.fetch_word(memory), self.core.y),
+            Am::IndexedIndirect => {
+                let pointer = self.fetch(memory).wrapping_add(self.core.x);
+                (self.read_pointer(pointer, memory), false)
+            }
+            Am::IndirectIndexed => {
+                let pointer = self.fetch(memory);
+                indexed(self.read_pointer(pointer, memory), self.core.y)
+            }
+            Am::Indirect => {
+                let pointer = self.fetch_word(memory);
+                let lo = self.read(pointer, memory);
+                let hi = self.read(pointer & 0xFF00 | pointer.wrapping_add(1) & 0x00FF, memory);
+                (u16::from_le_bytes([lo, hi]), false)
+            }
+            Am::Implied | Am::Accumulator | Am::Immediate | Am::Relative => unreachable!(),
+        }
+    }
+
+    fn implied(&mut self, op: Op) {
+        let core = &mut self.core;
+        match op {
+            Op::Clc => core.exec_clc(),
+            Op::Cld => core.exec_cld(),
+            Op::Cli => core.exec_cli(),
+            Op::Clv => core.exec_clv(),
+            Op::Dex => core.exec_dex(),
+            Op::Dey => core.exec_dey(),
+            Op::Inx => core.exec_inx(),
+            Op::Iny => core.exec_iny(),
+            Op::Nop => {}
+            Op::Sec => core.exec_sec(),
+            Op::Sed => core.exec_sed(),
+            Op::Sei => core.exec_sei(),
+            Op::Tax => core.exec_tax(),
+            Op::Tay => core.exec_tay(),
+            Op::Tsx => core.exec_tsx(),
+            Op::Txa => core.exec_txa(),
+            Op::Txs => core.exec_txs(),
+            Op::Tya => core.exec_tya(),
+            _ => unreachable!(),
+        }
+    }
+    fn load(&mut self, op: Op, data: u8) {
+        let core = &mut self.core;
+        match op {
+            Op::Adc => core.exec_adc(data),
+            Op::Alr => core.exec_alr(data),
+            Op::Anc => core.exec_anc(data),
+            Op::And => core.exec_and(data),
+            Op::Ane => core.exec_ane(data, UNSTABLE_MAGIC),
+            Op::Arr => core.exec_arr(data),
+            Op::Bit => core.exec_bit(data),
+            Op::Cmp => core.exec_cmp(data),
+            Op::Cpx => core.exec_cpx(data),
+            Op::Cpy => core.exec_cpy(data),
+            Op::Eor => core.exec_eor(data),
+            Op::Las => core.exec_las(data),
+            Op::Lax => core.exec_lax(data),
+            Op::Lda => core.exec_lda(data),
+            Op::Ldx => core.exec_ldx(data),
+            Op::Ldy => core.exec_ldy(data),
+            Op::Lxa => core.exec_lxa(data, UNSTABLE_MAGIC),
+            Op::Nop => {}
+            Op::Ora => core.exec_ora(data),
+            Op::Sbc => core.exec_sbc(data),
+            Op::Sbx => core.exec_sbx(data),
+            _ => unreachable!(),
+        }
+    }
+    fn modify(&mut self, op: Op, data: u8) -> u8 {
+        let core = &mut self.core;
+        match op {
+            Op::Asl => core.exec_asl(data),
+            Op::Dcp => core.exec_dcp(data),
+            Op::Dec => core.exec_dec(data),
+            Op::Inc => core.exec_inc(data),
+            Op::Isc => core.exec_isc(data),
+            Op::Lsr => core.exec_lsr(data),
+            Op::Rla => core.exec_rla(data),
+            Op::Rol => core.exec_rol(data),
+            Op::Ror => core.exec_ror(data),
+            Op::Rra => core.exec_rra(data),
+            Op::Slo => core.exec_slo(data),
+            Op::Sre => core.exec_sre(data),
+            _ => unreachable!(),
+        }
+    }
+    fn store(&mut self, op: Op, addr: u16, wrap: bool) -> (u8, u16) {
+        let core = &mut self.core;
+        match op {
+            Op::Sax => (core.a & core.x, addr),
+            Op::Sha => core.exec_sha(addr, wrap),
+            Op::Shx => core.exec_shx(addr, wrap),
+            Op::Shy => core.exec_shy(addr, wrap),
+            Op::Sta => (core.a, addr),
+            Op::Stx => (core.x, addr),
+            Op::Sty => (core.y, addr),
+            Op::Tas => core.exec_tas(addr, wrap),
+            _ => unreachable!(),
+        }
+    }
+    fn branch(&self, op: Op) -> bool {
+        let core = &self.core;
+        match op {
+            Op::Bcc => core.exec_bcc(),
+            Op::Bcs => core.exec_bcs(),
+            Op::Beq => core.exec_beq(),
+            Op::Bmi => core.exec_bmi(),
+            Op::Bne => core.exec_bne(),
+            Op::Bpl => core.exec_bpl(),
+            Op::Bvc => core.exec_bvc(),
+            Op::Bvs => core.exec_bvs(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: u16, memory: &mut impl Memory) -> u8 {
+        if let Some(data) = memory.read(addr) {
+            self.data = data;
+        }
+        self.data
+    }
+    fn write(&mut self, addr: u16, data: u8, memory: &mut impl Memory) {
+        self.data = data;
+        memory.write(addr, data);
+    }
+    fn fetch(&mut self, memory: &mut impl Memory) -> u8 {
+        let data = self.read(self.core.pc, memory);
+        self.core.pc = self.core.pc.wrapping_add(1);
+        data
+    }
+    fn fetch_word(&mut self, memory: &mut impl Memory) -> u16 {
+        let lo = self.fetch(memory);
+        u16::from_le_bytes([lo, self.fetch(memory)])
+    }
+    // Pointers wrap around within the zero page.
+    fn read_pointer(&mut self, pointer: u8, memory: &mut impl Memory) -> u16 {
+        let lo = self.read(pointer as u16, memory);
+        let hi = self.read(pointer.wrapping_add(1) as u16, memory);
+        u16::from_le_bytes([lo, hi])
+    }
+    fn push(&mut self, data: u8, memory: &mut impl Memory) {
+        self.write(self.core.s as u16 | 0x100, data, memory);
+        self.core.s = self.core.s.wrapping_sub(1);
+    }
+    fn push_word(&mut self, data: u16, memory: &mut impl Memory) {
+        let [lo, hi] = data.to_le_bytes();
+        self.push(hi, memory);
+        self.push(lo, memory);
+    }
+    fn pull(&mut self, memory: &mut impl Memory) -> u8 {
+        self.core.s = self.core.s.wrapping_add(1);
+        self.read(self.core.s as u16 | 0x100, memory)
+    }
+    fn pull_word(&mut self, memory: &mut impl Memory) -> u16 {
+        let lo = self.pull(memory);
+        u16::from_le_bytes([lo, self.pull(memory)])
+    }
+}
+
+// `M6502` is at an instruction boundary once a clock has set `bus.sync()`,
+// or once it is jammed. Its opcode fetch is then dropped: `FastCpu` reads
+// the opcode again, and counts that cycle again.
+impl From<M6502> for FastCpu {
+    fn from(cpu: M6502) -> Self {
+        let irq_masked = match cpu.micro() {
+            None => cpu.irq_masked,
+            Some(Micro::Sync) if cpu.op.delays_irq_mask() => cpu.irq_masked,
+            Some(Micro::Sync) => cpu.core.p.i(),
+            Some(Micro::Resume | Micro::Jam) => cpu.irq_masked,
+            Some(micro) => panic!("M6502 is not at an instruction boundary: next is {micro:?}"),
+        };
+        Self {
+            core: cpu.core,
+            irq_masked,
+            irq: cpu.irq_scheduled,
+            nmi: cpu.last_nmi,
+            nmi_pending: cpu.nmi_scheduled,
+            jammed: cpu.micro() == Some(Micro::Jam),
+            data: cpu.data,
+        }
+    }
+}
+// The `M6502` fetches the opcode at PC on its next clock, unless jammed.
+impl From<FastCpu> for M6502 {
+    fn from(cpu: FastCpu) -> Self {
+        let mut m6502 = M6502::resume(cpu.core, cpu.data, cpu.irq_masked, cpu.jammed);
+        m6502.irq_scheduled = cpu.irq;
+        m6502.last_nmi = cpu.nmi;
+        m6502.nmi_scheduled = cpu.nmi_pending;
+        m6502
+    }
+}
@@ -0,0 +1,173 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    loader::LoadError,
+    mapper::{Cartridge, Mapper, Mirroring},
+    memory::{Memory, MemoryMap, MemoryMapBuilder},
+};
+
+const MAGIC: &[u8] = b"NES\x1A";
+const HEADER_LEN: usize = 16;
+const TRAINER_LEN: usize = 512;
+const TRAINER: u16 = 0x7000;
+const KB8: usize = 0x2000;
+const KB16: usize = 0x4000;
+
+pub type SharedCartridge = Rc<RefCell<Cartridge<Box<dyn Mapper>>>>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub nes2: bool,
+    pub prg_rom: usize,
+    pub chr_rom: usize,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    pub trainer: bool,
+    pub prg_ram: usize,
+    pub prg_nvram: usize,
+    pub chr_ram: usize,
+    pub chr_nvram: usize,
+}
+impl Header {
+    pub fn parse(data: &[u8]) -> Result<Self, LoadError> {
+        if !data.starts_with(MAGIC) {
+            return Err(LoadError::BadHeader);
+        }
+        let h = data.get(..HEADER_LEN).ok_or(LoadError::Truncated)?;
+        let nes2 = h[7] & 0x0C == 0x08;
+
+        let mirroring = if h[6] & 0x08 != 0 {
+            Mirroring::FourScreen
+        } else if h[6] & 0x01 != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+        let mut header = Header {
+            nes2,
+            prg_rom: h[4] as usize * KB16,
+            chr_rom: h[5] as usize * KB8,
+            mapper: (h[6] >> 4) as u16,
+            submapper: 0,
+            mirroring,
+            battery: h[6] & 0x02 != 0,
+            trainer: h[6] & 0x04 != 0,
+            prg_ram: 0,
+            prg_nvram: 0,
+            chr_ram: 0,
+            chr_nvram: 0,
+        };
+
+        if nes2 {
+            header.prg_rom = rom_size(h[4], h[9] & 0x0F, KB16);
+            header.chr_rom = rom_size(h[5], h[9] >> 4, KB8);
+            header.mapper |= (h[7] & 0xF0) as u16 | ((h[8] & 0x0F) as u16) << 8;
+            header.submapper = h[8] >> 4;
+            header.prg_ram = ram_size(h[10] & 0x0F);
+            header.prg_nvram = ram_size(h[10] >> 4);
+            header.chr_ram = ram_size(h[11] & 0x0F);
+            header.chr_nvram = ram_size(h[11] >> 4);
+        } else {
+            // Old dumps often have a signature like "DiskDude!" in bytes
+            // 7-15; the high mapper nibble is only trusted if 12-15 are zero.
+            if h[12..16].iter().all(|&b| b == 0) {
+                header.mapper |= (h[7] & 0xF0) as u16;
+            }
+            let ram = (h[8] as usize).max(1) * KB8;
+            if header.battery {
+                header.prg_nvram = ram;
+            } else {
+                header.prg_ram = ram;
+            }
+            if header.chr_rom == 0 {
+                header.chr_ram = KB8;
+            }
+        }
+        Ok(header)
+    }
+}
+
+fn rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+    if msb == 0x0F {
+        let multiplier = (lsb & 3) as usize * 2 + 1;
+        multiplier << (lsb >> 2)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * unit
+    }
+}
+fn ram_size(shift: u8) -> usize {
+    if shift == 0 { 0 } else { 64 << shift }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rom {
+    pub header: Header,
+    pub trainer: Option<Vec<u8>>,
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+}
+impl Rom {
+    pub fn parse(data: &[u8]) -> Result<Self, LoadError> {
+        let header = Header::parse(data)?;
+        let mut rest = &data[HEADER_LEN..];
+        let mut take = |len: usize| {
+            let (taken, tail) = rest.split_at_checked(len).ok_or(LoadError::Truncated)?;
+            rest = tail;
+            Ok(taken.to_vec())
+        };
+
+        let trainer = if header.trainer {
+            Some(take(TRAINER_LEN)?)
+        } else {
+            None
+        };
+        let prg = take(header.prg_rom)?;
+        let chr = take(header.chr_rom)?;
+        if prg.is_empty() {
+            return Err(LoadError::BadHeader);
+        }
+        Ok(Self {
+            header,
+            trainer,
+            prg,
+            chr,
+        })
+    }
+
+    // CNROM only switches CHR, so on the CPU side it is NROM.
+    pub fn cartridge(&self) -> Result<Cartridge<Box<dyn Mapper>>, LoadError> {
+        let prg = self.prg.clone();
+        let mut prg_ram = self.header.prg_ram + self.header.prg_nvram;
+        if self.trainer.is_some() {
+            prg_ram = prg_ram.max(KB8);
+        }
+
+        let mut cartridge = match self.header.mapper {
+            0 | 3 => Cartridge::nrom(prg, prg_ram).boxed(),
+            1 => Cartridge::mmc1(prg, prg_ram).boxed(),
+            2 => Cartridge::uxrom(prg, prg_ram).boxed(),
+            mapper => return Err(LoadError::UnsupportedMapper(mapper)),
+        };
+        if let Some(trainer) = &self.trainer {
+            for (addr, &data) in (TRAINER..).zip(trainer) {
+                cartridge.poke(addr, data);
+            }
+        }
+        Ok(cartridge)
+    }
+
+    // The NES CPU's view: 2K of RAM mirrored up to $1FFF and the cartridge
+    // from $4020, which holds the reset vector. The PPU and APU registers
+    // are left for the caller to map; the cartridge is shared so the PPU
+    // side can reach its mirroring and the mapper can be clocked.
+    pub fn memory_map(&self) -> Result<(MemoryMapBuilder, SharedCartridge), LoadError> {
+        let cartridge = Rc::new(RefCell::new(self.cartridge()?));
+        let map = MemoryMap::builder()
+            .ram(0x0000..=0x07FF)
+            .mirror(0x0800..=0x1FFF, 0x0000..=0x07FF)
+            .absolute_device(0x4020..=0xFFFF, cartridge.clone());
+        Ok((map, cartridge))
+    }
+}
@@ -80,7 +80,7 @@ pub enum Op {
     Tya,
 }
 impl Op {
-    pub fn reads_operand(self) -> bool {
+    pub const fn reads_operand(self) -> bool {
         use Op::*;
         matches!(
             self,
@@ -112,7 +112,7 @@ impl Op {
                 | Las
         )
     }
-    pub fn writes_operand(self) -> bool {
+    pub const fn writes_operand(self) -> bool {
         use Op::*;
         matches!(
             self,
@@ -137,9 +137,13 @@ impl Op {
                 | Tas
         )
     }
-    pub fn is_rmw(self) -> bool {
+    pub const fn is_rmw(self) -> bool {
         self.reads_operand() && self.writes_operand()
     }
+    pub fn delays_irq_mask(self) -> bool {
+        use Op::*;
+        matches!(self, Cli | Sei | Plp)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -180,7 +184,7 @@ impl Display for Am {
     }
 }
 
-pub fn decode(opcode: u8) -> (Op, Am) {
+pub const fn decode(opcode: u8) -> (Op, Am) {
     DECODE_TABLE[opcode as usize]
 }
 
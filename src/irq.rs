@@ -0,0 +1,95 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use crate::Bus;
+
+#[derive(Debug, Default)]
+struct Shared {
+    asserted: Cell<u64>,
+    names: RefCell<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IrqLine {
+    shared: Rc<Shared>,
+}
+impl IrqLine {
+    pub const MAX_SOURCES: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn source(&self, name: &str) -> IrqSource {
+        let mut names = self.shared.names.borrow_mut();
+        let id = names.len();
+        assert!(id < Self::MAX_SOURCES, "too many IRQ sources");
+        names.push(name.to_string());
+
+        IrqSource {
+            shared: self.shared.clone(),
+            id,
+        }
+    }
+
+    pub fn level(&self) -> bool {
+        self.shared.asserted.get() != 0
+    }
+    pub fn is_active(&self, id: usize) -> bool {
+        self.shared.asserted.get() & (1 << id) != 0
+    }
+    pub fn active(&self) -> Vec<usize> {
+        let asserted = self.shared.asserted.get();
+        (0..self.shared.names.borrow().len())
+            .filter(|&id| asserted & (1 << id) != 0)
+            .collect()
+    }
+    pub fn name(&self, id: usize) -> String {
+        self.shared.names.borrow()[id].clone()
+    }
+
+    pub fn drive(&self, bus: &mut Bus) {
+        bus.set_irq(self.level());
+    }
+    pub fn drive_nmi(&self, bus: &mut Bus) {
+        bus.set_nmi(self.level());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IrqSource {
+    shared: Rc<Shared>,
+    id: usize,
+}
+impl IrqSource {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    pub fn name(&self) -> String {
+        self.shared.names.borrow()[self.id].clone()
+    }
+
+    pub fn is_asserted(&self) -> bool {
+        self.shared.asserted.get() & self.mask() != 0
+    }
+
+    pub fn assert(&self) {
+        self.set(true);
+    }
+    pub fn release(&self) {
+        self.set(false);
+    }
+    pub fn set(&self, to: bool) {
+        let mut asserted = self.shared.asserted.get() & !self.mask();
+        if to {
+            asserted |= self.mask();
+        }
+        self.shared.asserted.set(asserted);
+    }
+
+    fn mask(&self) -> u64 {
+        1 << self.id
+    }
+}
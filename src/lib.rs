@@ -1,9 +1,27 @@
 use core::{Core, P};
 
-use instr::{Am, Op};
+use instr::Op;
+use microcode::{Micro, Reg, microcode};
 
+pub mod asm;
 pub mod core;
+pub mod debug_info;
+pub mod devices;
+pub mod disasm;
+pub mod elf;
+pub mod fast;
+pub mod ines;
 pub mod instr;
+pub mod irq;
+pub mod loader;
+pub mod mapper;
+pub mod memory;
+pub mod microcode;
+pub mod mos_sim;
+pub mod o65;
+pub mod serial;
+pub mod sim65;
+pub mod symbols;
 #[cfg(test)]
 pub mod tests;
 
@@ -26,17 +44,31 @@ impl Bus {
         self.addr = addr;
         self.set_rw(true);
         self.set_sync(false);
+        self.set_floating(true);
     }
     pub fn read_sync(&mut self, addr: u16) {
         self.addr = addr;
         self.set_rw(true);
         self.set_sync(true);
+        self.set_floating(true);
     }
     pub fn write(&mut self, addr: u16, data: u8) {
         self.addr = addr;
         self.data = data;
         self.set_rw(false);
         self.set_sync(false);
+        self.set_floating(false);
+    }
+
+    /// Drives `data` onto the bus in answer to a read cycle.
+    pub fn respond(&mut self, data: u8) {
+        self.data = data;
+        self.set_floating(false);
+    }
+    /// Leaves a read cycle undriven, so `data` keeps the last value seen on
+    /// the bus (open bus).
+    pub fn float(&mut self) {
+        self.set_floating(true);
     }
 
     pub fn irq(self) -> bool {
@@ -54,6 +86,9 @@ impl Bus {
     pub fn sync(self) -> bool {
         self.flags & Self::SYNC != 0
     }
+    pub fn floating(self) -> bool {
+        self.flags & Self::FLOATING != 0
+    }
 
     pub fn set_irq(&mut self, to: bool) {
         self.flags &= !Self::IRQ;
@@ -85,12 +120,19 @@ impl Bus {
             self.flags |= Self::SYNC;
         }
     }
+    fn set_floating(&mut self, to: bool) {
+        self.flags &= !Self::FLOATING;
+        if to {
+            self.flags |= Self::FLOATING;
+        }
+    }
 
     const IRQ: u8 = 1;
     const NMI: u8 = 2;
     const RES: u8 = 4;
     const RW: u8 = 8;
     const SYNC: u8 = 16;
+    const FLOATING: u8 = 32;
 }
 
 const UNSTABLE_MAGIC: u8 = 0xEE;
@@ -99,15 +141,16 @@ const UNSTABLE_MAGIC: u8 = 0xEE;
 pub struct M6502 {
     core: Core,
 
-    am: Am,
     brk: Brk,
     cycle: u8,
+    micro: &'static [Micro],
     op: Op,
 
     addr: u16,
     data: u8,
     wrap: bool,
 
+    irq_masked: bool,
     irq_scheduled: bool,
     last_nmi: bool,
     nmi_scheduled: bool,
@@ -125,15 +168,16 @@ impl M6502 {
         Self {
             core,
 
-            am: Am::Implied,
             brk: Brk::Res,
             cycle: 1,
+            micro: microcode(0x00),
             op: Op::Brk,
 
             addr: 0,
             data: 0,
             wrap: false,
 
+            irq_masked: true,
             irq_scheduled: false,
             last_nmi: false,
             nmi_scheduled: false,
@@ -143,776 +187,413 @@ impl M6502 {
         Self {
             core,
 
-            am: Am::Implied,
             brk: Brk::Brk,
             cycle: 1,
+            micro: microcode(0xEA),
             op: Op::Nop,
 
             addr: 0,
             data: 0,
             wrap: false,
 
+            irq_masked: core.p.i(),
             irq_scheduled: false,
             last_nmi: false,
             nmi_scheduled: false,
         }
     }
+    // Between two instructions, as `FastCpu` leaves it: the next clock
+    // fetches the opcode at PC without touching the I mask, which lags
+    // behind I after CLI, SEI and PLP. A jammed one stays jammed.
+    fn resume(core: Core, data: u8, irq_masked: bool, jammed: bool) -> Self {
+        let (micro, op): (&'static [Micro], _) = match jammed {
+            // $02 is one of the JAM opcodes.
+            true => (microcode(0x02), Op::Jam),
+            false => (&microcode::RESUME, Op::Nop),
+        };
+        Self {
+            cycle: micro.len() as u8 - 1,
+            micro,
+            op,
+            data,
+            irq_masked,
+            ..Self::new(core)
+        }
+    }
 
     pub fn core(self) -> Core {
         self.core
     }
+    /// The registers can be changed safely while `bus.sync()` is set: the
+    /// previous instruction has retired and the next one is not decoded yet.
+    pub fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+    /// The micro-op the next clock runs, or None when that is the first one
+    /// of an instruction that has not been decoded yet.
+    pub fn micro(self) -> Option<Micro> {
+        match self.cycle {
+            0 => None,
+            cycle => Some(self.micro[cycle as usize]),
+        }
+    }
 
+    /// Runs one bus cycle.
+    ///
+    /// On return `bus.addr`, `bus.rw()` and `bus.sync()` describe the cycle.
+    /// For a write, `bus.data` holds the value written. For a read, the
+    /// host answers with [`Bus::respond`] before the next call, which
+    /// latches `bus.data`. The CPU never changes `bus.data` on a read cycle,
+    /// so a read that nobody answers (or that is marked with [`Bus::float`])
+    /// returns whatever was last on the bus, as open bus does on real
+    /// systems.
     pub fn clock(&mut self, bus: &mut Bus) {
         if self.cycle == 0 {
             self.finish_sync(bus);
         }
-        self.do_step(bus);
+        self.step(bus);
         self.latch_interrupts(bus);
     }
     fn finish_sync(&mut self, bus: &mut Bus) {
         if self.nmi_scheduled {
             self.nmi_scheduled = false;
             self.op = Op::Brk;
-            self.am = Am::Implied;
+            self.micro = microcode(0x00);
             self.brk = Brk::Nmi;
-        } else if self.irq_scheduled && !self.core.p.i() {
+        } else if self.irq_scheduled && !self.irq_masked {
             self.op = Op::Brk;
-            self.am = Am::Implied;
+            self.micro = microcode(0x00);
             self.brk = Brk::Irq;
         } else {
-            (self.op, self.am) = instr::decode(bus.data);
+            self.op = instr::decode(bus.data).0;
+            self.micro = microcode(bus.data);
             self.brk = Brk::Brk;
             self.core.pc = self.core.pc.wrapping_add(1);
         }
     }
-    fn do_step(&mut self, bus: &mut Bus) {
-        use Am::*;
-        use Op::*;
-        match (self.op, self.am) {
-            (Brk, Implied) => self.exec_brk(bus),
-            (Ora, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_ora, bus),
-            (Jam, Implied) => self.exec_jam(bus),
-            (Slo, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_slo, bus),
-            (Nop, Zero) => self.exec_zero(Self::exec_nop, bus),
-            (Ora, Zero) => self.exec_zero(Self::exec_ora, bus),
-            (Asl, Zero) => self.exec_zero(Self::exec_asl, bus),
-            (Slo, Zero) => self.exec_zero(Self::exec_slo, bus),
-            (Php, Implied) => self.exec_push(Self::exec_php, bus),
-            (Ora, Immediate) => self.exec_immediate(Self::exec_ora, bus),
-            (Asl, Accumulator) => self.exec_accumulator(Self::exec_asl, bus),
-            (Anc, Immediate) => self.exec_immediate(Self::exec_anc, bus),
-            (Nop, Absolute) => self.exec_absolute(Self::exec_nop, bus),
-            (Ora, Absolute) => self.exec_absolute(Self::exec_ora, bus),
-            (Asl, Absolute) => self.exec_absolute(Self::exec_asl, bus),
-            (Slo, Absolute) => self.exec_absolute(Self::exec_slo, bus),
-            (Bpl, Relative) => self.exec_relative(Self::exec_bpl, bus),
-            (Ora, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_ora, bus),
-            (Slo, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_slo, bus),
-            (Nop, ZeroX) => self.exec_zero_x(Self::exec_nop, bus),
-            (Ora, ZeroX) => self.exec_zero_x(Self::exec_ora, bus),
-            (Asl, ZeroX) => self.exec_zero_x(Self::exec_asl, bus),
-            (Slo, ZeroX) => self.exec_zero_x(Self::exec_slo, bus),
-            (Clc, Implied) => self.exec_implied(Self::exec_clc, bus),
-            (Ora, AbsoluteY) => self.exec_absolute_y(Self::exec_ora, bus),
-            (Nop, Implied) => self.exec_implied(Self::exec_nop, bus),
-            (Slo, AbsoluteY) => self.exec_absolute_y(Self::exec_slo, bus),
-            (Nop, AbsoluteX) => self.exec_absolute_x(Self::exec_nop, bus),
-            (Ora, AbsoluteX) => self.exec_absolute_x(Self::exec_ora, bus),
-            (Asl, AbsoluteX) => self.exec_absolute_x(Self::exec_asl, bus),
-            (Slo, AbsoluteX) => self.exec_absolute_x(Self::exec_slo, bus),
-            (Jsr, Absolute) => self.exec_jsr(bus),
-            (And, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_and, bus),
-            (Rla, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_rla, bus),
-            (Bit, Zero) => self.exec_zero(Self::exec_bit, bus),
-            (And, Zero) => self.exec_zero(Self::exec_and, bus),
-            (Rol, Zero) => self.exec_zero(Self::exec_rol, bus),
-            (Rla, Zero) => self.exec_zero(Self::exec_rla, bus),
-            (Plp, Implied) => self.exec_pull(Self::exec_plp, bus),
-            (And, Immediate) => self.exec_immediate(Self::exec_and, bus),
-            (Rol, Accumulator) => self.exec_accumulator(Self::exec_rol, bus),
-            (Bit, Absolute) => self.exec_absolute(Self::exec_bit, bus),
-            (And, Absolute) => self.exec_absolute(Self::exec_and, bus),
-            (Rol, Absolute) => self.exec_absolute(Self::exec_rol, bus),
-            (Rla, Absolute) => self.exec_absolute(Self::exec_rla, bus),
-            (Bmi, Relative) => self.exec_relative(Self::exec_bmi, bus),
-            (And, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_and, bus),
-            (Rla, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_rla, bus),
-            (And, ZeroX) => self.exec_zero_x(Self::exec_and, bus),
-            (Rol, ZeroX) => self.exec_zero_x(Self::exec_rol, bus),
-            (Rla, ZeroX) => self.exec_zero_x(Self::exec_rla, bus),
-            (Sec, Implied) => self.exec_implied(Self::exec_sec, bus),
-            (And, AbsoluteY) => self.exec_absolute_y(Self::exec_and, bus),
-            (Rla, AbsoluteY) => self.exec_absolute_y(Self::exec_rla, bus),
-            (And, AbsoluteX) => self.exec_absolute_x(Self::exec_and, bus),
-            (Rol, AbsoluteX) => self.exec_absolute_x(Self::exec_rol, bus),
-            (Rla, AbsoluteX) => self.exec_absolute_x(Self::exec_rla, bus),
-            (Rti, Implied) => self.exec_rti(bus),
-            (Eor, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_eor, bus),
-            (Sre, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_sre, bus),
-            (Eor, Zero) => self.exec_zero(Self::exec_eor, bus),
-            (Lsr, Zero) => self.exec_zero(Self::exec_lsr, bus),
-            (Sre, Zero) => self.exec_zero(Self::exec_sre, bus),
-            (Pha, Implied) => self.exec_push(Self::exec_pha, bus),
-            (Eor, Immediate) => self.exec_immediate(Self::exec_eor, bus),
-            (Lsr, Accumulator) => self.exec_accumulator(Self::exec_lsr, bus),
-            (Alr, Immediate) => self.exec_immediate(Self::exec_alr, bus),
-            (Jmp, Absolute) => self.exec_absolute(Self::exec_jmp, bus),
-            (Eor, Absolute) => self.exec_absolute(Self::exec_eor, bus),
-            (Lsr, Absolute) => self.exec_absolute(Self::exec_lsr, bus),
-            (Sre, Absolute) => self.exec_absolute(Self::exec_sre, bus),
-            (Bvc, Relative) => self.exec_relative(Self::exec_bvc, bus),
-            (Eor, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_eor, bus),
-            (Sre, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_sre, bus),
-            (Eor, ZeroX) => self.exec_zero_x(Self::exec_eor, bus),
-            (Lsr, ZeroX) => self.exec_zero_x(Self::exec_lsr, bus),
-            (Sre, ZeroX) => self.exec_zero_x(Self::exec_sre, bus),
-            (Cli, Implied) => self.exec_implied(Self::exec_cli, bus),
-            (Eor, AbsoluteY) => self.exec_absolute_y(Self::exec_eor, bus),
-            (Sre, AbsoluteY) => self.exec_absolute_y(Self::exec_sre, bus),
-            (Eor, AbsoluteX) => self.exec_absolute_x(Self::exec_eor, bus),
-            (Lsr, AbsoluteX) => self.exec_absolute_x(Self::exec_lsr, bus),
-            (Sre, AbsoluteX) => self.exec_absolute_x(Self::exec_sre, bus),
-            (Rts, Implied) => self.exec_rts(bus),
-            (Adc, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_adc, bus),
-            (Rra, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_rra, bus),
-            (Adc, Zero) => self.exec_zero(Self::exec_adc, bus),
-            (Ror, Zero) => self.exec_zero(Self::exec_ror, bus),
-            (Rra, Zero) => self.exec_zero(Self::exec_rra, bus),
-            (Pla, Implied) => self.exec_pull(Self::exec_pla, bus),
-            (Adc, Immediate) => self.exec_immediate(Self::exec_adc, bus),
-            (Ror, Accumulator) => self.exec_accumulator(Self::exec_ror, bus),
-            (Arr, Immediate) => self.exec_immediate(Self::exec_arr, bus),
-            (Jmp, Indirect) => self.exec_indirect(Self::exec_jmp, bus),
-            (Adc, Absolute) => self.exec_absolute(Self::exec_adc, bus),
-            (Ror, Absolute) => self.exec_absolute(Self::exec_ror, bus),
-            (Rra, Absolute) => self.exec_absolute(Self::exec_rra, bus),
-            (Bvs, Relative) => self.exec_relative(Self::exec_bvs, bus),
-            (Adc, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_adc, bus),
-            (Rra, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_rra, bus),
-            (Adc, ZeroX) => self.exec_zero_x(Self::exec_adc, bus),
-            (Ror, ZeroX) => self.exec_zero_x(Self::exec_ror, bus),
-            (Rra, ZeroX) => self.exec_zero_x(Self::exec_rra, bus),
-            (Sei, Implied) => self.exec_implied(Self::exec_sei, bus),
-            (Adc, AbsoluteY) => self.exec_absolute_y(Self::exec_adc, bus),
-            (Rra, AbsoluteY) => self.exec_absolute_y(Self::exec_rra, bus),
-            (Adc, AbsoluteX) => self.exec_absolute_x(Self::exec_adc, bus),
-            (Ror, AbsoluteX) => self.exec_absolute_x(Self::exec_ror, bus),
-            (Rra, AbsoluteX) => self.exec_absolute_x(Self::exec_rra, bus),
-            (Nop, Immediate) => self.exec_immediate(Self::exec_nop, bus),
-            (Sta, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_sta, bus),
-            (Sax, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_sax, bus),
-            (Sty, Zero) => self.exec_zero(Self::exec_sty, bus),
-            (Sta, Zero) => self.exec_zero(Self::exec_sta, bus),
-            (Stx, Zero) => self.exec_zero(Self::exec_stx, bus),
-            (Sax, Zero) => self.exec_zero(Self::exec_sax, bus),
-            (Dey, Implied) => self.exec_implied(Self::exec_dey, bus),
-            (Txa, Implied) => self.exec_implied(Self::exec_txa, bus),
-            (Ane, Immediate) => self.exec_immediate(Self::exec_ane, bus),
-            (Sty, Absolute) => self.exec_absolute(Self::exec_sty, bus),
-            (Sta, Absolute) => self.exec_absolute(Self::exec_sta, bus),
-            (Stx, Absolute) => self.exec_absolute(Self::exec_stx, bus),
-            (Sax, Absolute) => self.exec_absolute(Self::exec_sax, bus),
-            (Bcc, Relative) => self.exec_relative(Self::exec_bcc, bus),
-            (Sta, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_sta, bus),
-            (Sha, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_sha, bus),
-            (Sty, ZeroX) => self.exec_zero_x(Self::exec_sty, bus),
-            (Sta, ZeroX) => self.exec_zero_x(Self::exec_sta, bus),
-            (Stx, ZeroY) => self.exec_zero_y(Self::exec_stx, bus),
-            (Sax, ZeroY) => self.exec_zero_y(Self::exec_sax, bus),
-            (Tya, Implied) => self.exec_implied(Self::exec_tya, bus),
-            (Sta, AbsoluteY) => self.exec_absolute_y(Self::exec_sta, bus),
-            (Txs, Implied) => self.exec_implied(Self::exec_txs, bus),
-            (Tas, AbsoluteY) => self.exec_absolute_y(Self::exec_tas, bus),
-            (Shy, AbsoluteX) => self.exec_absolute_x(Self::exec_shy, bus),
-            (Sta, AbsoluteX) => self.exec_absolute_x(Self::exec_sta, bus),
-            (Shx, AbsoluteY) => self.exec_absolute_y(Self::exec_shx, bus),
-            (Sha, AbsoluteY) => self.exec_absolute_y(Self::exec_sha, bus),
-            (Ldy, Immediate) => self.exec_immediate(Self::exec_ldy, bus),
-            (Lda, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_lda, bus),
-            (Ldx, Immediate) => self.exec_immediate(Self::exec_ldx, bus),
-            (Lax, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_lax, bus),
-            (Ldy, Zero) => self.exec_zero(Self::exec_ldy, bus),
-            (Lda, Zero) => self.exec_zero(Self::exec_lda, bus),
-            (Ldx, Zero) => self.exec_zero(Self::exec_ldx, bus),
-            (Lax, Zero) => self.exec_zero(Self::exec_lax, bus),
-            (Tay, Implied) => self.exec_implied(Self::exec_tay, bus),
-            (Lda, Immediate) => self.exec_immediate(Self::exec_lda, bus),
-            (Tax, Implied) => self.exec_implied(Self::exec_tax, bus),
-            (Lxa, Immediate) => self.exec_immediate(Self::exec_lxa, bus),
-            (Ldy, Absolute) => self.exec_absolute(Self::exec_ldy, bus),
-            (Lda, Absolute) => self.exec_absolute(Self::exec_lda, bus),
-            (Ldx, Absolute) => self.exec_absolute(Self::exec_ldx, bus),
-            (Lax, Absolute) => self.exec_absolute(Self::exec_lax, bus),
-            (Bcs, Relative) => self.exec_relative(Self::exec_bcs, bus),
-            (Lda, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_lda, bus),
-            (Lax, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_lax, bus),
-            (Ldy, ZeroX) => self.exec_zero_x(Self::exec_ldy, bus),
-            (Lda, ZeroX) => self.exec_zero_x(Self::exec_lda, bus),
-            (Ldx, ZeroY) => self.exec_zero_y(Self::exec_ldx, bus),
-            (Lax, ZeroY) => self.exec_zero_y(Self::exec_lax, bus),
-            (Clv, Implied) => self.exec_implied(Self::exec_clv, bus),
-            (Lda, AbsoluteY) => self.exec_absolute_y(Self::exec_lda, bus),
-            (Tsx, Implied) => self.exec_implied(Self::exec_tsx, bus),
-            (Las, AbsoluteY) => self.exec_absolute_y(Self::exec_las, bus),
-            (Ldy, AbsoluteX) => self.exec_absolute_x(Self::exec_ldy, bus),
-            (Lda, AbsoluteX) => self.exec_absolute_x(Self::exec_lda, bus),
-            (Ldx, AbsoluteY) => self.exec_absolute_y(Self::exec_ldx, bus),
-            (Lax, AbsoluteY) => self.exec_absolute_y(Self::exec_lax, bus),
-            (Cpy, Immediate) => self.exec_immediate(Self::exec_cpy, bus),
-            (Cmp, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_cmp, bus),
-            (Dcp, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_dcp, bus),
-            (Cpy, Zero) => self.exec_zero(Self::exec_cpy, bus),
-            (Cmp, Zero) => self.exec_zero(Self::exec_cmp, bus),
-            (Dec, Zero) => self.exec_zero(Self::exec_dec, bus),
-            (Dcp, Zero) => self.exec_zero(Self::exec_dcp, bus),
-            (Iny, Implied) => self.exec_implied(Self::exec_iny, bus),
-            (Cmp, Immediate) => self.exec_immediate(Self::exec_cmp, bus),
-            (Dex, Implied) => self.exec_implied(Self::exec_dex, bus),
-            (Sbx, Immediate) => self.exec_immediate(Self::exec_sbx, bus),
-            (Cpy, Absolute) => self.exec_absolute(Self::exec_cpy, bus),
-            (Cmp, Absolute) => self.exec_absolute(Self::exec_cmp, bus),
-            (Dec, Absolute) => self.exec_absolute(Self::exec_dec, bus),
-            (Dcp, Absolute) => self.exec_absolute(Self::exec_dcp, bus),
-            (Bne, Relative) => self.exec_relative(Self::exec_bne, bus),
-            (Cmp, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_cmp, bus),
-            (Dcp, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_dcp, bus),
-            (Cmp, ZeroX) => self.exec_zero_x(Self::exec_cmp, bus),
-            (Dec, ZeroX) => self.exec_zero_x(Self::exec_dec, bus),
-            (Dcp, ZeroX) => self.exec_zero_x(Self::exec_dcp, bus),
-            (Cld, Implied) => self.exec_implied(Self::exec_cld, bus),
-            (Cmp, AbsoluteY) => self.exec_absolute_y(Self::exec_cmp, bus),
-            (Dcp, AbsoluteY) => self.exec_absolute_y(Self::exec_dcp, bus),
-            (Cmp, AbsoluteX) => self.exec_absolute_x(Self::exec_cmp, bus),
-            (Dec, AbsoluteX) => self.exec_absolute_x(Self::exec_dec, bus),
-            (Dcp, AbsoluteX) => self.exec_absolute_x(Self::exec_dcp, bus),
-            (Cpx, Immediate) => self.exec_immediate(Self::exec_cpx, bus),
-            (Sbc, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_sbc, bus),
-            (Isc, IndexedIndirect) => self.exec_indexed_indirect(Self::exec_isc, bus),
-            (Cpx, Zero) => self.exec_zero(Self::exec_cpx, bus),
-            (Sbc, Zero) => self.exec_zero(Self::exec_sbc, bus),
-            (Inc, Zero) => self.exec_zero(Self::exec_inc, bus),
-            (Isc, Zero) => self.exec_zero(Self::exec_isc, bus),
-            (Inx, Implied) => self.exec_implied(Self::exec_inx, bus),
-            (Sbc, Immediate) => self.exec_immediate(Self::exec_sbc, bus),
-            (Cpx, Absolute) => self.exec_absolute(Self::exec_cpx, bus),
-            (Sbc, Absolute) => self.exec_absolute(Self::exec_sbc, bus),
-            (Inc, Absolute) => self.exec_absolute(Self::exec_inc, bus),
-            (Isc, Absolute) => self.exec_absolute(Self::exec_isc, bus),
-            (Beq, Relative) => self.exec_relative(Self::exec_beq, bus),
-            (Sbc, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_sbc, bus),
-            (Isc, IndirectIndexed) => self.exec_indirect_indexed(Self::exec_isc, bus),
-            (Sbc, ZeroX) => self.exec_zero_x(Self::exec_sbc, bus),
-            (Inc, ZeroX) => self.exec_zero_x(Self::exec_inc, bus),
-            (Isc, ZeroX) => self.exec_zero_x(Self::exec_isc, bus),
-            (Sed, Implied) => self.exec_implied(Self::exec_sed, bus),
-            (Sbc, AbsoluteY) => self.exec_absolute_y(Self::exec_sbc, bus),
-            (Isc, AbsoluteY) => self.exec_absolute_y(Self::exec_isc, bus),
-            (Sbc, AbsoluteX) => self.exec_absolute_x(Self::exec_sbc, bus),
-            (Inc, AbsoluteX) => self.exec_absolute_x(Self::exec_inc, bus),
-            (Isc, AbsoluteX) => self.exec_absolute_x(Self::exec_isc, bus),
-            _ => unreachable!(),
-        }
-    }
-    fn latch_interrupts(&mut self, bus: &mut Bus) {
-        self.irq_scheduled = bus.irq();
-        self.nmi_scheduled |= !self.last_nmi && bus.nmi();
-
-        self.last_nmi = bus.nmi();
-    }
-
-    fn next(&mut self) {
-        self.cycle += 1;
-    }
-    fn skip(&mut self) {
-        self.cycle += 2;
-    }
-    fn goto(&mut self, cycle: u8) {
-        self.cycle = cycle;
-    }
-    fn fetch(&mut self, bus: &mut Bus) {
-        bus.read(self.core.pc);
-        self.core.pc = self.core.pc.wrapping_add(1);
-    }
-    fn push(&mut self, data: u8, bus: &mut Bus) {
-        bus.write(self.core.s as u16 | 0x100, data);
-        self.core.s = self.core.s.wrapping_sub(1);
-    }
-    fn push_brk(&mut self, data: u8, bus: &mut Bus) {
-        if self.brk.push() {
-            self.push(data, bus);
-        } else {
-            bus.read(self.core.s as u16 | 0x100);
-            self.core.s = self.core.s.wrapping_sub(1);
-        }
-    }
-    fn pull(&mut self, bus: &mut Bus) {
-        self.core.s = self.core.s.wrapping_add(1);
-        bus.read(self.core.s as u16 | 0x100);
-    }
-
-    fn sync(&mut self, bus: &mut Bus) {
-        bus.read_sync(self.core.pc);
-        self.goto(0);
-    }
-
-    fn exec_absolute(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
-            }
-            1 => {
-                self.addr = bus.data as u16;
-                self.fetch(bus);
-                self.next();
-            }
-            2 => {
-                self.addr |= (bus.data as u16) << 8;
-                if self.op.reads_operand() {
-                    bus.read(self.addr);
-                    self.next();
-                } else {
-                    op(self);
-                    bus.write(self.addr, self.data);
-                    self.goto(5);
-                }
+    fn step(&mut self, bus: &mut Bus) {
+        use Micro::*;
+        let micro = self.micro[self.cycle as usize];
+        self.next();
+        match micro {
+            Fetch => self.fetch(bus),
+            Peek => bus.read(self.core.pc),
+            PeekExec => {
+                bus.read(self.core.pc);
+                self.exec();
             }
-            3 => {
+            Sync => self.sync(bus),
+            ExecSync => {
                 self.data = bus.data;
-                if self.op.is_rmw() {
-                    bus.write(self.addr, self.data);
-                    op(self);
-                    self.next();
-                } else {
-                    op(self);
-                    if self.op.writes_operand() {
-                        bus.write(self.addr, self.data);
-                        self.skip();
-                    } else {
-                        self.sync(bus);
-                    }
-                }
-            }
-            4 => {
-                bus.write(self.addr, self.data);
-                self.next();
-            }
-            5 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_absolute_x(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        self.exec_absolute_index(op, self.core.x, bus);
-    }
-    fn exec_absolute_y(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        self.exec_absolute_index(op, self.core.y, bus);
-    }
-    fn exec_accumulator(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                self.next();
+                self.exec();
+                self.sync(bus);
             }
-            1 => {
+            ExecAccumulator => {
                 self.data = self.core.a;
-                op(self);
+                self.exec();
                 self.core.a = self.data;
                 self.sync(bus);
             }
-            _ => unreachable!(),
-        }
-    }
-    fn exec_immediate(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
-            }
-            1 => {
-                self.data = bus.data;
-                op(self);
-                self.sync(bus);
-            }
-            _ => unreachable!(),
-        }
-    }
-    fn exec_implied(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                op(self);
-                self.next();
-            }
-            1 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_indexed_indirect(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
+
+            FetchHigh => {
+                self.addr = bus.data as u16;
                 self.fetch(bus);
-                self.next()
-            }
-            1 => {
-                bus.read(bus.data as u16);
-                self.data = bus.data;
-                self.next();
             }
-            2 => {
-                self.data = self.data.wrapping_add(self.core.x);
-                bus.read(self.data as u16);
-                self.next();
-            }
-            3 => {
+            ReadZero => {
                 self.addr = bus.data as u16;
-                self.data = self.data.wrapping_add(1);
-                bus.read(self.data as u16);
-                self.next();
-            }
-            4 => {
-                self.addr |= (bus.data as u16) << 8;
-                if self.op.reads_operand() {
-                    bus.read(self.addr);
-                    self.next();
-                } else {
-                    op(self);
-                    bus.write(self.addr, self.data);
-                    self.goto(7);
-                }
-            }
-            5 => {
-                self.data = bus.data;
-                if self.op.is_rmw() {
-                    bus.write(self.addr, self.data);
-                    op(self);
-                    self.next();
-                } else {
-                    op(self);
-                    if self.op.writes_operand() {
-                        bus.write(self.addr, self.data);
-                        self.skip();
-                    } else {
-                        self.sync(bus);
-                    }
-                }
-            }
-            6 => {
-                bus.write(self.addr, self.data);
-                self.next();
-            }
-            7 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_indirect(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
+                bus.read(self.addr);
             }
-            1 => {
+            WriteZero => {
                 self.addr = bus.data as u16;
-                self.fetch(bus);
-                self.next();
+                self.exec();
+                bus.write(self.addr, self.data);
             }
-            2 => {
+            ReadAbsolute => {
                 self.addr |= (bus.data as u16) << 8;
                 bus.read(self.addr);
-                self.next();
             }
-            3 => {
-                let hi = self.addr & 0xFF00;
-                let lo = self.addr.wrapping_add(1) & 0x00FF;
-                bus.read(hi | lo);
-                self.addr = bus.data as u16;
-                self.next();
+            WriteAbsolute => {
+                self.addr |= (bus.data as u16) << 8;
+                self.exec();
+                bus.write(self.addr, self.data);
             }
-            4 => {
+            Jump => {
                 self.addr |= (bus.data as u16) << 8;
-                op(self);
+                self.exec();
                 self.sync(bus);
             }
-            _ => unreachable!(),
-        }
-    }
-    fn exec_indirect_indexed(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
+            ReadAddr => bus.read(self.addr),
+            ExecWrite => {
+                self.exec();
+                bus.write(self.addr, self.data);
             }
-            1 => {
+            Write => bus.write(self.addr, self.data),
+            Modify => {
                 self.data = bus.data;
-                bus.read(self.data as u16);
-                self.next();
-            }
-            2 => {
-                self.addr = bus.data as u16;
-                bus.read(self.data.wrapping_add(1) as u16);
-                self.next();
+                bus.write(self.addr, self.data);
+                self.exec();
             }
-            3 => {
-                self.addr |= (bus.data as u16) << 8;
-                let new = self.addr.wrapping_add(self.core.y as u16);
-                let c = self.addr & 0xFF00 != new & 0xFF00;
-                let without_wrap = self.addr & 0xFF00 | new & 0x00FF;
-                self.addr = new;
-                self.wrap = c;
-                bus.read(without_wrap);
 
-                let stall = (self.op.reads_operand() && c) || self.op.is_rmw();
-                if !stall {
-                    self.skip();
-                } else {
-                    self.next();
-                }
-            }
-            4 => {
-                bus.read(self.addr);
-                self.next();
+            IndexZero(index) => {
+                bus.read(bus.data as u16);
+                self.addr = bus.data.wrapping_add(self.index(index)) as u16;
             }
-            5 => {
-                self.data = bus.data;
-                if self.op.is_rmw() {
-                    bus.write(self.addr, self.data);
-                    op(self);
+            Index(index) => self.index_absolute(index, bus),
+            IndexSkip(index) => {
+                self.index_absolute(index, bus);
+                if !self.wrap {
                     self.next();
-                } else {
-                    op(self);
-                    if self.op.writes_operand() {
-                        bus.write(self.addr, self.data);
-                        self.skip();
-                    } else {
-                        self.sync(bus);
-                    }
                 }
             }
-            6 => {
-                bus.write(self.addr, self.data);
-                self.next();
-            }
-            7 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_pull(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                self.next();
-            }
-            1 => {
-                bus.read(self.core.s as u16 | 0x100);
-                self.next();
-            }
-            2 => {
-                self.pull(bus);
-                self.next();
-            }
-            3 => {
+            ReadPointer => {
                 self.data = bus.data;
-                op(self);
-                self.sync(bus);
+                bus.read(self.data as u16);
             }
-            _ => unreachable!(),
-        }
-    }
-    fn exec_push(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                self.next();
+            IndexPointer => {
+                self.data = self.data.wrapping_add(self.core.x);
+                bus.read(self.data as u16);
             }
-            1 => {
-                op(self);
-                self.push(self.data, bus);
-                self.next();
+            ReadPointerHigh => {
+                self.addr = bus.data as u16;
+                bus.read(self.data.wrapping_add(1) as u16);
             }
-            2 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_relative(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
+            ReadIndirectHigh => {
+                let hi = self.addr & 0xFF00;
+                let lo = self.addr.wrapping_add(1) & 0x00FF;
+                bus.read(hi | lo);
+                self.addr = bus.data as u16;
             }
-            1 => {
+
+            Branch => {
                 let offset = bus.data as i8 as i16;
                 let old = self.core.pc;
                 let new = old.wrapping_add_signed(offset);
                 let wrap = old & 0xFF00 != new & 0xFF00;
                 self.addr = new;
 
-                op(self);
+                self.exec();
                 let take = self.data != 0;
 
                 if take && wrap {
                     bus.read(self.core.pc);
-                    self.next();
                 } else if take {
                     bus.read(self.core.pc);
-                    self.skip();
+                    self.next();
                 } else {
                     self.sync(bus);
                 }
             }
-            2 => {
+            BranchFixup => {
                 let without_wrap = self.core.pc & 0xFF00 | self.addr & 0x00FF;
                 bus.read(without_wrap);
-                self.next();
             }
-            3 => {
+            TakeBranch => {
                 self.core.pc = self.addr;
                 self.sync(bus);
             }
-            _ => unreachable!(),
-        }
-    }
-    fn exec_zero(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
-            }
-            1 => {
-                self.addr = bus.data as u16;
-                if self.op.reads_operand() {
-                    bus.read(self.addr);
-                    self.next();
-                } else {
-                    op(self);
-                    bus.write(self.addr, self.data);
-                    self.goto(4);
-                }
+
+            ExecPush => {
+                self.exec();
+                self.push(self.data, bus);
             }
-            2 => {
+            PeekStack => bus.read(self.core.s as u16 | 0x100),
+            Pull => self.pull(bus),
+            PeekStackLow => {
                 self.data = bus.data;
-                if self.op.is_rmw() {
-                    bus.write(self.addr, self.data);
-                    op(self);
-                    self.next();
-                } else {
-                    op(self);
-                    if self.op.writes_operand() {
-                        bus.write(self.addr, self.data);
-                        self.skip();
-                    } else {
-                        self.sync(bus);
-                    }
-                }
+                bus.read(self.core.s as u16 | 0x100);
             }
-            3 => {
-                bus.write(self.addr, self.data);
-                self.next();
+            PushPch => self.push(self.core.pc.to_le_bytes()[1], bus),
+            PushPcl => self.push(self.core.pc.to_le_bytes()[0], bus),
+            JumpSubroutine => {
+                self.core.pc = u16::from_le_bytes([self.data, bus.data]);
+                self.sync(bus);
             }
-            4 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_zero_x(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        self.exec_zero_index(op, self.core.x, bus);
-    }
-    fn exec_zero_y(&mut self, op: fn(&mut Self), bus: &mut Bus) {
-        self.exec_zero_index(op, self.core.y, bus);
-    }
-
-    fn exec_absolute_index(&mut self, op: fn(&mut Self), x: u8, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
+            PullLow => {
+                self.data = bus.data;
+                self.pull(bus);
             }
-            1 => {
-                self.addr = bus.data as u16;
+            Return => {
+                self.core.pc = u16::from_le_bytes([self.data, bus.data]);
                 self.fetch(bus);
-                self.next();
             }
-            2 => {
-                self.addr |= (bus.data as u16) << 8;
-                let new = self.addr.wrapping_add(x as u16);
-                let c = self.addr & 0xFF00 != new & 0xFF00;
-                let without_wrap = self.addr & 0xFF00 | new & 0x00FF;
-                self.addr = new;
-                self.wrap = c;
-                bus.read(without_wrap);
-
-                let stall = (self.op.reads_operand() && c) || self.op.is_rmw();
-                if !stall {
-                    self.skip();
-                } else {
-                    self.next();
-                }
+            PullP => {
+                self.core.p = P::from_pull_byte(bus.data);
+                self.pull(bus);
             }
-            3 => {
-                bus.read(self.addr);
-                self.next();
+            PullPcl => {
+                self.core.pc = bus.data as u16;
+                self.pull(bus);
             }
-            4 => {
-                self.data = bus.data;
-                if self.op.is_rmw() {
-                    bus.write(self.addr, self.data);
-                    op(self);
-                    self.next();
+            ReturnInterrupt => {
+                self.core.pc |= (bus.data as u16) << 8;
+                self.sync(bus);
+            }
+
+            Interrupt => {
+                if self.brk.skip_id_byte() {
+                    self.fetch(bus);
                 } else {
-                    op(self);
-                    if self.op.writes_operand() {
-                        bus.write(self.addr, self.data);
-                        self.skip();
-                    } else {
-                        self.sync(bus);
-                    }
+                    bus.read(self.core.pc);
                 }
             }
-            5 => {
-                bus.write(self.addr, self.data);
-                self.next();
-            }
-            6 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
-    fn exec_zero_index(&mut self, op: fn(&mut Self), x: u8, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
-            }
-            1 => {
-                bus.read(bus.data as u16);
-                self.addr = bus.data.wrapping_add(x) as u16;
-                self.next();
+            InterruptPch => self.push_brk((self.core.pc >> 8) as u8, bus),
+            InterruptPcl => self.push_brk(self.core.pc as u8, bus),
+            InterruptP => self.push_brk(self.core.p.to_push_byte(self.brk.b()), bus),
+            Vector => bus.read(self.brk.vector()),
+            VectorHigh => {
+                self.core.pc = bus.data as u16;
+                bus.read(self.brk.vector() + 1);
             }
-            2 => {
-                if self.op.reads_operand() {
-                    bus.read(self.addr);
-                    self.next();
-                } else {
-                    op(self);
-                    bus.write(self.addr, self.data);
-                    self.goto(5);
-                }
+            VectorSync => {
+                self.core.pc |= (bus.data as u16) << 8;
+                self.core.p.set_i(true);
+                self.sync(bus);
             }
-            3 => {
-                self.data = bus.data;
-                op(self);
 
-                if self.op.is_rmw() {
-                    bus.write(self.addr, bus.data);
-                    self.next();
-                } else {
-                    self.sync(bus);
-                }
+            ReadAt(addr) => bus.read(addr),
+            Jam => {
+                bus.read(0xFFFF);
+                self.cycle -= 1;
             }
-            4 => {
-                bus.write(self.addr, self.data);
-                self.next();
+            Resume => {
+                bus.read_sync(self.core.pc);
+                self.cycle = 0;
             }
-            5 => self.sync(bus),
-            _ => unreachable!(),
         }
     }
+    fn latch_interrupts(&mut self, bus: &mut Bus) {
+        self.irq_scheduled = bus.irq();
+        self.nmi_scheduled |= !self.last_nmi && bus.nmi();
+
+        self.last_nmi = bus.nmi();
+    }
+
+    fn next(&mut self) {
+        self.cycle += 1;
+    }
+    fn fetch(&mut self, bus: &mut Bus) {
+        bus.read(self.core.pc);
+        self.core.pc = self.core.pc.wrapping_add(1);
+    }
+    fn push(&mut self, data: u8, bus: &mut Bus) {
+        bus.write(self.core.s as u16 | 0x100, data);
+        self.core.s = self.core.s.wrapping_sub(1);
+    }
+    fn push_brk(&mut self, data: u8, bus: &mut Bus) {
+        if self.brk.push() {
+            self.push(data, bus);
+        } else {
+            bus.read(self.core.s as u16 | 0x100);
+            self.core.s = self.core.s.wrapping_sub(1);
+        }
+    }
+    fn pull(&mut self, bus: &mut Bus) {
+        self.core.s = self.core.s.wrapping_add(1);
+        bus.read(self.core.s as u16 | 0x100);
+    }
+
+    fn sync(&mut self, bus: &mut Bus) {
+        bus.read_sync(self.core.pc);
+        self.cycle = 0;
+
+        // CLI, SEI and PLP poll for interrupts before they update I,
+        // so their new mask only applies after the next instruction.
+        if !self.op.delays_irq_mask() {
+            self.irq_masked = self.core.p.i();
+        }
+    }
+    fn exec(&mut self) {
+        use Op::*;
+        match self.op {
+            Adc => self.exec_adc(),
+            Alr => self.exec_alr(),
+            Anc => self.exec_anc(),
+            And => self.exec_and(),
+            Ane => self.exec_ane(),
+            Arr => self.exec_arr(),
+            Asl => self.exec_asl(),
+            Bcc => self.exec_bcc(),
+            Bcs => self.exec_bcs(),
+            Beq => self.exec_beq(),
+            Bit => self.exec_bit(),
+            Bmi => self.exec_bmi(),
+            Bne => self.exec_bne(),
+            Bpl => self.exec_bpl(),
+            Bvc => self.exec_bvc(),
+            Bvs => self.exec_bvs(),
+            Clc => self.exec_clc(),
+            Cld => self.exec_cld(),
+            Cli => self.exec_cli(),
+            Clv => self.exec_clv(),
+            Cmp => self.exec_cmp(),
+            Cpx => self.exec_cpx(),
+            Cpy => self.exec_cpy(),
+            Dcp => self.exec_dcp(),
+            Dec => self.exec_dec(),
+            Dex => self.exec_dex(),
+            Dey => self.exec_dey(),
+            Eor => self.exec_eor(),
+            Inc => self.exec_inc(),
+            Inx => self.exec_inx(),
+            Iny => self.exec_iny(),
+            Isc => self.exec_isc(),
+            Jmp => self.exec_jmp(),
+            Las => self.exec_las(),
+            Lax => self.exec_lax(),
+            Lda => self.exec_lda(),
+            Ldx => self.exec_ldx(),
+            Ldy => self.exec_ldy(),
+            Lsr => self.exec_lsr(),
+            Lxa => self.exec_lxa(),
+            Nop => self.exec_nop(),
+            Ora => self.exec_ora(),
+            Pha => self.exec_pha(),
+            Php => self.exec_php(),
+            Pla => self.exec_pla(),
+            Plp => self.exec_plp(),
+            Rla => self.exec_rla(),
+            Rol => self.exec_rol(),
+            Ror => self.exec_ror(),
+            Rra => self.exec_rra(),
+            Sax => self.exec_sax(),
+            Sbc => self.exec_sbc(),
+            Sbx => self.exec_sbx(),
+            Sec => self.exec_sec(),
+            Sed => self.exec_sed(),
+            Sei => self.exec_sei(),
+            Sha => self.exec_sha(),
+            Shx => self.exec_shx(),
+            Shy => self.exec_shy(),
+            Slo => self.exec_slo(),
+            Sre => self.exec_sre(),
+            Sta => self.exec_sta(),
+            Stx => self.exec_stx(),
+            Sty => self.exec_sty(),
+            Tas => self.exec_tas(),
+            Tax => self.exec_tax(),
+            Tay => self.exec_tay(),
+            Tsx => self.exec_tsx(),
+            Txa => self.exec_txa(),
+            Txs => self.exec_txs(),
+            Tya => self.exec_tya(),
+            Brk | Jam | Jsr | Rti | Rts => unreachable!(),
+        }
+    }
+    fn index(&self, index: Reg) -> u8 {
+        match index {
+            Reg::X => self.core.x,
+            Reg::Y => self.core.y,
+        }
+    }
+    fn index_absolute(&mut self, index: Reg, bus: &mut Bus) {
+        self.addr |= (bus.data as u16) << 8;
+        let new = self.addr.wrapping_add(self.index(index) as u16);
+        let c = self.addr & 0xFF00 != new & 0xFF00;
+        let without_wrap = self.addr & 0xFF00 | new & 0x00FF;
+        self.addr = new;
+        self.wrap = c;
+        bus.read(without_wrap);
+    }
 
     fn exec_adc(&mut self) {
         self.core.exec_adc(self.data);
@@ -956,45 +637,6 @@ impl M6502 {
     fn exec_bpl(&mut self) {
         self.data = self.core.exec_bpl() as u8;
     }
-    fn exec_brk(&mut self, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                if self.brk.skip_id_byte() {
-                    self.fetch(bus);
-                } else {
-                    bus.read(self.core.pc);
-                }
-                self.next();
-            }
-            1 => {
-                self.push_brk((self.core.pc >> 8) as u8, bus);
-                self.next();
-            }
-            2 => {
-                self.push_brk(self.core.pc as u8, bus);
-                self.next();
-            }
-            3 => {
-                self.push_brk(self.core.p.to_push_byte(self.brk.b()), bus);
-                self.next();
-            }
-            4 => {
-                bus.read(self.brk.vector());
-                self.next();
-            }
-            5 => {
-                self.core.pc = bus.data as u16;
-                bus.read(self.brk.vector() + 1);
-                self.next();
-            }
-            6 => {
-                self.core.pc |= (bus.data as u16) << 8;
-                self.core.p.set_i(true);
-                self.sync(bus);
-            }
-            _ => unreachable!(),
-        }
-    }
     fn exec_bvc(&mut self) {
         self.data = self.core.exec_bvc() as u8;
     }
@@ -1008,6 +650,7 @@ impl M6502 {
         self.core.exec_cld();
     }
     fn exec_cli(&mut self) {
+        self.irq_masked = self.core.p.i();
         self.core.exec_cli();
     }
     fn exec_clv(&mut self) {
@@ -1049,63 +692,9 @@ impl M6502 {
     fn exec_isc(&mut self) {
         self.data = self.core.exec_isc(self.data);
     }
-    fn exec_jam(&mut self, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                self.next();
-            }
-            1 => {
-                bus.read(0xFFFF);
-                self.next();
-            }
-            2 => {
-                bus.read(0xFFFE);
-                self.next();
-            }
-            3 => {
-                bus.read(0xFFFE);
-                self.next();
-            }
-            4.. => {
-                bus.read(0xFFFF);
-                self.next();
-            }
-        }
-    }
     fn exec_jmp(&mut self) {
         self.core.pc = self.addr;
     }
-    fn exec_jsr(&mut self, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                self.fetch(bus);
-                self.next();
-            }
-            1 => {
-                self.data = bus.data;
-                bus.read(self.core.s as u16 | 0x100);
-                self.next();
-            }
-            2 => {
-                self.push(self.core.pc.to_le_bytes()[1], bus);
-                self.next();
-            }
-            3 => {
-                self.push(self.core.pc.to_le_bytes()[0], bus);
-                self.next();
-            }
-            4 => {
-                self.fetch(bus);
-                self.next();
-            }
-            5 => {
-                self.core.pc = u16::from_le_bytes([self.data, bus.data]);
-                self.sync(bus);
-            }
-            _ => unreachable!(),
-        }
-    }
     fn exec_las(&mut self) {
         self.core.exec_las(self.data);
     }
@@ -1141,6 +730,7 @@ impl M6502 {
         self.core.exec_pla(self.data);
     }
     fn exec_plp(&mut self) {
+        self.irq_masked = self.core.p.i();
         self.core.exec_plp(self.data);
     }
     fn exec_rla(&mut self) {
@@ -1155,65 +745,6 @@ impl M6502 {
     fn exec_rra(&mut self) {
         self.data = self.core.exec_rra(self.data);
     }
-    fn exec_rti(&mut self, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                self.next();
-            }
-            1 => {
-                bus.read(self.core.s as u16 | 0x100);
-                self.next();
-            }
-            2 => {
-                self.pull(bus);
-                self.next();
-            }
-            3 => {
-                self.core.p = P::from_pull_byte(bus.data);
-                self.pull(bus);
-                self.next();
-            }
-            4 => {
-                self.core.pc = bus.data as u16;
-                self.pull(bus);
-                self.next();
-            }
-            5 => {
-                self.core.pc |= (bus.data as u16) << 8;
-                self.sync(bus);
-            }
-            _ => unreachable!(),
-        }
-    }
-    fn exec_rts(&mut self, bus: &mut Bus) {
-        match self.cycle {
-            0 => {
-                bus.read(self.core.pc);
-                self.next();
-            }
-            1 => {
-                bus.read(self.core.s as u16 | 0x100);
-                self.next();
-            }
-            2 => {
-                self.pull(bus);
-                self.next();
-            }
-            3 => {
-                self.data = bus.data;
-                self.pull(bus);
-                self.next();
-            }
-            4 => {
-                self.core.pc = u16::from_le_bytes([self.data, bus.data]);
-                self.fetch(bus);
-                self.next();
-            }
-            5 => self.sync(bus),
-            _ => unreachable!(),
-        }
-    }
     fn exec_sax(&mut self) {
         self.data = self.core.a & self.core.x;
     }
@@ -1230,6 +761,7 @@ impl M6502 {
         self.core.exec_sed();
     }
     fn exec_sei(&mut self) {
+        self.irq_masked = self.core.p.i();
         self.core.exec_sei();
     }
     fn exec_sha(&mut self) {
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use crate::{core::Core, memory::Memory};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    Truncated,
+    OutOfRange,
+    Syntax { line: usize },
+    Checksum { line: usize },
+    BadHeader,
+    UnsupportedMapper(u16),
+    UndefinedSymbol(String),
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "file is truncated"),
+            Self::OutOfRange => write!(f, "data lies outside the 64K address space"),
+            Self::Syntax { line } => write!(f, "line {line}: malformed record"),
+            Self::Checksum { line } => write!(f, "line {line}: checksum mismatch"),
+            Self::BadHeader => write!(f, "unrecognised file header"),
+            Self::UnsupportedMapper(mapper) => write!(f, "mapper {mapper} is not supported"),
+            Self::UndefinedSymbol(name) => write!(f, "undefined symbol {name}"),
+        }
+    }
+}
+impl std::error::Error for LoadError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub addr: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    pub segments: Vec<Segment>,
+    pub start: Option<u16>,
+}
+impl Image {
+    pub fn raw(data: &[u8], addr: u16) -> Result<Self, LoadError> {
+        let mut image = Self::default();
+        image.push(addr as u32, data)?;
+        Ok(image)
+    }
+    // A C64 PRG file: the load address, then the data.
+    pub fn prg(data: &[u8]) -> Result<Self, LoadError> {
+        let [lo, hi, data @ ..] = data else {
+            return Err(LoadError::Truncated);
+        };
+        Self::raw(data, u16::from_le_bytes([*lo, *hi]))
+    }
+
+    pub fn intel_hex(text: &str) -> Result<Self, LoadError> {
+        let mut image = Self::default();
+        let mut base = 0;
+        for (line, record) in records(text) {
+            let bytes = record
+                .strip_prefix(':')
+                .and_then(hex_bytes)
+                .filter(|b| b.len() >= 5 && b.len() == b[0] as usize + 5)
+                .ok_or(LoadError::Syntax { line })?;
+            if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+                return Err(LoadError::Checksum { line });
+            }
+
+            let addr = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+            let data = &bytes[4..bytes.len() - 1];
+            let word = || u16::from_be_bytes([data[0], data[1]]) as u32;
+            let long = || u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            match (bytes[3], data.len()) {
+                (0x00, _) => image.push(base + addr, data)?,
+                (0x01, _) => break,
+                (0x02, 2) => base = word() << 4,
+                (0x04, 2) => base = word() << 16,
+                (0x03, 4) => image.set_start((long() >> 16 << 4) + (long() & 0xFFFF))?,
+                (0x05, 4) => image.set_start(long())?,
+                _ => return Err(LoadError::Syntax { line }),
+            }
+        }
+        Ok(image)
+    }
+
+    pub fn srec(text: &str) -> Result<Self, LoadError> {
+        let mut image = Self::default();
+        for (line, record) in records(text) {
+            let syntax = LoadError::Syntax { line };
+            let mut chars = record.chars();
+            let (Some('S'), Some(kind)) = (chars.next(), chars.next()) else {
+                return Err(syntax);
+            };
+            let bytes = hex_bytes(chars.as_str())
+                .filter(|b| !b.is_empty() && b.len() == b[0] as usize + 1)
+                .ok_or(syntax.clone())?;
+            if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0xFF {
+                return Err(LoadError::Checksum { line });
+            }
+
+            let addr_len = match kind {
+                '0' | '1' | '5' | '9' => 2,
+                '2' | '6' | '8' => 3,
+                '3' | '7' => 4,
+                _ => return Err(syntax),
+            };
+            let body = &bytes[1..bytes.len() - 1];
+            if body.len() < addr_len {
+                return Err(syntax);
+            }
+            let (addr, data) = body.split_at(addr_len);
+            let addr = addr.iter().fold(0, |addr, &b| addr << 8 | b as u32);
+            match kind {
+                '1' | '2' | '3' => image.push(addr, data)?,
+                '7' | '8' | '9' => image.set_start(addr)?,
+                _ => (),
+            }
+        }
+        Ok(image)
+    }
+
+    pub fn install(&self, mem: &mut impl Memory) {
+        for segment in &self.segments {
+            for (i, &data) in segment.data.iter().enumerate() {
+                mem.poke(segment.addr.wrapping_add(i as u16), data);
+            }
+        }
+    }
+    // Installs the image and, if it names one, jumps to its start address.
+    pub fn boot(&self, mem: &mut impl Memory, core: &mut Core) {
+        self.install(mem);
+        if let Some(start) = self.start {
+            core.pc = start;
+        }
+    }
+
+    fn push(&mut self, addr: u32, data: &[u8]) -> Result<(), LoadError> {
+        if addr as usize + data.len() > 0x10000 {
+            return Err(LoadError::OutOfRange);
+        }
+        // Records that continue the previous one join its segment.
+        match self.segments.last_mut() {
+            Some(last) if last.addr as usize + last.data.len() == addr as usize => {
+                last.data.extend_from_slice(data);
+            }
+            _ => self.segments.push(Segment {
+                addr: addr as u16,
+                data: data.to_vec(),
+            }),
+        }
+        Ok(())
+    }
+    fn set_start(&mut self, addr: u32) -> Result<(), LoadError> {
+        self.start = Some(u16::try_from(addr).map_err(|_| LoadError::OutOfRange)?);
+        Ok(())
+    }
+}
+
+fn records(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
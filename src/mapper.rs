@@ -0,0 +1,382 @@
+use crate::memory::Memory;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Rom,
+    Ram,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    SingleLower,
+    SingleUpper,
+    FourScreen,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Window {
+    start: u16,
+    size: usize,
+    source: Source,
+    bank: usize,
+}
+impl Window {
+    fn contains(&self, addr: u16) -> bool {
+        addr >= self.start && ((addr - self.start) as usize) < self.size
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Banks {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    windows: Vec<Window>,
+}
+impl Banks {
+    pub fn new(rom: impl Into<Vec<u8>>) -> Self {
+        Self {
+            rom: rom.into(),
+            ram: Vec::new(),
+            windows: Vec::new(),
+        }
+    }
+    pub fn with_ram(mut self, size: usize) -> Self {
+        self.ram = vec![0; size];
+        self
+    }
+    pub fn with_window(mut self, start: u16, size: usize, source: Source, bank: usize) -> Self {
+        assert!(size > 0, "bank windows cannot be empty");
+        self.windows.push(Window {
+            start,
+            size,
+            source,
+            bank: 0,
+        });
+        let window = self.windows.len() - 1;
+        self.select(window, bank);
+        self
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    pub fn windows(&self) -> usize {
+        self.windows.len()
+    }
+    pub fn bank_count(&self, window: usize) -> usize {
+        let window = self.windows[window];
+        let len = self.source(window.source).len();
+        (len / window.size).max(1)
+    }
+    pub fn selected(&self, window: usize) -> usize {
+        self.windows[window].bank
+    }
+    pub fn select(&mut self, window: usize, bank: usize) {
+        let bank = bank % self.bank_count(window);
+        self.windows[window].bank = bank;
+    }
+
+    pub fn read(&self, addr: u16) -> Option<u8> {
+        let window = self.windows.iter().find(|w| w.contains(addr))?;
+        let source = self.source(window.source);
+        if source.is_empty() {
+            return None;
+        }
+        let offset = window.bank * window.size + (addr - window.start) as usize;
+        Some(source[offset % source.len()])
+    }
+    pub fn write(&mut self, addr: u16, data: u8) {
+        let Some(window) = self.windows.iter().find(|w| w.contains(addr)) else {
+            return;
+        };
+        if window.source != Source::Ram || self.ram.is_empty() {
+            return;
+        }
+        let offset = window.bank * window.size + (addr - window.start) as usize;
+        let len = self.ram.len();
+        self.ram[offset % len] = data;
+    }
+
+    fn source(&self, source: Source) -> &[u8] {
+        match source {
+            Source::Rom => &self.rom,
+            Source::Ram => &self.ram,
+        }
+    }
+}
+
+pub trait Mapper {
+    fn is_register(&self, addr: u16) -> bool;
+    fn write_register(&mut self, banks: &mut Banks, addr: u16, data: u8);
+
+    fn read(&mut self, banks: &mut Banks, addr: u16) -> Option<u8> {
+        banks.read(addr)
+    }
+    fn write(&mut self, banks: &mut Banks, addr: u16, data: u8) {
+        banks.write(addr, data);
+    }
+    fn clock(&mut self, _banks: &mut Banks) {}
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+}
+
+impl<M: Mapper + ?Sized> Mapper for Box<M> {
+    fn is_register(&self, addr: u16) -> bool {
+        (**self).is_register(addr)
+    }
+    fn write_register(&mut self, banks: &mut Banks, addr: u16, data: u8) {
+        (**self).write_register(banks, addr, data);
+    }
+    fn read(&mut self, banks: &mut Banks, addr: u16) -> Option<u8> {
+        (**self).read(banks, addr)
+    }
+    fn write(&mut self, banks: &mut Banks, addr: u16, data: u8) {
+        (**self).write(banks, addr, data);
+    }
+    fn clock(&mut self, banks: &mut Banks) {
+        (**self).clock(banks);
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        (**self).mirroring()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cartridge<M> {
+    pub banks: Banks,
+    pub mapper: M,
+}
+impl<M: Mapper> Cartridge<M> {
+    pub fn new(banks: Banks, mapper: M) -> Self {
+        Self { banks, mapper }
+    }
+
+    pub fn clock(&mut self) {
+        self.mapper.clock(&mut self.banks);
+    }
+    pub fn mirroring(&self) -> Option<Mirroring> {
+        self.mapper.mirroring()
+    }
+}
+impl<M: Mapper + 'static> Cartridge<M> {
+    pub fn boxed(self) -> Cartridge<Box<dyn Mapper>> {
+        Cartridge::new(self.banks, Box::new(self.mapper))
+    }
+}
+impl<M: Mapper> Memory for Cartridge<M> {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.mapper.read(&mut self.banks, addr)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        if self.mapper.is_register(addr) {
+            self.mapper.write_register(&mut self.banks, addr, data);
+        } else {
+            self.mapper.write(&mut self.banks, addr, data);
+        }
+    }
+}
+
+const PRG_RAM: u16 = 0x6000;
+const PRG_ROM: u16 = 0x8000;
+const KB8: usize = 0x2000;
+const KB16: usize = 0x4000;
+
+fn prg_banks(prg: Vec<u8>, prg_ram: usize) -> Banks {
+    let banks = Banks::new(prg).with_ram(prg_ram);
+    if prg_ram != 0 {
+        banks.with_window(PRG_RAM, KB8, Source::Ram, 0)
+    } else {
+        banks
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Nrom;
+impl Mapper for Nrom {
+    fn is_register(&self, _: u16) -> bool {
+        false
+    }
+    fn write_register(&mut self, _: &mut Banks, _: u16, _: u8) {}
+}
+impl Cartridge<Nrom> {
+    pub fn nrom(prg: Vec<u8>, prg_ram: usize) -> Self {
+        let banks = prg_banks(prg, prg_ram)
+            .with_window(PRG_ROM, KB16, Source::Rom, 0)
+            .with_window(0xC000, KB16, Source::Rom, 1);
+        Self::new(banks, Nrom)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Uxrom {
+    window: usize,
+}
+impl Mapper for Uxrom {
+    fn is_register(&self, addr: u16) -> bool {
+        addr >= PRG_ROM
+    }
+    fn write_register(&mut self, banks: &mut Banks, _: u16, data: u8) {
+        banks.select(self.window, data as usize);
+    }
+}
+impl Cartridge<Uxrom> {
+    pub fn uxrom(prg: Vec<u8>, prg_ram: usize) -> Self {
+        let last = prg.len() / KB16;
+        let banks = prg_banks(prg, prg_ram);
+        let window = banks.windows();
+        let banks = banks
+            .with_window(PRG_ROM, KB16, Source::Rom, 0)
+            .with_window(0xC000, KB16, Source::Rom, last.saturating_sub(1));
+        Self::new(banks, Uxrom { window })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mmc1 {
+    window: usize,
+    shift: u8,
+    count: u8,
+    control: u8,
+    chr: [u8; 2],
+    prg: u8,
+}
+impl Mmc1 {
+    pub fn control(&self) -> u8 {
+        self.control
+    }
+    pub fn chr_banks(&self) -> [u8; 2] {
+        self.chr
+    }
+    pub fn prg_bank(&self) -> u8 {
+        self.prg
+    }
+    pub fn ram_enabled(&self) -> bool {
+        self.prg & 0x10 == 0
+    }
+
+    fn update(&self, banks: &mut Banks) {
+        let bank = (self.prg & 0x0F) as usize;
+        let last = banks.bank_count(self.window) - 1;
+        let (lo, hi) = match (self.control >> 2) & 3 {
+            0 | 1 => (bank & !1, bank | 1),
+            2 => (0, bank),
+            _ => (bank, last),
+        };
+        banks.select(self.window, lo);
+        banks.select(self.window + 1, hi);
+    }
+}
+impl Mapper for Mmc1 {
+    fn is_register(&self, addr: u16) -> bool {
+        addr >= PRG_ROM
+    }
+    fn write_register(&mut self, banks: &mut Banks, addr: u16, data: u8) {
+        if data & 0x80 != 0 {
+            self.shift = 0;
+            self.count = 0;
+            self.control |= 0x0C;
+            self.update(banks);
+            return;
+        }
+
+        self.shift |= (data & 1) << self.count;
+        self.count += 1;
+        if self.count < 5 {
+            return;
+        }
+
+        let value = self.shift;
+        self.shift = 0;
+        self.count = 0;
+        match (addr >> 13) & 3 {
+            0 => self.control = value,
+            1 => self.chr[0] = value,
+            2 => self.chr[1] = value,
+            _ => self.prg = value,
+        }
+        self.update(banks);
+    }
+    fn read(&mut self, banks: &mut Banks, addr: u16) -> Option<u8> {
+        if addr < PRG_ROM && !self.ram_enabled() {
+            return None;
+        }
+        banks.read(addr)
+    }
+    fn write(&mut self, banks: &mut Banks, addr: u16, data: u8) {
+        if addr < PRG_ROM && !self.ram_enabled() {
+            return;
+        }
+        banks.write(addr, data);
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(match self.control & 3 {
+            0 => Mirroring::SingleLower,
+            1 => Mirroring::SingleUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        })
+    }
+}
+impl Cartridge<Mmc1> {
+    pub fn mmc1(prg: Vec<u8>, prg_ram: usize) -> Self {
+        let banks = prg_banks(prg, prg_ram);
+        let window = banks.windows();
+        let mut banks = banks
+            .with_window(PRG_ROM, KB16, Source::Rom, 0)
+            .with_window(0xC000, KB16, Source::Rom, 0);
+        let mapper = Mmc1 {
+            window,
+            shift: 0,
+            count: 0,
+            control: 0x0C,
+            chr: [0; 2],
+            prg: 0,
+        };
+        mapper.update(&mut banks);
+        Self::new(banks, mapper)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Switcher {
+    first: usize,
+    size: usize,
+}
+impl Mapper for Switcher {
+    fn is_register(&self, addr: u16) -> bool {
+        addr >= PRG_ROM
+    }
+    fn write_register(&mut self, banks: &mut Banks, addr: u16, data: u8) {
+        let window = (addr - PRG_ROM) as usize / self.size;
+        banks.select(self.first + window, data as usize);
+    }
+}
+impl Cartridge<Switcher> {
+    pub fn switcher(prg: Vec<u8>, window_size: usize, prg_ram: usize) -> Self {
+        assert!(
+            window_size == KB8 || window_size == KB16,
+            "switcher windows are 8K or 16K"
+        );
+        let mut banks = prg_banks(prg, prg_ram);
+        let first = banks.windows();
+        for i in 0..0x8000 / window_size {
+            let start = PRG_ROM + (i * window_size) as u16;
+            banks = banks.with_window(start, window_size, Source::Rom, i);
+        }
+        let mapper = Switcher {
+            first,
+            size: window_size,
+        };
+        Self::new(banks, mapper)
+    }
+}
@@ -0,0 +1,260 @@
+use std::{cell::RefCell, ops::RangeInclusive, rc::Rc};
+
+use crate::Bus;
+
+pub trait Memory {
+    fn read(&mut self, addr: u16) -> Option<u8>;
+    fn write(&mut self, addr: u16, data: u8);
+
+    /// Stores `data` the way a loader or debugger would: ROM takes it too,
+    /// and nothing on the bus is disturbed.
+    fn poke(&mut self, addr: u16, data: u8) {
+        self.write(addr, data);
+    }
+    /// Reads `addr` the way a debugger would. Devices whose reads have side
+    /// effects should override this to leave their state alone.
+    fn peek(&mut self, addr: u16) -> Option<u8> {
+        self.read(addr)
+    }
+
+    fn access(&mut self, bus: &mut Bus) {
+        if bus.rw() {
+            match self.read(bus.addr) {
+                Some(data) => bus.respond(data),
+                None => bus.float(),
+            }
+        } else {
+            self.write(bus.addr, bus.data);
+        }
+    }
+}
+impl Memory for [u8; 65536] {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        Some(self[addr as usize])
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        self[addr as usize] = data;
+    }
+}
+impl<M: Memory + ?Sized> Memory for Box<M> {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        (**self).read(addr)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        (**self).write(addr, data);
+    }
+    fn poke(&mut self, addr: u16, data: u8) {
+        (**self).poke(addr, data);
+    }
+    fn peek(&mut self, addr: u16) -> Option<u8> {
+        (**self).peek(addr)
+    }
+}
+impl<M: Memory + ?Sized> Memory for Rc<RefCell<M>> {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        self.borrow_mut().read(addr)
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        self.borrow_mut().write(addr, data);
+    }
+    fn poke(&mut self, addr: u16, data: u8) {
+        self.borrow_mut().poke(addr, data);
+    }
+    fn peek(&mut self, addr: u16) -> Option<u8> {
+        self.borrow_mut().peek(addr)
+    }
+}
+
+pub type UnmappedHandler = Box<dyn FnMut(u16, u8) -> Option<u8>>;
+
+enum Region {
+    Ram(Vec<u8>),
+    Rom(Vec<u8>),
+    // The start of the target and its length, which is 65536 at most.
+    Mirror(u16, u32),
+    Device(Box<dyn Memory>, u16),
+    Unmapped(UnmappedHandler),
+}
+
+struct Mapping {
+    start: u16,
+    end: u16,
+    region: Region,
+}
+
+pub struct MemoryMap {
+    mappings: Vec<Mapping>,
+    open_bus: u8,
+}
+impl MemoryMap {
+    pub fn builder() -> MemoryMapBuilder {
+        MemoryMapBuilder {
+            mappings: Vec::new(),
+        }
+    }
+
+    pub fn open_bus(&self) -> u8 {
+        self.open_bus
+    }
+
+    fn find(&self, addr: u16) -> Option<usize> {
+        let i = self.mappings.partition_point(|m| m.end < addr);
+        self.mappings.get(i).filter(|m| m.start <= addr).map(|_| i)
+    }
+}
+impl Memory for MemoryMap {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let i = self.find(addr)?;
+        let mapping = &mut self.mappings[i];
+        let offset = addr - mapping.start;
+        let data = match &mut mapping.region {
+            Region::Ram(ram) => Some(ram[offset as usize]),
+            Region::Rom(rom) => Some(rom[offset as usize % rom.len()]),
+            &mut Region::Mirror(target, size) => return self.read(mirrored(target, size, offset)),
+            Region::Device(device, base) => device.read(addr - *base),
+            Region::Unmapped(handler) => handler(addr, self.open_bus),
+        };
+
+        if let Some(data) = data {
+            self.open_bus = data;
+        }
+        data
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
+        let Some(i) = self.find(addr) else {
+            return;
+        };
+        let mapping = &mut self.mappings[i];
+        let offset = addr - mapping.start;
+        match &mut mapping.region {
+            Region::Ram(ram) => ram[offset as usize] = data,
+            Region::Rom(_) => (),
+            &mut Region::Mirror(target, size) => self.write(mirrored(target, size, offset), data),
+            Region::Device(device, base) => device.write(addr - *base, data),
+            Region::Unmapped(_) => (),
+        }
+    }
+    fn poke(&mut self, addr: u16, data: u8) {
+        let Some(i) = self.find(addr) else {
+            return;
+        };
+        let mapping = &mut self.mappings[i];
+        let offset = addr - mapping.start;
+        match &mut mapping.region {
+            Region::Ram(ram) => ram[offset as usize] = data,
+            Region::Rom(rom) => {
+                let len = rom.len();
+                rom[offset as usize % len] = data;
+            }
+            &mut Region::Mirror(target, size) => self.poke(mirrored(target, size, offset), data),
+            Region::Device(device, base) => device.poke(addr - *base, data),
+            Region::Unmapped(_) => (),
+        }
+    }
+    fn peek(&mut self, addr: u16) -> Option<u8> {
+        let i = self.find(addr)?;
+        let mapping = &mut self.mappings[i];
+        let offset = addr - mapping.start;
+        match &mut mapping.region {
+            Region::Ram(ram) => Some(ram[offset as usize]),
+            Region::Rom(rom) => Some(rom[offset as usize % rom.len()]),
+            &mut Region::Mirror(target, size) => self.peek(mirrored(target, size, offset)),
+            Region::Device(device, base) => device.peek(addr - *base),
+            Region::Unmapped(_) => None,
+        }
+    }
+}
+
+pub struct MemoryMapBuilder {
+    mappings: Vec<Mapping>,
+}
+impl MemoryMapBuilder {
+    pub fn ram(self, range: RangeInclusive<u16>) -> Self {
+        let size = range_len(&range);
+        self.map(range, Region::Ram(vec![0; size]))
+    }
+    pub fn rom(self, range: RangeInclusive<u16>, image: impl Into<Vec<u8>>) -> Self {
+        let image = image.into();
+        assert!(!image.is_empty(), "ROM image is empty");
+        assert!(
+            image.len() <= range_len(&range),
+            "ROM image does not fit its region"
+        );
+        self.map(range, Region::Rom(image))
+    }
+    pub fn mirror(self, range: RangeInclusive<u16>, target: RangeInclusive<u16>) -> Self {
+        let size = range_len(&target) as u32;
+        self.map(range, Region::Mirror(*target.start(), size))
+    }
+    pub fn device(self, range: RangeInclusive<u16>, device: impl Memory + 'static) -> Self {
+        let base = *range.start();
+        self.map(range, Region::Device(Box::new(device), base))
+    }
+    pub fn absolute_device(
+        self,
+        range: RangeInclusive<u16>,
+        device: impl Memory + 'static,
+    ) -> Self {
+        self.map(range, Region::Device(Box::new(device), 0))
+    }
+    pub fn unmapped(
+        self,
+        range: RangeInclusive<u16>,
+        handler: impl FnMut(u16, u8) -> Option<u8> + 'static,
+    ) -> Self {
+        self.map(range, Region::Unmapped(Box::new(handler)))
+    }
+
+    pub fn build(mut self) -> MemoryMap {
+        self.mappings.sort_by_key(|m| m.start);
+        for pair in self.mappings.windows(2) {
+            assert!(
+                pair[0].end < pair[1].start,
+                "regions {:0>4x}-{:0>4x} and {:0>4x}-{:0>4x} overlap",
+                pair[0].start,
+                pair[0].end,
+                pair[1].start,
+                pair[1].end
+            );
+        }
+
+        // A mirror of a mirror, itself included, could loop forever.
+        for mapping in &self.mappings {
+            if let Region::Mirror(target, size) = mapping.region {
+                let end = (target as u32 + size - 1) as u16;
+                for other in &self.mappings {
+                    assert!(
+                        !matches!(other.region, Region::Mirror(..))
+                            || other.end < target
+                            || other.start > end,
+                        "mirror {:0>4x}-{:0>4x} targets the mirror {:0>4x}-{:0>4x}",
+                        mapping.start,
+                        mapping.end,
+                        other.start,
+                        other.end
+                    );
+                }
+            }
+        }
+
+        MemoryMap {
+            mappings: self.mappings,
+            open_bus: 0,
+        }
+    }
+
+    fn map(mut self, range: RangeInclusive<u16>, region: Region) -> Self {
+        let (start, end) = range.into_inner();
+        assert!(start <= end, "empty region {start:0>4x}-{end:0>4x}");
+        self.mappings.push(Mapping { start, end, region });
+        self
+    }
+}
+
+fn mirrored(target: u16, size: u32, offset: u16) -> u16 {
+    target.wrapping_add((offset as u32 % size) as u16)
+}
+fn range_len(range: &RangeInclusive<u16>) -> usize {
+    (*range.end() as usize + 1).saturating_sub(*range.start() as usize)
+}
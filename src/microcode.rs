@@ -0,0 +1,287 @@
+use crate::instr::{self, Am, Op};
+
+// Every opcode compiles to a fixed sequence of micro-ops, one per bus cycle,
+// which `M6502` steps through. A micro-op first takes in what the previous
+// cycle read (`bus.data`) and then starts its own bus cycle. Only a few
+// can change the length of the sequence: the indexed reads skip their
+// fix-up cycle when the page is not crossed, branches skip or end early,
+// and every sequence ends in a micro-op that syncs to the next opcode.
+//
+// `addr` and `data` are the two internal latches, and "exec" runs the
+// instruction's ALU operation from `core`. `Fetch` reads at PC and
+// increments it, `Peek` reads at PC without, and the `Sync` ones fetch
+// the next opcode.
+
+/// One bus cycle of an instruction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Micro {
+    Fetch,
+    Peek,
+    PeekExec,
+    Sync,
+    ExecSync,
+    ExecAccumulator,
+
+    FetchHigh,
+    ReadZero,
+    WriteZero,
+    ReadAbsolute,
+    WriteAbsolute,
+    Jump,
+    ReadAddr,
+    ExecWrite,
+    Write,
+    /// Takes the data read, writes it back unchanged and exec.
+    Modify,
+
+    IndexZero(Reg),
+    /// Takes the high address byte and adds the index, reading the address
+    /// before the carry into the high byte is added.
+    Index(Reg),
+    /// Like `Index`, but skips the next micro-op, the read of the fixed
+    /// address, when no page was crossed.
+    IndexSkip(Reg),
+    ReadPointer,
+    IndexPointer,
+    ReadPointerHigh,
+    /// Takes the low byte of a `JMP (a)` target and reads the high byte,
+    /// without carrying into the pointer's high byte.
+    ReadIndirectHigh,
+
+    /// Takes the offset and exec, which decides the branch. Sync when not
+    /// taken, else reads PC and skips `BranchFixup` when no page is crossed.
+    Branch,
+    BranchFixup,
+    /// Jumps to the branch target and sync.
+    TakeBranch,
+
+    ExecPush,
+    PeekStack,
+    Pull,
+    /// Takes the low target byte of JSR into `data` and reads the stack.
+    PeekStackLow,
+    PushPch,
+    PushPcl,
+    JumpSubroutine,
+    PullLow,
+    Return,
+    PullP,
+    PullPcl,
+    ReturnInterrupt,
+
+    /// Reads PC, incrementing it only for BRK.
+    Interrupt,
+    /// Pushes the high byte of PC, or reads the stack on reset, as do the
+    /// two after it.
+    InterruptPch,
+    InterruptPcl,
+    InterruptP,
+    Vector,
+    VectorHigh,
+    VectorSync,
+
+    ReadAt(u16),
+    /// Reads $FFFF forever.
+    Jam,
+    /// Fetches the next opcode, leaving the I mask as it is, when `M6502`
+    /// resumes between two instructions.
+    Resume,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Reg {
+    X,
+    Y,
+}
+
+/// The micro-ops of an opcode. Interrupts and reset run those of BRK.
+pub fn microcode(opcode: u8) -> &'static [Micro] {
+    MICROCODE[opcode as usize]
+}
+
+/// The cycles an opcode takes, without the extra cycle of an indexed read
+/// that crosses a page or the one or two of a taken branch.
+pub fn cycles(opcode: u8) -> u8 {
+    CYCLES[opcode as usize]
+}
+
+// The first entry stands for the cycle before, which the resumed CPU did
+// not run.
+pub static RESUME: [Micro; 2] = [Micro::Sync, Micro::Resume];
+
+static MICROCODE: [&[Micro]; 256] = {
+    let mut table: [&[Micro]; 256] = [&[]; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        let (op, am) = instr::decode(opcode as u8);
+        table[opcode] = compile(op, am);
+        opcode += 1;
+    }
+    table
+};
+
+static CYCLES: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        let program = MICROCODE[opcode];
+        let mut cycles = program.len();
+        let mut i = 0;
+        while i < program.len() {
+            match program[i] {
+                Micro::IndexSkip(_) => cycles -= 1,
+                Micro::Branch => cycles -= 2,
+                _ => {}
+            }
+            i += 1;
+        }
+        table[opcode] = cycles as u8;
+        opcode += 1;
+    }
+    table
+};
+
+const fn compile(op: Op, am: Am) -> &'static [Micro] {
+    use Micro::*;
+    use Reg::*;
+    let (read, modify) = (op.reads_operand(), op.is_rmw());
+    match am {
+        Am::Implied => match op {
+            Op::Brk => &[
+                Interrupt,
+                InterruptPch,
+                InterruptPcl,
+                InterruptP,
+                Vector,
+                VectorHigh,
+                VectorSync,
+            ],
+            Op::Jam => &[Peek, ReadAt(0xFFFF), ReadAt(0xFFFE), ReadAt(0xFFFE), Jam],
+            Op::Rti => &[Peek, PeekStack, Pull, PullP, PullPcl, ReturnInterrupt],
+            Op::Rts => &[Peek, PeekStack, Pull, PullLow, Return, Sync],
+            Op::Pha | Op::Php => &[Peek, ExecPush, Sync],
+            Op::Pla | Op::Plp => &[Peek, PeekStack, Pull, ExecSync],
+            _ => &[PeekExec, Sync],
+        },
+        Am::Accumulator => &[Peek, ExecAccumulator],
+        Am::Immediate => &[Fetch, ExecSync],
+        Am::Relative => &[Fetch, Branch, BranchFixup, TakeBranch],
+        Am::Indirect => &[Fetch, FetchHigh, ReadAbsolute, ReadIndirectHigh, Jump],
+        Am::Absolute => match op {
+            Op::Jmp => &[Fetch, FetchHigh, Jump],
+            Op::Jsr => &[Fetch, PeekStackLow, PushPch, PushPcl, Fetch, JumpSubroutine],
+            _ if modify => &[Fetch, FetchHigh, ReadAbsolute, Modify, Write, Sync],
+            _ if read => &[Fetch, FetchHigh, ReadAbsolute, ExecSync],
+            _ => &[Fetch, FetchHigh, WriteAbsolute, Sync],
+        },
+        Am::AbsoluteX => {
+            if modify {
+                &[Fetch, FetchHigh, Index(X), ReadAddr, Modify, Write, Sync]
+            } else if read {
+                &[Fetch, FetchHigh, IndexSkip(X), ReadAddr, ExecSync]
+            } else {
+                &[Fetch, FetchHigh, Index(X), ExecWrite, Sync]
+            }
+        }
+        Am::AbsoluteY => {
+            if modify {
+                &[Fetch, FetchHigh, Index(Y), ReadAddr, Modify, Write, Sync]
+            } else if read {
+                &[Fetch, FetchHigh, IndexSkip(Y), ReadAddr, ExecSync]
+            } else {
+                &[Fetch, FetchHigh, Index(Y), ExecWrite, Sync]
+            }
+        }
+        Am::Zero => {
+            if modify {
+                &[Fetch, ReadZero, Modify, Write, Sync]
+            } else if read {
+                &[Fetch, ReadZero, ExecSync]
+            } else {
+                &[Fetch, WriteZero, Sync]
+            }
+        }
+        Am::ZeroX => {
+            if modify {
+                &[Fetch, IndexZero(X), ReadAddr, Modify, Write, Sync]
+            } else if read {
+                &[Fetch, IndexZero(X), ReadAddr, ExecSync]
+            } else {
+                &[Fetch, IndexZero(X), ExecWrite, Sync]
+            }
+        }
+        Am::ZeroY => {
+            if modify {
+                &[Fetch, IndexZero(Y), ReadAddr, Modify, Write, Sync]
+            } else if read {
+                &[Fetch, IndexZero(Y), ReadAddr, ExecSync]
+            } else {
+                &[Fetch, IndexZero(Y), ExecWrite, Sync]
+            }
+        }
+        Am::IndexedIndirect => {
+            if modify {
+                &[
+                    Fetch,
+                    ReadPointer,
+                    IndexPointer,
+                    ReadPointerHigh,
+                    ReadAbsolute,
+                    Modify,
+                    Write,
+                    Sync,
+                ]
+            } else if read {
+                &[
+                    Fetch,
+                    ReadPointer,
+                    IndexPointer,
+                    ReadPointerHigh,
+                    ReadAbsolute,
+                    ExecSync,
+                ]
+            } else {
+                &[
+                    Fetch,
+                    ReadPointer,
+                    IndexPointer,
+                    ReadPointerHigh,
+                    WriteAbsolute,
+                    Sync,
+                ]
+            }
+        }
+        Am::IndirectIndexed => {
+            if modify {
+                &[
+                    Fetch,
+                    ReadPointer,
+                    ReadPointerHigh,
+                    Index(Y),
+                    ReadAddr,
+                    Modify,
+                    Write,
+                    Sync,
+                ]
+            } else if read {
+                &[
+                    Fetch,
+                    ReadPointer,
+                    ReadPointerHigh,
+                    IndexSkip(Y),
+                    ReadAddr,
+                    ExecSync,
+                ]
+            } else {
+                &[
+                    Fetch,
+                    ReadPointer,
+                    ReadPointerHigh,
+                    Index(Y),
+                    ExecWrite,
+                    Sync,
+                ]
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+
+use crate::{Bus, M6502, memory::Memory};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Exit(u8),
+    Abort,
+}
+
+// The system simulated by llvm-mos's `mos-sim`: 64K of RAM with a few
+// registers below the vectors.
+pub struct MosSim {
+    ram: Box<[u8; 0x10000]>,
+    cycles: u64,
+    latch: [u8; 4],
+    outcome: Option<Outcome>,
+    output: Box<dyn Write>,
+}
+impl MosSim {
+    pub const CLOCK: u16 = 0xFFF0;
+    pub const ABORT: u16 = 0xFFF7;
+    pub const EXIT: u16 = 0xFFF8;
+    pub const PUTCHAR: u16 = 0xFFF9;
+
+    pub fn new() -> Self {
+        Self {
+            ram: Box::new([0; 0x10000]),
+            cycles: 0,
+            latch: [0; 4],
+            outcome: None,
+            output: Box::new(io::stdout()),
+        }
+    }
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    // The image is a sequence of blocks, each a little-endian address and
+    // length followed by that many bytes. Returns None if it is truncated.
+    pub fn load(&mut self, image: &[u8]) -> Option<()> {
+        let mut rest = image;
+        while !rest.is_empty() {
+            let (head, tail) = rest.split_at_checked(4)?;
+            let addr = u16::from_le_bytes([head[0], head[1]]) as usize;
+            let len = u16::from_le_bytes([head[2], head[3]]) as usize;
+            let (data, tail) = tail.split_at_checked(len)?;
+            if addr + len > self.ram.len() {
+                return None;
+            }
+            self.ram[addr..addr + len].copy_from_slice(data);
+            rest = tail;
+        }
+        Some(())
+    }
+
+    pub fn ram(&self) -> &[u8; 0x10000] {
+        &self.ram
+    }
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    // Runs a CPU from reset until the program exits or `limit` cycles pass.
+    pub fn run(&mut self, cpu: &mut M6502, limit: u64) -> Option<Outcome> {
+        let mut bus = Bus::new();
+        for _ in 0..limit {
+            cpu.clock(&mut bus);
+            self.access(&mut bus);
+            if self.outcome.is_some() {
+                break;
+            }
+        }
+        self.outcome
+    }
+}
+impl Memory for MosSim {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        // Reading the low byte latches the whole counter so that a
+        // multi-byte read is consistent.
+        if addr == Self::CLOCK {
+            self.latch = (self.cycles as u32).to_le_bytes();
+        }
+        match addr {
+            Self::CLOCK..=0xFFF3 => Some(self.latch[(addr - Self::CLOCK) as usize]),
+            _ => Some(self.ram[addr as usize]),
+        }
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            Self::ABORT => self.outcome = Some(Outcome::Abort),
+            Self::EXIT => self.outcome = Some(Outcome::Exit(data)),
+            Self::PUTCHAR => {
+                let _ = self.output.write_all(&[data]);
+                let _ = self.output.flush();
+            }
+            Self::CLOCK..=0xFFF9 => {}
+            _ => self.ram[addr as usize] = data,
+        }
+    }
+    fn poke(&mut self, addr: u16, data: u8) {
+        self.ram[addr as usize] = data;
+    }
+    fn peek(&mut self, addr: u16) -> Option<u8> {
+        Some(self.ram[addr as usize])
+    }
+    // Every bus cycle passes through here, which makes it the cycle counter.
+    fn access(&mut self, bus: &mut Bus) {
+        self.cycles += 1;
+        if bus.rw() {
+            let data = self.read(bus.addr).unwrap();
+            bus.respond(data);
+        } else {
+            self.write(bus.addr, bus.data);
+        }
+    }
+}
+impl Default for MosSim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use crate::loader::{Image, LoadError};
+
+const MAGIC: &[u8] = b"\x01\x00o65";
+
+const MODE_65816: u16 = 0x8000;
+const MODE_PAGEWISE: u16 = 0x4000;
+const MODE_LONG: u16 = 0x2000;
+const MODE_BSSZERO: u16 = 0x0200;
+
+const SEG_UNDEFINED: u8 = 0;
+const SEG_ABSOLUTE: u8 = 1;
+const SEG_TEXT: u8 = 2;
+const SEG_DATA: u8 = 3;
+const SEG_BSS: u8 = 4;
+const SEG_ZERO: u8 = 5;
+
+const RELOC_WORD: u8 = 0x80;
+const RELOC_HIGH: u8 = 0x40;
+const RELOC_LOW: u8 = 0x20;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub text: u16,
+    pub data: u16,
+    pub bss: u16,
+    pub zero: u16,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Reloc {
+    offset: usize,
+    kind: u8,
+    segment: u8,
+    symbol: usize,
+    low: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    pub name: String,
+    pub segment: u8,
+    pub value: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct O65 {
+    pub mode: u16,
+    pub placement: Placement,
+    pub text: Vec<u8>,
+    pub data: Vec<u8>,
+    pub bss_len: u16,
+    pub zero_len: u16,
+    pub stack: u16,
+    pub options: Vec<(u8, Vec<u8>)>,
+    pub undefined: Vec<String>,
+    pub exports: Vec<Export>,
+    text_relocs: Vec<Reloc>,
+    data_relocs: Vec<Reloc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relocated {
+    pub image: Image,
+    pub exports: HashMap<String, u16>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    long: bool,
+}
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8], LoadError> {
+        let (taken, rest) = self
+            .data
+            .split_at_checked(len)
+            .ok_or(LoadError::Truncated)?;
+        self.data = rest;
+        Ok(taken)
+    }
+    fn byte(&mut self) -> Result<u8, LoadError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn word(&mut self) -> Result<u16, LoadError> {
+        let len = if self.long { 4 } else { 2 };
+        let value = self
+            .bytes(len)?
+            .iter()
+            .rev()
+            .fold(0, |v, &b| v << 8 | b as u32);
+        u16::try_from(value).map_err(|_| LoadError::OutOfRange)
+    }
+    fn name(&mut self) -> Result<String, LoadError> {
+        let len = self
+            .data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(LoadError::Truncated)?;
+        let name = String::from_utf8_lossy(&self.data[..len]).into_owned();
+        self.data = &self.data[len + 1..];
+        Ok(name)
+    }
+
+    fn relocs(&mut self, pagewise: bool) -> Result<Vec<Reloc>, LoadError> {
+        let mut relocs = Vec::new();
+        // Offsets count from one byte before the segment.
+        let mut offset = 0usize;
+        loop {
+            match self.byte()? {
+                0 => break,
+                255 => {
+                    offset += 254;
+                    continue;
+                }
+                skip => offset += skip as usize,
+            }
+            let typebyte = self.byte()?;
+            let mut reloc = Reloc {
+                offset: offset - 1,
+                kind: typebyte & 0xE0,
+                segment: typebyte & 0x1F,
+                symbol: 0,
+                low: 0,
+            };
+            if reloc.segment == SEG_UNDEFINED {
+                reloc.symbol = self.word()? as usize;
+            }
+            match reloc.kind {
+                RELOC_WORD | RELOC_LOW => (),
+                RELOC_HIGH if !pagewise => reloc.low = self.byte()?,
+                RELOC_HIGH => (),
+                _ => return Err(LoadError::BadHeader),
+            }
+            relocs.push(reloc);
+        }
+        Ok(relocs)
+    }
+}
+
+impl O65 {
+    pub fn parse(file: &[u8]) -> Result<Self, LoadError> {
+        if !file.starts_with(MAGIC) {
+            return Err(LoadError::BadHeader);
+        }
+        let mut r = Reader {
+            data: &file[MAGIC.len()..],
+            long: false,
+        };
+        if r.byte()? != 0 {
+            return Err(LoadError::BadHeader);
+        }
+        let mode = u16::from_le_bytes([r.byte()?, r.byte()?]);
+        if mode & MODE_65816 != 0 {
+            return Err(LoadError::BadHeader);
+        }
+        r.long = mode & MODE_LONG != 0;
+
+        let (text_base, text_len) = (r.word()?, r.word()?);
+        let (data_base, data_len) = (r.word()?, r.word()?);
+        let (bss_base, bss_len) = (r.word()?, r.word()?);
+        let (zero_base, zero_len) = (r.word()?, r.word()?);
+        let stack = r.word()?;
+
+        let mut options = Vec::new();
+        loop {
+            let len = r.byte()? as usize;
+            if len == 0 {
+                break;
+            }
+            let kind = r.byte()?;
+            let body = r.bytes(len.checked_sub(2).ok_or(LoadError::BadHeader)?)?;
+            options.push((kind, body.to_vec()));
+        }
+
+        let text = r.bytes(text_len as usize)?.to_vec();
+        let data = r.bytes(data_len as usize)?.to_vec();
+
+        let undefined = (0..r.word()?).map(|_| r.name()).collect::<Result<_, _>>()?;
+        let pagewise = mode & MODE_PAGEWISE != 0;
+        let text_relocs = r.relocs(pagewise)?;
+        let data_relocs = r.relocs(pagewise)?;
+
+        let exports = (0..r.word()?)
+            .map(|_| {
+                Ok(Export {
+                    name: r.name()?,
+                    segment: r.byte()?,
+                    value: r.word()?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            mode,
+            placement: Placement {
+                text: text_base,
+                data: data_base,
+                bss: bss_base,
+                zero: zero_base,
+            },
+            text,
+            data,
+            bss_len,
+            zero_len,
+            stack,
+            options,
+            undefined,
+            exports,
+            text_relocs,
+            data_relocs,
+        })
+    }
+
+    // Moves the segments to `to`, resolving undefined references against
+    // `imports`. The image holds the text and data segments, and a cleared
+    // BSS if the file asks for one.
+    pub fn relocate(
+        &self,
+        to: Placement,
+        imports: &HashMap<String, u16>,
+    ) -> Result<Relocated, LoadError> {
+        let from = self.placement;
+        let symbols = self
+            .undefined
+            .iter()
+            .map(|name| {
+                imports
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| LoadError::UndefinedSymbol(name.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let delta = |segment: u8, symbol: usize| -> Result<u16, LoadError> {
+            Ok(match segment {
+                SEG_UNDEFINED => *symbols.get(symbol).ok_or(LoadError::BadHeader)?,
+                SEG_ABSOLUTE => 0,
+                SEG_TEXT => to.text.wrapping_sub(from.text),
+                SEG_DATA => to.data.wrapping_sub(from.data),
+                SEG_BSS => to.bss.wrapping_sub(from.bss),
+                SEG_ZERO => to.zero.wrapping_sub(from.zero),
+                _ => return Err(LoadError::BadHeader),
+            })
+        };
+
+        let mut text = self.text.clone();
+        let mut data = self.data.clone();
+        for (segment, relocs) in [
+            (&mut text, &self.text_relocs),
+            (&mut data, &self.data_relocs),
+        ] {
+            for reloc in relocs {
+                let delta = delta(reloc.segment, reloc.symbol)?;
+                let at = reloc.offset;
+                let width = if reloc.kind == RELOC_WORD { 2 } else { 1 };
+                if at + width > segment.len() {
+                    return Err(LoadError::OutOfRange);
+                }
+                match reloc.kind {
+                    RELOC_WORD => {
+                        let value = u16::from_le_bytes([segment[at], segment[at + 1]]);
+                        let value = value.wrapping_add(delta).to_le_bytes();
+                        segment[at..at + 2].copy_from_slice(&value);
+                    }
+                    RELOC_HIGH => {
+                        let value = u16::from_le_bytes([reloc.low, segment[at]]);
+                        segment[at] = (value.wrapping_add(delta) >> 8) as u8;
+                    }
+                    _ => segment[at] = segment[at].wrapping_add(delta as u8),
+                }
+            }
+        }
+
+        let mut image = Image::raw(&text, to.text)?;
+        image.segments.extend(Image::raw(&data, to.data)?.segments);
+        if self.mode & MODE_BSSZERO != 0 {
+            let bss = vec![0; self.bss_len as usize];
+            image.segments.extend(Image::raw(&bss, to.bss)?.segments);
+        }
+        image.segments.retain(|segment| !segment.data.is_empty());
+
+        let exports = self
+            .exports
+            .iter()
+            .map(|export| {
+                let value = export.value.wrapping_add(delta(export.segment, 0)?);
+                Ok((export.name.clone(), value))
+            })
+            .collect::<Result<_, LoadError>>()?;
+        Ok(Relocated { image, exports })
+    }
+}
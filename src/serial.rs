@@ -0,0 +1,121 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::Path,
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+pub trait SerialHost {
+    fn poll(&mut self) -> Option<u8>;
+    fn send(&mut self, byte: u8);
+}
+impl<H: SerialHost + ?Sized> SerialHost for Box<H> {
+    fn poll(&mut self) -> Option<u8> {
+        (**self).poll()
+    }
+    fn send(&mut self, byte: u8) {
+        (**self).send(byte)
+    }
+}
+impl<H: SerialHost> SerialHost for Rc<RefCell<H>> {
+    fn poll(&mut self) -> Option<u8> {
+        self.borrow_mut().poll()
+    }
+    fn send(&mut self, byte: u8) {
+        self.borrow_mut().send(byte)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Queue {
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>,
+}
+impl Queue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+}
+impl SerialHost for Queue {
+    fn poll(&mut self) -> Option<u8> {
+        self.input.pop_front()
+    }
+    fn send(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+}
+
+// A byte stream read by a background thread so polling never blocks the
+// emulator. Creating PTYs needs platform calls we don't link against, so
+// `open` takes the path of one made elsewhere, e.g. by
+// `socat pty,raw,echo=0,link=/tmp/sbc -`.
+pub struct HostStream {
+    input: Receiver<u8>,
+    output: Box<dyn Write + Send>,
+}
+impl HostStream {
+    pub fn new(mut input: impl Read + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0; 256];
+            while let Ok(len @ 1..) = input.read(&mut buf) {
+                if buf[..len].iter().any(|&byte| tx.send(byte).is_err()) {
+                    break;
+                }
+            }
+        });
+        Self {
+            input: rx,
+            output: Box::new(output),
+        }
+    }
+
+    // Line-buffered terminals end lines with LF; 6502 monitors want CR.
+    pub fn stdio() -> Self {
+        Self::new(LfToCr(io::stdin()), io::stdout())
+    }
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+    pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self::new(file.try_clone()?, file))
+    }
+}
+impl SerialHost for HostStream {
+    fn poll(&mut self) -> Option<u8> {
+        self.input.try_recv().ok()
+    }
+    fn send(&mut self, byte: u8) {
+        let _ = self.output.write_all(&[byte]);
+        let _ = self.output.flush();
+    }
+}
+
+struct LfToCr<R>(R);
+impl<R: Read> Read for LfToCr<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.0.read(buf)?;
+        for byte in &mut buf[..len] {
+            if *byte == b'\n' {
+                *byte = b'\r';
+            }
+        }
+        Ok(len)
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+};
+
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+    memory::Memory,
+};
+
+const MAGIC: &[u8] = b"sim65";
+const HEADER_LEN: usize = 12;
+
+const O_RDONLY: u16 = 0x01;
+const O_WRONLY: u16 = 0x02;
+const O_CREAT: u16 = 0x10;
+const O_TRUNC: u16 = 0x20;
+const O_APPEND: u16 = 0x40;
+const O_EXCL: u16 = 0x80;
+
+const RTS: u8 = 0x60;
+const ERROR: u16 = 0xFFFF;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub cpu: u8,
+    pub sp: u8,
+    pub load: u16,
+    pub reset: u16,
+}
+impl Header {
+    pub const CPU_6502: u8 = 0;
+    pub const CPU_65C02: u8 = 1;
+
+    // Only version 2 headers are recognised; they carry everything needed
+    // to start the program.
+    pub fn parse(image: &[u8]) -> Option<(Header, &[u8])> {
+        if image.len() < HEADER_LEN || !image.starts_with(MAGIC) || image[5] != 2 {
+            return None;
+        }
+        let header = Header {
+            version: image[5],
+            cpu: image[6],
+            sp: image[7],
+            load: u16::from_le_bytes([image[8], image[9]]),
+            reset: u16::from_le_bytes([image[10], image[11]]),
+        };
+        Some((header, &image[HEADER_LEN..]))
+    }
+}
+
+enum Handle {
+    Input(Box<dyn Read>),
+    Output(Box<dyn Write>),
+    File(File),
+}
+
+// The paravirtualisation hooks of cc65's sim65. A program calls them with
+// `JSR`; the host services the call when the CPU fetches the opcode at the
+// hook address and answers the fetch with `RTS`.
+pub struct Sim65 {
+    sp: u8,
+    args: Vec<String>,
+    files: Vec<Option<Handle>>,
+    exit: Option<u8>,
+}
+impl Sim65 {
+    pub const OPEN: u16 = 0xFFF4;
+    pub const CLOSE: u16 = 0xFFF5;
+    pub const READ: u16 = 0xFFF6;
+    pub const WRITE: u16 = 0xFFF7;
+    pub const ARGS: u16 = 0xFFF8;
+    pub const EXIT: u16 = 0xFFF9;
+
+    pub fn new(sp: u8) -> Self {
+        Self {
+            sp,
+            args: vec!["sim65".to_string()],
+            files: vec![
+                Some(Handle::Input(Box::new(io::stdin()))),
+                Some(Handle::Output(Box::new(io::stdout()))),
+                Some(Handle::Output(Box::new(io::stderr()))),
+            ],
+            exit: None,
+        }
+    }
+    // The first argument is the program name, as in `argv[0]`.
+    pub fn with_args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn with_stdin(mut self, stdin: impl Read + 'static) -> Self {
+        self.files[0] = Some(Handle::Input(Box::new(stdin)));
+        self
+    }
+    pub fn with_stdout(mut self, stdout: impl Write + 'static) -> Self {
+        self.files[1] = Some(Handle::Output(Box::new(stdout)));
+        self
+    }
+    pub fn with_stderr(mut self, stderr: impl Write + 'static) -> Self {
+        self.files[2] = Some(Handle::Output(Box::new(stderr)));
+        self
+    }
+
+    // Loads an image into a fresh 64K of RAM and sets up a CPU at its
+    // reset address, or None if it is not a 6502 image or runs past $FFFF.
+    pub fn load(image: &[u8]) -> Option<(Self, M6502, Box<[u8; 0x10000]>)> {
+        let (header, data) = Header::parse(image)?;
+        if header.cpu != Header::CPU_6502 {
+            return None;
+        }
+
+        let mut ram = Box::new([0; 0x10000]);
+        let load = header.load as usize;
+        ram.get_mut(load..load + data.len())?.copy_from_slice(data);
+
+        let core = Core {
+            a: 0,
+            p: P(0x24),
+            pc: header.reset,
+            s: 0xFF,
+            x: 0,
+            y: 0,
+        };
+        Some((Self::new(header.sp), M6502::new(core), ram))
+    }
+
+    pub fn exit_code(&self) -> Option<u8> {
+        self.exit
+    }
+
+    /// Services a hook if the cycle on `bus` fetches one, answering the
+    /// fetch itself. Returns whether it did; the cycle must then not be
+    /// passed on to memory.
+    pub fn trap(&mut self, cpu: &mut M6502, bus: &mut Bus, mem: &mut impl Memory) -> bool {
+        if !bus.sync() || !(Self::OPEN..=Self::EXIT).contains(&bus.addr) {
+            return false;
+        }
+
+        let core = cpu.core_mut();
+        let ax = u16::from_le_bytes([core.a, core.x]);
+        let result = match bus.addr {
+            Self::OPEN => self.open(core.y, mem),
+            Self::CLOSE => self.close(ax),
+            Self::READ => self.read(ax, mem),
+            Self::WRITE => self.write(ax, mem),
+            Self::ARGS => self.args(ax, mem),
+            _ => {
+                self.exit = Some(core.a);
+                ax
+            }
+        };
+        [core.a, core.x] = result.to_le_bytes();
+        bus.respond(RTS);
+        true
+    }
+
+    // Runs until the program exits or `limit` cycles pass.
+    pub fn run(&mut self, cpu: &mut M6502, mem: &mut impl Memory, limit: u64) -> Option<u8> {
+        let mut bus = Bus::new();
+        for _ in 0..limit {
+            cpu.clock(&mut bus);
+            if !self.trap(cpu, &mut bus, mem) {
+                mem.access(&mut bus);
+            }
+            if self.exit.is_some() {
+                break;
+            }
+        }
+        self.exit
+    }
+
+    fn pop(&self, mem: &mut impl Memory, incr: u16) -> u16 {
+        let addr = read_word(mem, self.sp as u16);
+        write_word(mem, self.sp as u16, addr.wrapping_add(incr));
+        read_word(mem, addr)
+    }
+
+    fn open(&mut self, arg_bytes: u8, mem: &mut impl Memory) -> u16 {
+        self.pop(mem, (arg_bytes as u16).saturating_sub(4));
+        let flags = self.pop(mem, 2);
+        let mut name_addr = self.pop(mem, 2);
+
+        let mut name = Vec::new();
+        loop {
+            let byte = mem.read(name_addr).unwrap_or(0);
+            if byte == 0 {
+                break;
+            }
+            name.push(byte);
+            name_addr = name_addr.wrapping_add(1);
+        }
+
+        let mut options = OpenOptions::new();
+        options
+            .read(flags & O_RDONLY != 0)
+            .write(flags & O_WRONLY != 0)
+            .create(flags & O_CREAT != 0)
+            .truncate(flags & O_TRUNC != 0)
+            .append(flags & O_APPEND != 0)
+            .create_new(flags & (O_CREAT | O_EXCL) == O_CREAT | O_EXCL);
+        let Ok(file) = options.open(String::from_utf8_lossy(&name).as_ref()) else {
+            return ERROR;
+        };
+
+        let fd = match self.files.iter().position(Option::is_none) {
+            Some(fd) => fd,
+            None => {
+                self.files.push(None);
+                self.files.len() - 1
+            }
+        };
+        self.files[fd] = Some(Handle::File(file));
+        fd as u16
+    }
+    fn close(&mut self, fd: u16) -> u16 {
+        match self.files.get_mut(fd as usize).and_then(Option::take) {
+            Some(_) => 0,
+            None => ERROR,
+        }
+    }
+    fn read(&mut self, count: u16, mem: &mut impl Memory) -> u16 {
+        let mut addr = self.pop(mem, 2);
+        let fd = self.pop(mem, 2);
+
+        let mut buf = vec![0; count as usize];
+        let len = match self.files.get_mut(fd as usize) {
+            Some(Some(Handle::Input(input))) => input.read(&mut buf),
+            Some(Some(Handle::File(file))) => file.read(&mut buf),
+            _ => return ERROR,
+        };
+        let Ok(len) = len else {
+            return ERROR;
+        };
+        for &byte in &buf[..len] {
+            mem.write(addr, byte);
+            addr = addr.wrapping_add(1);
+        }
+        len as u16
+    }
+    fn write(&mut self, count: u16, mem: &mut impl Memory) -> u16 {
+        let addr = self.pop(mem, 2);
+        let fd = self.pop(mem, 2);
+
+        let buf: Vec<u8> = (0..count)
+            .map(|i| mem.read(addr.wrapping_add(i)).unwrap_or(0))
+            .collect();
+        let written = match self.files.get_mut(fd as usize) {
+            Some(Some(Handle::Output(output))) => output.write(&buf).and_then(|len| {
+                output.flush()?;
+                Ok(len)
+            }),
+            Some(Some(Handle::File(file))) => file.write(&buf),
+            _ => return ERROR,
+        };
+        written.map_or(ERROR, |len| len as u16)
+    }
+    // Copies the arguments below the C stack and points `*argv` at them.
+    fn args(&mut self, argv: u16, mem: &mut impl Memory) -> u16 {
+        let argc = self.args.len() as u16;
+        let mut sp = read_word(mem, self.sp as u16);
+        let mut table = sp.wrapping_sub((argc + 1) * 2);
+        write_word(mem, argv, table);
+
+        sp = table;
+        for arg in &self.args {
+            sp = sp.wrapping_sub(arg.len() as u16 + 1);
+            for (i, &byte) in arg.as_bytes().iter().chain(&[0]).enumerate() {
+                mem.write(sp.wrapping_add(i as u16), byte);
+            }
+            write_word(mem, table, sp);
+            table = table.wrapping_add(2);
+        }
+        write_word(mem, table, 0);
+
+        write_word(mem, self.sp as u16, sp);
+        argc
+    }
+}
+
+fn read_word(mem: &mut impl Memory, addr: u16) -> u16 {
+    let lo = mem.read(addr).unwrap_or(0);
+    let hi = mem.read(addr.wrapping_add(1)).unwrap_or(0);
+    u16::from_le_bytes([lo, hi])
+}
+fn write_word(mem: &mut impl Memory, addr: u16, value: u16) {
+    let [lo, hi] = value.to_le_bytes();
+    mem.write(addr, lo);
+    mem.write(addr.wrapping_add(1), hi);
+}
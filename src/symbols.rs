@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::loader::LoadError;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Object,
+    Label,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u16,
+    pub size: u16,
+    pub kind: SymbolKind,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    by_addr: BTreeMap<u16, Vec<Symbol>>,
+    by_name: HashMap<String, u16>,
+}
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A name defined twice keeps its latest address.
+    pub fn insert(&mut self, symbol: Symbol) {
+        if let Some(old) = self.by_name.insert(symbol.name.clone(), symbol.addr) {
+            let symbols = self.by_addr.get_mut(&old).unwrap();
+            symbols.retain(|s| s.name != symbol.name);
+            if symbols.is_empty() {
+                self.by_addr.remove(&old);
+            }
+        }
+        self.by_addr.entry(symbol.addr).or_default().push(symbol);
+    }
+    pub fn extend(&mut self, symbols: impl IntoIterator<Item = Symbol>) {
+        for symbol in symbols {
+            self.insert(symbol);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.by_addr.values().flatten()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        let addr = self.by_name.get(name)?;
+        self.by_addr[addr].iter().find(|s| s.name == name)
+    }
+    pub fn at(&self, addr: u16) -> &[Symbol] {
+        self.by_addr.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    // The symbol at or before `addr` and the offset into it. A symbol with
+    // a size only covers that many bytes; labels run up to the next symbol.
+    pub fn lookup(&self, addr: u16) -> Option<(&Symbol, u16)> {
+        let (&start, symbols) = self.by_addr.range(..=addr).next_back()?;
+        let offset = addr - start;
+        let symbol = symbols
+            .iter()
+            .find(|s| s.kind != SymbolKind::Label)
+            .unwrap_or(&symbols[0]);
+        if symbol.size != 0 && offset >= symbol.size {
+            return None;
+        }
+        Some((symbol, offset))
+    }
+    pub fn describe(&self, addr: u16) -> Option<String> {
+        let (symbol, offset) = self.lookup(addr)?;
+        Some(if offset == 0 {
+            symbol.name.clone()
+        } else {
+            format!("{}+{offset}", symbol.name)
+        })
+    }
+}
+
+// VICE monitor labels as written by `ld65 -Ln`: `al C:0801 .start`.
+pub fn parse_vice(text: &str) -> Result<SymbolTable, LoadError> {
+    let mut table = SymbolTable::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let syntax = LoadError::Syntax { line: i + 1 };
+        if command != "al" {
+            return Err(syntax);
+        }
+        let (Some(addr), Some(name), None) = (words.next(), words.next(), words.next()) else {
+            return Err(syntax);
+        };
+        let addr = addr.rsplit(':').next().unwrap();
+        let addr = u16::from_str_radix(addr, 16).map_err(|_| syntax)?;
+        table.insert(label(name.trim_start_matches('.'), addr));
+    }
+    Ok(table)
+}
+
+// Assignments like `name = $addr`, one per line, with `;` comments.
+pub fn parse_labels(text: &str) -> Result<SymbolTable, LoadError> {
+    let mut table = SymbolTable::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let syntax = LoadError::Syntax { line: i + 1 };
+        let (name, value) = line.split_once('=').ok_or(syntax.clone())?;
+        let (name, value) = (name.trim(), value.trim());
+        let addr = if let Some(hex) = value.strip_prefix('$') {
+            u16::from_str_radix(hex, 16)
+        } else if let Some(hex) = value.strip_prefix("0x") {
+            u16::from_str_radix(hex, 16)
+        } else {
+            value.parse()
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(syntax);
+        }
+        table.insert(label(name, addr.map_err(|_| syntax)?));
+    }
+    Ok(table)
+}
+
+fn label(name: &str, addr: u16) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        addr,
+        size: 0,
+        kind: SymbolKind::Label,
+    }
+}
@@ -1,9 +1,50 @@
 use serde::Deserialize;
 
+mod acia;
+mod asm;
+mod cia;
+mod debug_info;
+mod disasm;
+mod elf;
+mod fast;
+mod fuzz;
+mod ines;
+mod interrupts;
+mod irq;
+mod loader;
+mod mapper;
+mod memory;
+mod microcode;
+mod mos_sim;
+mod o65;
+mod open_bus;
+mod pia;
+mod reference;
+mod riot;
+mod sim65;
+mod switch_level;
+mod symbols;
+mod via;
+
 use crate::{
     Bus, M6502,
     core::{Core, P},
+    memory::Memory,
 };
+use std::{cell::RefCell, io, rc::Rc};
+
+// Captures what a simulated program writes to its host.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
 
 #[derive(Deserialize)]
 struct Test {
@@ -38,11 +79,7 @@ fn run_test(test: &Test, ram: &mut [u8; 65536]) {
 
     for cycle in &test.cycles {
         cpu.clock(&mut bus);
-        if bus.rw() {
-            bus.data = ram[bus.addr as usize];
-        } else {
-            ram[bus.addr as usize] = bus.data;
-        }
+        ram.access(&mut bus);
 
         compare_cycle(cycle, bus);
     }
@@ -151,6 +188,29 @@ fn run_test_file(path: &str) {
     }
 }
 
+#[test]
+fn jmp_absolute_takes_three_cycles() {
+    let mut ram = [0; 65536];
+    ram[0x0200..0x0203].copy_from_slice(&[0x4C, 0x00, 0x02]);
+    let mut cpu = prepare_cpu(&State {
+        a: 0,
+        p: 0x24,
+        pc: 0x0200,
+        s: 0xFD,
+        x: 0,
+        y: 0,
+        ram: Vec::new(),
+    });
+    let mut bus = Bus::new();
+    for _ in 0..7 {
+        cpu.clock(&mut bus);
+        ram.access(&mut bus);
+        assert!(bus.rw());
+    }
+    assert!(bus.sync());
+    assert_eq!(ram[0x0200], 0x4C);
+}
+
 #[test]
 fn opcode_00_brk_implied() {
     let path = concat!(env!("CARGO_MANIFEST_DIR"), "/65x02/nes6502/v1/00.json");
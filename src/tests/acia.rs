@@ -0,0 +1,107 @@
+use crate::{
+    devices::acia::{Acia, AciaModel},
+    irq::IrqLine,
+    memory::Memory,
+    serial::Queue,
+};
+use std::{cell::RefCell, rc::Rc};
+
+const DATA: u16 = 0;
+const STATUS: u16 = 1;
+const COMMAND: u16 = 2;
+const CONTROL: u16 = 3;
+
+fn acia(model: AciaModel, control: u8, command: u8) -> (Acia, Rc<RefCell<Queue>>) {
+    let host = Rc::new(RefCell::new(Queue::new()));
+    let mut acia = Acia::new(model).with_host(host.clone());
+    acia.write(CONTROL, control);
+    acia.write(COMMAND, command);
+    (acia, host)
+}
+
+#[test]
+fn transmit_takes_a_frame_at_the_baud_rate() {
+    let (mut acia, host) = acia(AciaModel::Mos6551, 0x1E, 0x0B);
+    acia.write(DATA, b'A');
+    assert_eq!(acia.read(STATUS).unwrap() & 0x10, 0);
+
+    // 10 bits at 9600 baud is 1041.7us; one more cycle moves TDR to the shifter.
+    acia.clock();
+    assert_eq!(acia.read(STATUS).unwrap() & 0x10, 0x10);
+    for _ in 0..1041 {
+        acia.clock();
+    }
+    assert!(host.borrow().output.is_empty());
+    acia.clock();
+    assert_eq!(host.borrow().output, b"A");
+}
+#[test]
+fn transmitter_is_double_buffered() {
+    let (mut acia, host) = acia(AciaModel::Mos6551, 0x1F, 0x0B);
+    acia.write(DATA, b'H');
+    acia.clock();
+    acia.write(DATA, b'i');
+    acia.clock();
+    assert_eq!(acia.read(STATUS).unwrap() & 0x10, 0);
+
+    for _ in 0..2000 {
+        acia.clock();
+    }
+    assert_eq!(host.borrow().output, b"Hi");
+    assert_eq!(acia.read(STATUS).unwrap() & 0x10, 0x10);
+}
+#[test]
+fn wdc_transmit_bug() {
+    let line = IrqLine::new();
+    let (acia, host) = acia(AciaModel::Wdc65C51, 0x1F, 0x05);
+    let mut acia = acia.with_irq(line.source("acia"));
+    assert!(!line.level());
+
+    acia.write(DATA, b'H');
+    assert_eq!(acia.read(STATUS).unwrap() & 0x10, 0x10);
+    acia.clock();
+    acia.write(DATA, b'i');
+    for _ in 0..2000 {
+        acia.clock();
+    }
+    assert_eq!(host.borrow().output, b"i");
+    assert!(!line.level());
+}
+#[test]
+fn receive_raises_irq() {
+    let line = IrqLine::new();
+    let (acia, host) = acia(AciaModel::Mos6551, 0x10, 0x09);
+    let mut acia = acia.with_irq(line.source("acia"));
+    host.borrow_mut().push(b"OK");
+
+    acia.clock();
+    assert!(line.level());
+    assert_eq!(acia.read(STATUS), Some(0x98));
+    assert!(!line.level());
+    assert_eq!(acia.read(DATA), Some(b'O'));
+    assert_eq!(acia.read(STATUS), Some(0x10));
+
+    acia.clock();
+    assert_eq!(acia.read(DATA), Some(b'K'));
+}
+#[test]
+fn overrun_keeps_the_first_byte() {
+    let (mut acia, host) = acia(AciaModel::Mos6551, 0x10, 0x0B);
+    host.borrow_mut().push(&[1, 2]);
+    acia.clock();
+    acia.clock();
+    assert_eq!(acia.read(STATUS), Some(0x1C));
+    assert_eq!(acia.read(DATA), Some(1));
+    assert_eq!(acia.read(STATUS), Some(0x10));
+
+    acia.write(STATUS, 0);
+    assert_eq!(acia.read(COMMAND), Some(0x00));
+}
+#[test]
+fn echo_mode_and_word_length() {
+    let (mut acia, host) = acia(AciaModel::Mos6551, 0x30, 0x13);
+    host.borrow_mut().push(b"\xC1");
+    acia.clock();
+    assert_eq!(acia.read(DATA), Some(0x41));
+    assert_eq!(host.borrow().output, b"A");
+}
@@ -0,0 +1,61 @@
+use crate::{
+    asm::{AsmError, assemble, opcode},
+    disasm::Instruction,
+    instr,
+    symbols::{Symbol, SymbolKind, SymbolTable},
+};
+
+#[test]
+fn round_trips_the_disassembler() {
+    for byte in 0..=255u8 {
+        let (op, am) = instr::decode(byte);
+        if opcode(op, am) != Some(byte) {
+            continue;
+        }
+        let bytes = [byte, 0x12, 0x34];
+        let instruction = Instruction::decode(&bytes);
+        let text = instruction.format(0x0400, None);
+        let size = instruction.size() as usize;
+        assert_eq!(
+            assemble(&text, 0x0400, None).unwrap(),
+            bytes[..size],
+            "{text}"
+        );
+    }
+}
+#[test]
+fn mode_selection() {
+    assert_eq!(assemble("lda $10", 0, None).unwrap(), [0xA5, 0x10]);
+    assert_eq!(assemble("lda $0010", 0, None).unwrap(), [0xAD, 0x10, 0x00]);
+    assert_eq!(assemble("ldx 16,y", 0, None).unwrap(), [0xB6, 0x10]);
+    assert_eq!(assemble("sta $10,y", 0, None).unwrap(), [0x99, 0x10, 0x00]);
+    assert_eq!(assemble("asl", 0, None).unwrap(), [0x0A]);
+    assert_eq!(assemble("nop", 0, None).unwrap(), [0xEA]);
+    assert_eq!(assemble("sbc #%1010", 0, None).unwrap(), [0xE9, 0x0A]);
+    assert_eq!(assemble("jmp *", 0x0300, None).unwrap(), [0x4C, 0x00, 0x03]);
+    assert_eq!(assemble("bne *", 0x0300, None).unwrap(), [0xD0, 0xFE]);
+}
+#[test]
+fn symbols_and_errors() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert(Symbol {
+        name: "ptr".to_string(),
+        addr: 0x00FB,
+        size: 0,
+        kind: SymbolKind::Object,
+    });
+    let symbols = Some(&symbols);
+    assert_eq!(assemble("lda (ptr),y", 0, symbols).unwrap(), [0xB1, 0xFB]);
+    assert_eq!(assemble("inc ptr", 0, symbols).unwrap(), [0xE6, 0xFB]);
+
+    let err = |text| assemble(text, 0x0300, symbols).unwrap_err();
+    assert_eq!(err("lda buf"), AsmError::UndefinedSymbol("buf".to_string()));
+    assert_eq!(err("mov a"), AsmError::UnknownMnemonic("mov".to_string()));
+    assert_eq!(
+        err("lda ($1234),y"),
+        AsmError::BadOperand("($1234),y".to_string())
+    );
+    assert_eq!(err("ldx $10,x"), AsmError::NoSuchMode);
+    assert_eq!(err("beq $0400"), AsmError::BranchOutOfRange);
+    assert_eq!(err("lda #"), AsmError::BadOperand(String::new()));
+}
@@ -0,0 +1,207 @@
+use crate::{
+    devices::cia::{Cia, CiaModel},
+    irq::IrqLine,
+    memory::Memory,
+};
+
+const PRB: u16 = 0x1;
+const TAL: u16 = 0x4;
+const TAH: u16 = 0x5;
+const TBL: u16 = 0x6;
+const TBH: u16 = 0x7;
+const TOD_10THS: u16 = 0x8;
+const TOD_SEC: u16 = 0x9;
+const TOD_MIN: u16 = 0xA;
+const TOD_HR: u16 = 0xB;
+const SDR: u16 = 0xC;
+const ICR: u16 = 0xD;
+const CRA: u16 = 0xE;
+const CRB: u16 = 0xF;
+
+fn cycles_until_irq(cia: &mut Cia, limit: usize) -> usize {
+    for i in 1..=limit {
+        cia.clock();
+        if cia.irq() {
+            return i;
+        }
+    }
+    panic!("no interrupt within {limit} cycles");
+}
+
+#[test]
+fn timer_a_continuous() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    cia.write(ICR, 0x81);
+    cia.write(TAL, 4);
+    cia.write(TAH, 0);
+    cia.write(CRA, 0x01);
+
+    let first = cycles_until_irq(&mut cia, 100);
+    assert_eq!(cia.read(ICR), Some(0x81));
+    assert!(!cia.irq());
+    assert_eq!(cycles_until_irq(&mut cia, 100), 5);
+    assert!(first > 5);
+}
+#[test]
+fn timer_a_one_shot_stops() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    cia.write(TAL, 2);
+    cia.write(CRA, 0x08);
+    cia.write(TAH, 0);
+    assert_eq!(cia.read(CRA).unwrap() & 1, 1);
+
+    for _ in 0..10 {
+        cia.clock();
+    }
+    assert_eq!(cia.read(CRA).unwrap() & 1, 0);
+    assert_eq!(cia.read(ICR), Some(0x01));
+    assert_eq!(cia.read(TAL), Some(2));
+}
+#[test]
+fn timer_b_counts_timer_a_underflows() {
+    let mut cia = Cia::new(CiaModel::Mos8521);
+    cia.write(TAL, 1);
+    cia.write(TAH, 0);
+    cia.write(TBL, 2);
+    cia.write(TBH, 0);
+    cia.write(CRB, 0x41);
+    cia.write(CRA, 0x01);
+
+    for _ in 0..5 {
+        cia.clock();
+    }
+    assert_eq!(cia.read(TBL), Some(0));
+    assert_eq!(cia.read(ICR), Some(0x01));
+    for _ in 0..3 {
+        cia.clock();
+    }
+    assert_eq!(cia.read(ICR).unwrap() & 0x02, 0x02);
+}
+#[test]
+fn old_cia_delays_timer_b_interrupt() {
+    let run = |model| {
+        let mut cia = Cia::new(model);
+        cia.write(ICR, 0x82);
+        cia.write(TBL, 3);
+        cia.write(TBH, 0);
+        cia.write(CRB, 0x01);
+        cycles_until_irq(&mut cia, 100)
+    };
+    assert_eq!(run(CiaModel::Mos6526), run(CiaModel::Mos8521) + 1);
+}
+#[test]
+fn icr_read_races_interrupt() {
+    let mut cia = Cia::new(CiaModel::Mos8521);
+    cia.write(ICR, 0x81);
+    cia.write(TAL, 3);
+    cia.write(TAH, 0);
+    cia.write(CRA, 0x01);
+
+    let mut raced = cia.clone();
+
+    for _ in 0..6 {
+        raced.clock();
+    }
+    assert!(!raced.irq());
+    assert_eq!(raced.read(ICR), Some(0x01));
+    raced.clock();
+    assert!(!raced.irq());
+
+    for _ in 0..7 {
+        cia.clock();
+    }
+    assert!(cia.irq());
+    assert_eq!(cia.read(ICR), Some(0x81));
+}
+#[test]
+fn tod_counts_and_latches() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    cia.write(CRA, 0x80);
+    cia.write(TOD_HR, 0x11);
+    cia.write(TOD_MIN, 0x59);
+    cia.write(TOD_SEC, 0x59);
+    cia.write(TOD_10THS, 0x09);
+
+    assert_eq!(cia.read(TOD_HR), Some(0x11));
+    for _ in 0..5 {
+        cia.tod_tick();
+    }
+    assert_eq!(cia.read(TOD_SEC), Some(0x59));
+    assert_eq!(cia.read(TOD_10THS), Some(0x09));
+
+    assert_eq!(cia.read(TOD_HR), Some(0x92));
+    assert_eq!(cia.read(TOD_MIN), Some(0x00));
+    assert_eq!(cia.read(TOD_10THS), Some(0x00));
+}
+#[test]
+fn tod_alarm() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    cia.write(CRB, 0x80);
+    cia.write(TOD_HR, 0x01);
+    cia.write(TOD_MIN, 0x00);
+    cia.write(TOD_SEC, 0x01);
+    cia.write(TOD_10THS, 0x00);
+    cia.write(CRB, 0x00);
+
+    for _ in 0..60 {
+        cia.tod_tick();
+    }
+    cia.clock();
+    assert_eq!(cia.read(ICR), Some(0x04));
+}
+#[test]
+fn serial_output() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    cia.write(TAL, 1);
+    cia.write(TAH, 0);
+    cia.write(CRA, 0x41);
+    cia.write(SDR, 0b1100_1010);
+
+    let mut bits = Vec::new();
+    let mut last_cnt = cia.cnt();
+    for _ in 0..64 {
+        cia.clock();
+        if !last_cnt && cia.cnt() {
+            bits.push(cia.sp() as u8);
+        }
+        last_cnt = cia.cnt();
+    }
+    assert_eq!(bits, vec![1, 1, 0, 0, 1, 0, 1, 0]);
+    assert_eq!(cia.read(ICR).unwrap() & 0x08, 0x08);
+}
+#[test]
+fn serial_input() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    for bit in [1, 0, 0, 1, 0, 1, 1, 0] {
+        cia.set_sp(bit != 0);
+        cia.set_cnt(false);
+        cia.set_cnt(true);
+    }
+    cia.clock();
+    assert_eq!(cia.read(SDR), Some(0b1001_0110));
+    assert_eq!(cia.read(ICR), Some(0x08));
+}
+#[test]
+fn timer_a_toggles_pb6() {
+    let mut cia = Cia::new(CiaModel::Mos6526);
+    cia.write(TAL, 2);
+    cia.write(TAH, 0);
+    cia.write(CRA, 0x07);
+    assert_eq!(cia.read(PRB).unwrap() & 0x40, 0x40);
+    for _ in 0..4 {
+        cia.clock();
+    }
+    assert_eq!(cia.read(PRB).unwrap() & 0x40, 0x00);
+}
+#[test]
+fn drives_irq_line() {
+    let line = IrqLine::new();
+    let mut cia = Cia::new(CiaModel::Mos6526).with_irq(line.source("cia1"));
+    cia.write(ICR, 0x90);
+    cia.set_flag(false);
+    cia.clock();
+    cia.clock();
+    assert!(line.level());
+    cia.read(ICR);
+    assert!(!line.level());
+}
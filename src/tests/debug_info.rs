@@ -0,0 +1,130 @@
+use crate::{
+    debug_info::{DebugInfo, LineKind, SourceLine},
+    loader::LoadError,
+    symbols::SymbolKind,
+};
+
+// Trimmed from `cl65 -g --dbgfile` output for a C program with an assembly
+// startup file: main.c line 4 compiled to the bytes of main.s line 20, and a
+// macro expanded on line 12 of crt0.s.
+const DBG: &str = r#"version	major=2,minor=0
+info	csym=0,file=2,lib=0,line=5,mod=2,scope=3,seg=2,span=5,sym=3,type=0
+file	id=0,name="crt0.s",size=420,mtime=0x5F000000,mod=0
+file	id=1,name="src/main.c",size=80,mtime=0x5F000000,mod=1
+mod	id=0,name="crt0.o",file=0
+mod	id=1,name="main.o",file=1
+seg	id=0,name="STARTUP",start=0x000801,size=0x0010,addrsize=absolute,type=ro,oname="a.prg",ooffs=2
+seg	id=1,name="CODE",start=0x000811,size=0x0020,addrsize=absolute,type=ro,oname="a.prg",ooffs=18
+span	id=0,seg=0,start=0,size=3
+span	id=1,seg=0,start=3,size=6
+span	id=2,seg=1,start=0,size=8
+span	id=3,seg=1,start=0,size=32
+span	id=4,seg=0,start=3,size=2
+line	id=0,file=0,line=10,span=0
+line	id=1,file=0,line=12,span=1
+line	id=2,file=0,line=3,type=2,count=1,span=4
+line	id=3,file=1,line=4,type=1,span=2
+line	id=4,file=1,line=20,span=2
+scope	id=0,name="",mod=0,size=16,span=0+1
+scope	id=1,name="_main",mod=1,type=scope,size=32,parent=0,span=3
+scope	id=2,name="loop",mod=1,type=scope,size=8,parent=1,span=2
+sym	id=0,name="start",addrsize=absolute,scope=0,def=0,val=0x801,seg=0,type=lab
+sym	id=1,name="_main",addrsize=absolute,size=32,scope=0,def=3,val=0x811,seg=1,type=lab
+sym	id=2,name="_exit",addrsize=absolute,scope=0,type=imp,exp=5
+"#;
+
+fn line(file: &str, line: u32, kind: LineKind) -> SourceLine {
+    SourceLine {
+        file: file.to_string(),
+        line,
+        kind,
+    }
+}
+
+#[test]
+fn lines_map_to_addresses() {
+    let info = DebugInfo::parse_dbg(DBG).unwrap();
+
+    assert_eq!(info.files(), ["crt0.s", "src/main.c"]);
+    assert_eq!(
+        info.line_at(0x0801),
+        Some(line("crt0.s", 10, LineKind::Assembly))
+    );
+    assert_eq!(
+        info.line_at(0x0808),
+        Some(line("crt0.s", 12, LineKind::Assembly))
+    );
+    assert_eq!(
+        info.line_at(0x0811),
+        Some(line("src/main.c", 20, LineKind::Assembly))
+    );
+    assert_eq!(info.line_at(0x0819), None);
+    assert_eq!(
+        info.lines_at(0x0804),
+        [
+            line("crt0.s", 12, LineKind::Assembly),
+            line("crt0.s", 3, LineKind::Macro)
+        ]
+    );
+    assert_eq!(
+        info.lines_at(0x0815)[1],
+        line("src/main.c", 4, LineKind::External)
+    );
+
+    assert_eq!(info.addr_of_line("crt0.s", 12), Some(0x0804));
+    assert_eq!(info.addr_of_line("main.c", 4), Some(0x0811));
+    assert_eq!(info.addr_of_line("main.c", 5), None);
+}
+#[test]
+fn scopes_and_symbols() {
+    let info = DebugInfo::parse_dbg(DBG).unwrap();
+
+    assert_eq!(info.scope_at(0x0815), Some("loop"));
+    assert_eq!(info.scope_at(0x0820), Some("_main"));
+    assert_eq!(info.scope_at(0x0801), None);
+
+    assert_eq!(info.symbols.len(), 2);
+    let main = info.symbols.get("_main").unwrap();
+    assert_eq!(
+        (main.addr, main.size, main.kind),
+        (0x0811, 32, SymbolKind::Label)
+    );
+    assert_eq!(info.symbols.describe(0x0803).as_deref(), Some("start+2"));
+    assert!(info.symbols.get("_exit").is_none());
+}
+#[test]
+fn labels_are_qualified_by_scope() {
+    // Two procs with a `loop` label each, and a cheap local after `start`.
+    let info = DebugInfo::parse_dbg(
+        r#"scope	id=0,name=""
+scope	id=1,name="fill",parent=0
+scope	id=2,name="copy",parent=0
+scope	id=3,name="inner",parent=2
+sym	id=0,name="start",scope=0,val=0x801,type=lab
+sym	id=1,name="@skip",scope=0,parent=0,val=0x806,type=lab
+sym	id=2,name="loop",scope=1,val=0x810,type=lab
+sym	id=3,name="loop",scope=2,val=0x820,type=lab
+sym	id=4,name="loop",scope=3,val=0x828,type=lab
+"#,
+    )
+    .unwrap();
+
+    let addr = |name| info.symbols.get(name).map(|sym| sym.addr);
+    assert_eq!(addr("start"), Some(0x0801));
+    assert_eq!(addr("start@skip"), Some(0x0806));
+    assert_eq!(addr("fill::loop"), Some(0x0810));
+    assert_eq!(addr("copy::loop"), Some(0x0820));
+    assert_eq!(addr("copy::inner::loop"), Some(0x0828));
+    assert_eq!(addr("loop"), None);
+}
+#[test]
+fn malformed_records() {
+    assert_eq!(
+        DebugInfo::parse_dbg("version\tmajor=2,minor=0\nfile\tid=0,name=\"a.s\n"),
+        Err(LoadError::Syntax { line: 2 })
+    );
+    assert_eq!(
+        DebugInfo::parse_dbg("span\tid=0,seg=3,start=0,size=1\n"),
+        Err(LoadError::BadHeader)
+    );
+}
@@ -0,0 +1,54 @@
+use crate::{
+    disasm::Instruction,
+    instr::{Am, Op},
+    symbols::{Symbol, SymbolKind, SymbolTable},
+};
+
+fn text(bytes: &[u8], pc: u16) -> String {
+    Instruction::decode(bytes).format(pc, None)
+}
+
+#[test]
+fn addressing_mode_syntax() {
+    assert_eq!(text(&[0xEA], 0), "NOP");
+    assert_eq!(text(&[0x0A], 0), "ASL A");
+    assert_eq!(text(&[0xA9, 0x05], 0), "LDA #$05");
+    assert_eq!(text(&[0xB5, 0x10], 0), "LDA $10,X");
+    assert_eq!(text(&[0xB6, 0x10], 0), "LDX $10,Y");
+    assert_eq!(text(&[0xA1, 0x20], 0), "LDA ($20,X)");
+    assert_eq!(text(&[0xB1, 0x20], 0), "LDA ($20),Y");
+    assert_eq!(text(&[0x9D, 0x00, 0x02], 0), "STA $0200,X");
+    assert_eq!(text(&[0x6C, 0xFC, 0xFF], 0), "JMP ($FFFC)");
+    assert_eq!(text(&[0xA7, 0x33], 0), "LAX $33");
+    assert_eq!(text(&[0x02], 0), "JAM");
+}
+#[test]
+fn branches_and_symbols() {
+    assert_eq!(text(&[0xD0, 0xFE], 0x0400), "BNE $0400");
+    assert_eq!(text(&[0x10, 0x7F], 0xFFF0), "BPL $0071");
+
+    let mut symbols = SymbolTable::new();
+    symbols.insert(Symbol {
+        name: "chrout".to_string(),
+        addr: 0xFFD2,
+        size: 0,
+        kind: SymbolKind::Label,
+    });
+    let jsr = Instruction::decode(&[0x20, 0xD2, 0xFF]);
+    assert_eq!(jsr.format(0, Some(&symbols)), "JSR chrout");
+    let lda = Instruction::decode(&[0xAD, 0xD3, 0xFF]);
+    assert_eq!(lda.format(0, Some(&symbols)), "LDA $FFD3");
+}
+#[test]
+fn sizes_and_short_input() {
+    let jmp = Instruction::decode(&[0x4C, 0x34]);
+    assert_eq!(
+        (jmp.op, jmp.am, jmp.operand),
+        (Op::Jmp, Am::Absolute, 0x0034)
+    );
+    assert_eq!(jmp.size(), 3);
+    assert_eq!(Instruction::decode(&[0x00]).size(), 1);
+    assert_eq!(Instruction::decode(&[0x91, 0x00]).size(), 2);
+    assert_eq!(Instruction::decode(&[0x91]).target(0), Some(0x0000));
+    assert_eq!(Instruction::decode(&[0xA9, 0x00]).target(0), None);
+}
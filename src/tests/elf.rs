@@ -0,0 +1,110 @@
+use crate::{
+    core::{Core, P},
+    elf::Elf,
+    loader::{LoadError, Segment},
+    symbols::SymbolKind,
+};
+
+const TEXT: [u8; 4] = [0xA9, 0x01, 0xEA, 0x60];
+
+fn push32(out: &mut Vec<u8>, words: &[u32]) {
+    for word in words {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+}
+fn sym(out: &mut Vec<u8>, name: u32, value: u32, size: u32, info: u8, shndx: u16) {
+    push32(out, &[name, value, size]);
+    out.extend_from_slice(&[info, 0]);
+    out.extend_from_slice(&shndx.to_le_bytes());
+}
+
+// Header, one PT_LOAD program header, the text, .strtab, .symtab and the
+// section headers for [null, .text, .symtab, .strtab].
+fn elf(machine: u16) -> Vec<u8> {
+    let strtab = b"\0main\0buf\0__rc0\0extern\0";
+    let text_off = 52 + 32;
+    let str_off = text_off + TEXT.len() as u32;
+    let sym_off = str_off + strtab.len() as u32;
+    let sh_off = sym_off + 6 * 16;
+
+    let mut f = b"\x7FELF\x01\x01\x01".to_vec();
+    f.resize(16, 0);
+    f.extend_from_slice(&2u16.to_le_bytes());
+    f.extend_from_slice(&machine.to_le_bytes());
+    push32(&mut f, &[1, 0x0200, 52, sh_off, 0]);
+    for half in [52u16, 32, 1, 40, 4, 0] {
+        f.extend_from_slice(&half.to_le_bytes());
+    }
+
+    push32(&mut f, &[1, text_off, 0x0200, 0x0200, 4, 8, 5, 1]);
+    f.extend_from_slice(&TEXT);
+    f.extend_from_slice(strtab);
+
+    sym(&mut f, 0, 0, 0, 0, 0);
+    sym(&mut f, 0, 0x0200, 0, 0x03, 1);
+    sym(&mut f, 1, 0x0200, 3, 0x12, 1);
+    sym(&mut f, 6, 0x0204, 4, 0x11, 1);
+    sym(&mut f, 10, 0x0002, 0, 0x10, 0xFFF1);
+    sym(&mut f, 16, 0, 0, 0x10, 0);
+
+    push32(&mut f, &[0; 10]);
+    push32(&mut f, &[0, 1, 6, 0x0200, text_off, 4, 0, 0, 1, 0]);
+    push32(&mut f, &[0, 2, 0, 0, sym_off, 6 * 16, 3, 2, 4, 16]);
+    push32(
+        &mut f,
+        &[0, 3, 0, 0, str_off, strtab.len() as u32, 0, 0, 1, 0],
+    );
+    f
+}
+
+#[test]
+fn loads_segments_and_entry() {
+    let elf = Elf::parse(&elf(6502)).unwrap();
+    let mut text = TEXT.to_vec();
+    text.resize(8, 0);
+    assert_eq!(
+        elf.image.segments,
+        vec![Segment {
+            addr: 0x0200,
+            data: text,
+        }]
+    );
+
+    let mut ram = [0xFF; 65536];
+    let mut core = Core {
+        a: 0,
+        p: P::new(),
+        pc: 0,
+        s: 0,
+        x: 0,
+        y: 0,
+    };
+    elf.image.boot(&mut ram, &mut core);
+    assert_eq!(core.pc, 0x0200);
+    assert_eq!(ram[0x0201], 0x01);
+    assert_eq!(ram[0x0207], 0x00);
+}
+#[test]
+fn drops_bank_bits() {
+    let mut file = elf(6502);
+    file[24..28].copy_from_slice(&0x0003_0200u32.to_le_bytes());
+    file[64..68].copy_from_slice(&0x0003_0200u32.to_le_bytes());
+    let image = Elf::parse(&file).unwrap().image;
+    assert_eq!(image.start, Some(0x0200));
+    assert_eq!(image.segments[0].addr, 0x0200);
+}
+#[test]
+fn imports_symbols() {
+    let symbols = Elf::parse(&elf(6502)).unwrap().symbols;
+    assert_eq!(symbols.len(), 3);
+    assert_eq!(symbols.get("main").unwrap().kind, SymbolKind::Function);
+    assert_eq!(symbols.get("buf").unwrap().kind, SymbolKind::Object);
+    assert_eq!(symbols.get("__rc0").unwrap().addr, 0x0002);
+    assert!(symbols.get("extern").is_none());
+    assert_eq!(symbols.describe(0x0202).as_deref(), Some("main+2"));
+}
+#[test]
+fn rejects_other_machines() {
+    assert_eq!(Elf::parse(&elf(3)), Err(LoadError::BadHeader));
+    assert_eq!(Elf::parse(&elf(6502)[..60]), Err(LoadError::Truncated));
+}
@@ -0,0 +1,174 @@
+use super::reference::mix;
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+    fast::FastCpu,
+    instr::{self, Op},
+    memory::Memory,
+};
+
+fn core(pc: u16) -> Core {
+    Core {
+        a: 0,
+        p: P::new().with_i(true),
+        pc,
+        s: 0xFF,
+        x: 0,
+        y: 0,
+    }
+}
+
+// Runs `M6502` from one opcode fetch to the next and returns the cycles in
+// between.
+fn instruction(cpu: &mut M6502, bus: &mut Bus, ram: &mut [u8; 65536]) -> u8 {
+    let mut cycles = 0;
+    loop {
+        ram.access(bus);
+        cpu.clock(bus);
+        cycles += 1;
+        if bus.sync() {
+            return cycles;
+        }
+    }
+}
+
+#[test]
+fn fast_matches_m6502() {
+    for case in 0..500 {
+        let mut state = mix(case);
+        let mut next = || {
+            state = mix(state);
+            state
+        };
+        let mut ram = Box::new([0; 65536]);
+        for byte in ram.iter_mut() {
+            *byte = next() as u8;
+        }
+        let bytes = next().to_le_bytes();
+        let start = Core {
+            a: bytes[0],
+            p: P::from_pull_byte(bytes[1]),
+            pc: u16::from_le_bytes([bytes[2], bytes[3]]),
+            s: bytes[4],
+            x: bytes[5],
+            y: bytes[6],
+        };
+        let mut fast_ram = ram.clone();
+
+        let mut cpu = M6502::new(start);
+        let mut bus = Bus::new();
+        cpu.clock(&mut bus);
+        let mut fast = FastCpu::new(start);
+        for _ in 0..24 {
+            if instr::decode(ram[cpu.core().pc as usize]).0 == Op::Jam {
+                break;
+            }
+            let cycles = instruction(&mut cpu, &mut bus, &mut ram);
+            assert_eq!(
+                (fast.step(&mut fast_ram), fast.core()),
+                (cycles, cpu.core()),
+                "case {case}"
+            );
+        }
+        assert!(ram == fast_ram, "case {case}");
+    }
+}
+
+#[test]
+fn switching_keeps_state() {
+    let mut ram = Box::new([0; 65536]);
+    // CLI; INX; INX; JMP $0202, with an IRQ handler of INY; RTI.
+    ram[0x0200..0x0206].copy_from_slice(&[0x58, 0xE8, 0xE8, 0x4C, 0x02, 0x02]);
+    ram[0x0300..0x0302].copy_from_slice(&[0xC8, 0x40]);
+    ram[0xFFFE..].copy_from_slice(&[0x00, 0x03]);
+
+    let mut exact = Vec::new();
+    let mut exact_ram = ram.clone();
+    let mut cpu = M6502::new(core(0x0200));
+    let mut bus = Bus::new();
+    bus.set_irq(true);
+    cpu.clock(&mut bus);
+    for _ in 0..40 {
+        let cycles = instruction(&mut cpu, &mut bus, &mut exact_ram);
+        exact.push((cpu.core(), cycles));
+    }
+    // The IRQ waits for the instruction after CLI.
+    assert_eq!(exact[1].0.x, 1);
+    assert_eq!(exact[2].0.pc, 0x0300);
+
+    // Alternates between the two on every instruction.
+    let mut switched = Vec::new();
+    let mut fast = FastCpu::new(core(0x0200));
+    fast.set_irq(true);
+    for i in 0..40 {
+        let cycles = if i % 2 == 1 {
+            fast.step(&mut ram)
+        } else {
+            let mut cpu = M6502::from(fast);
+            cpu.clock(&mut bus);
+            let cycles = instruction(&mut cpu, &mut bus, &mut ram);
+            fast = FastCpu::from(cpu);
+            cycles
+        };
+        switched.push((fast.core(), cycles));
+    }
+    assert_eq!(switched, exact);
+    assert!(ram == exact_ram);
+}
+
+#[test]
+fn pending_nmi_survives_switch() {
+    let mut ram = Box::new([0xEA; 65536]);
+    ram[0xFFFA..0xFFFC].copy_from_slice(&[0x00, 0x90]);
+    let mut fast = FastCpu::new(core(0x0200));
+    fast.set_nmi(true);
+    let mut cpu = M6502::from(fast);
+    let mut bus = Bus::new();
+    bus.set_nmi(true);
+    cpu.clock(&mut bus);
+    assert_eq!(instruction(&mut cpu, &mut bus, &mut ram), 7);
+    assert_eq!(cpu.core().pc, 0x9000);
+    assert_eq!(ram[0x01FF], 0x02);
+}
+
+#[test]
+fn switching_back_and_forth_is_lossless() {
+    let mut ram = Box::new([0xEA; 65536]);
+    // SEI; LDA #$42
+    ram[0x0200..0x0203].copy_from_slice(&[0x78, 0xA9, 0x42]);
+    let mut fast = FastCpu::new(core(0x0200));
+    fast.core_mut().p.set_i(false);
+    fast.set_irq(true);
+    fast.step(&mut ram);
+    fast.step(&mut ram);
+    assert_eq!(FastCpu::from(M6502::from(fast)), fast);
+}
+
+#[test]
+fn jam_ignores_interrupts() {
+    let mut ram = Box::new([0xEA; 65536]);
+    ram[0x0200] = 0x02;
+    ram[0xFFFA..].copy_from_slice(&[0x00, 0x90, 0x00, 0x00, 0x00, 0x90]);
+    let mut fast = FastCpu::new(core(0x0200));
+    fast.core_mut().p.set_i(false);
+    assert_eq!(fast.step(&mut ram), 5);
+    assert!(fast.jammed());
+    fast.set_irq(true);
+    fast.set_nmi(true);
+    assert_eq!(fast.step(&mut ram), 1);
+    assert_eq!(fast.core().pc, 0x0201);
+
+    let mut cpu = M6502::from(fast);
+    let mut bus = Bus::new();
+    bus.set_irq(true);
+    bus.set_nmi(true);
+    for _ in 0..20 {
+        cpu.clock(&mut bus);
+        ram.access(&mut bus);
+        assert!(!bus.sync());
+        assert_eq!(bus.addr, 0xFFFF);
+    }
+    let fast = FastCpu::from(cpu);
+    assert!(fast.jammed());
+    assert_eq!(fast.core().pc, 0x0201);
+}
@@ -0,0 +1,224 @@
+use std::{env, fmt::Write};
+
+use super::reference::{Cycle, Ram, Reference, mix};
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+};
+
+// Runs random programs from random states on `M6502` and on the reference
+// interpreter and compares every bus cycle and the registers after every
+// instruction. M6502_FUZZ_CASES and M6502_FUZZ_SEED run more or other
+// cases than the default. A mismatch is shrunk before it is reported.
+
+const CASES: u64 = 300;
+const SEED: u64 = 0x6502;
+const STEPS: usize = 24;
+const MAX_CYCLES: usize = 16;
+
+// A program placed at PC over memory that reads as a hash of the address.
+#[derive(Clone, Debug)]
+struct Case {
+    seed: u64,
+    core: Core,
+    program: Vec<u8>,
+    steps: usize,
+}
+impl Case {
+    fn random(seed: u64) -> Self {
+        let mut state = seed;
+        let mut next = || {
+            state = mix(state);
+            state
+        };
+        let bytes = next().to_le_bytes();
+        let len = next() % 32 + 1;
+        Self {
+            seed: next() | 1,
+            core: Core {
+                a: bytes[0],
+                p: P::from_pull_byte(bytes[1]),
+                pc: u16::from_le_bytes([bytes[2], bytes[3]]),
+                s: bytes[4],
+                x: bytes[5],
+                y: bytes[6],
+            },
+            program: (0..len).map(|_| next() as u8).collect(),
+            steps: STEPS,
+        }
+    }
+
+    fn ram(&self) -> Ram {
+        let mut ram = Ram::new(self.seed);
+        ram.load(self.core.pc, &self.program);
+        ram
+    }
+}
+
+struct Mismatch {
+    step: usize,
+    expected: (Vec<Cycle>, Core),
+    actual: (Vec<Cycle>, Core),
+}
+
+// The first instruction the two disagree on.
+fn mismatch(case: &Case) -> Option<Mismatch> {
+    let mut reference = Reference::new(case.core, case.ram());
+    let mut ram = case.ram();
+    let mut cpu = M6502::new(case.core);
+    let mut bus = Bus::new();
+    let mut clock = |cpu: &mut M6502, bus: &mut Bus| {
+        cpu.clock(bus);
+        if bus.rw() {
+            bus.respond(ram.read(bus.addr));
+        } else {
+            ram.write(bus.addr, bus.data);
+        }
+        Cycle {
+            addr: bus.addr,
+            data: bus.data,
+            write: !bus.rw(),
+        }
+    };
+
+    let mut fetch = clock(&mut cpu, &mut bus);
+    for step in 0..case.steps {
+        if Reference::jams(fetch.data) {
+            return None;
+        }
+        reference.step();
+        let mut cycles = vec![fetch];
+        loop {
+            let cycle = clock(&mut cpu, &mut bus);
+            if bus.sync() || cycles.len() == MAX_CYCLES {
+                fetch = cycle;
+                break;
+            }
+            cycles.push(cycle);
+        }
+        let core = cpu.core();
+        if cycles != reference.cycles || core != reference.core {
+            return Some(Mismatch {
+                step,
+                expected: (reference.cycles.clone(), reference.core),
+                actual: (cycles, core),
+            });
+        }
+    }
+    None
+}
+
+// Greedily takes the first simpler case that still fails until none does:
+// fewer steps, fewer program bytes, zero bytes, zeroed memory and plainer
+// registers.
+fn shrink(mut case: Case) -> Case {
+    loop {
+        case.steps = mismatch(&case).unwrap().step + 1;
+        let mut candidates = Vec::new();
+        for i in 0..case.program.len() {
+            let mut candidate = case.clone();
+            candidate.program.remove(i);
+            candidates.push(candidate);
+        }
+        for i in 0..case.program.len() {
+            if case.program[i] != 0 {
+                let mut candidate = case.clone();
+                candidate.program[i] = 0;
+                candidates.push(candidate);
+            }
+        }
+        let plain = Core {
+            a: 0,
+            p: P::new(),
+            pc: 0x0200,
+            s: 0xFF,
+            x: 0,
+            y: 0,
+        };
+        let registers: [fn(&mut Core, &Core); 6] = [
+            |core, plain| core.a = plain.a,
+            |core, plain| core.x = plain.x,
+            |core, plain| core.y = plain.y,
+            |core, plain| core.s = plain.s,
+            |core, plain| core.p = plain.p,
+            |core, plain| core.pc = plain.pc,
+        ];
+        for set in registers {
+            let mut candidate = case.clone();
+            set(&mut candidate.core, &plain);
+            if candidate.core != case.core {
+                candidates.push(candidate);
+            }
+        }
+        if case.seed != 0 {
+            candidates.push(Case {
+                seed: 0,
+                ..case.clone()
+            });
+        }
+
+        match candidates.into_iter().find(|c| mismatch(c).is_some()) {
+            Some(smaller) => case = smaller,
+            None => return case,
+        }
+    }
+}
+
+fn report(case: &Case) -> String {
+    let mismatch = mismatch(case).unwrap();
+    let mut text = format!(
+        "M6502 and the reference disagree at instruction {}\n",
+        mismatch.step
+    );
+    writeln!(text, "{case:X?}").unwrap();
+    for (name, (cycles, core)) in [("expected", mismatch.expected), ("actual", mismatch.actual)] {
+        writeln!(text, "{name}:").unwrap();
+        for cycle in cycles {
+            let rw = if cycle.write { 'W' } else { 'R' };
+            writeln!(text, "  {rw} {:04X} {:02X}", cycle.addr, cycle.data).unwrap();
+        }
+        writeln!(text, "  {core:X?}").unwrap();
+    }
+    text
+}
+
+fn var(name: &str) -> Option<u64> {
+    let text = env::var(name).ok()?;
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[test]
+fn m6502_matches_reference() {
+    let cases = var("M6502_FUZZ_CASES").unwrap_or(CASES);
+    let seed = var("M6502_FUZZ_SEED").unwrap_or(SEED);
+    for i in 0..cases {
+        let case = Case::random(mix(seed ^ i));
+        if mismatch(&case).is_some() {
+            panic!("{}", report(&shrink(case)));
+        }
+    }
+}
+
+#[test]
+fn reference_takes_extra_cycle_crossing_page() {
+    // LDA $02FF,X with X=1.
+    let core = Core {
+        a: 0,
+        p: P::new(),
+        pc: 0x0200,
+        s: 0xFF,
+        x: 1,
+        y: 0,
+    };
+    let mut ram = Ram::new(0);
+    ram.load(0x0200, &[0xBD, 0xFF, 0x02]);
+    ram.write(0x0300, 0x42);
+    let mut reference = Reference::new(core, ram);
+    reference.step();
+    let addrs: Vec<_> = reference.cycles.iter().map(|cycle| cycle.addr).collect();
+    assert_eq!(addrs, [0x0200, 0x0201, 0x0202, 0x0200, 0x0300]);
+    assert_eq!(reference.core.a, 0x42);
+}
@@ -0,0 +1,102 @@
+use crate::{
+    Bus, M6502,
+    ines::{Header, Rom},
+    loader::LoadError,
+    mapper::Mirroring,
+    memory::Memory,
+};
+
+fn ines(flags6: u8, flags7: u8, prg_banks: u8, tail: &[u8]) -> Vec<u8> {
+    let mut rom = b"NES\x1A".to_vec();
+    rom.extend_from_slice(&[prg_banks, 1, flags6, flags7]);
+    rom.resize(16, 0);
+    rom.extend_from_slice(tail);
+
+    let mut prg = vec![0xEA; prg_banks as usize * 0x4000];
+    let len = prg.len();
+    prg[len - 4..len - 2].copy_from_slice(&[0x23, 0xC1]);
+    rom.extend(prg);
+    rom.extend(vec![0; 0x2000]);
+    rom
+}
+
+#[test]
+fn ines_header() {
+    let header = Header::parse(&ines(0x13, 0x00, 2, &[])).unwrap();
+    assert!(!header.nes2);
+    assert_eq!(header.prg_rom, 0x8000);
+    assert_eq!(header.chr_rom, 0x2000);
+    assert_eq!(header.mapper, 1);
+    assert_eq!(header.mirroring, Mirroring::Vertical);
+    assert!(header.battery);
+    assert_eq!(header.prg_nvram, 0x2000);
+
+    let mut dirty = ines(0x08, 0x40, 1, &[]);
+    dirty[7..16].copy_from_slice(b"DiskDude!");
+    let header = Header::parse(&dirty).unwrap();
+    assert_eq!(header.mapper, 0);
+    assert_eq!(header.mirroring, Mirroring::FourScreen);
+}
+#[test]
+fn nes2_header() {
+    let mut rom = ines(0x10, 0x28, 1, &[]);
+    rom[8] = 0x31;
+    rom[9] = 0x00;
+    rom[10] = 0x70;
+    rom[11] = 0x07;
+    let header = Header::parse(&rom).unwrap();
+    assert!(header.nes2);
+    assert_eq!(header.mapper, 0x121);
+    assert_eq!(header.submapper, 3);
+    assert_eq!(header.prg_nvram, 0x2000);
+    assert_eq!(header.prg_ram, 0);
+    assert_eq!(header.chr_ram, 0x2000);
+
+    rom[4] = 0b0000_1101;
+    rom[9] = 0x0F;
+    assert_eq!(Header::parse(&rom).unwrap().prg_rom, 3 << 3);
+}
+#[test]
+fn reset_vector_boots_the_cpu() {
+    let rom = Rom::parse(&ines(0x00, 0x00, 1, &[])).unwrap();
+    let (map, _) = rom.memory_map().unwrap();
+    let mut map = map.build();
+    assert_eq!(map.read(0xFFFC), Some(0x23));
+    assert_eq!(map.read(0xBFFD), Some(0xC1));
+
+    let mut cpu = M6502::start();
+    let mut bus = Bus::new();
+    loop {
+        cpu.clock(&mut bus);
+        map.access(&mut bus);
+        if bus.sync() {
+            break;
+        }
+    }
+    assert_eq!(bus.addr, 0xC123);
+
+    map.write(0x0801, 0x55);
+    assert_eq!(map.read(0x1801), Some(0x55));
+}
+#[test]
+fn trainer_and_mapper_registers() {
+    let trainer = [0x42; 512];
+    let rom = Rom::parse(&ines(0x24, 0x00, 4, &trainer)).unwrap();
+    let (map, cartridge) = rom.memory_map().unwrap();
+    let mut map = map.build();
+    assert_eq!(map.read(0x7000), Some(0x42));
+    assert_eq!(map.read(0x71FF), Some(0x42));
+    assert_eq!(map.read(0x7200), Some(0x00));
+
+    map.write(0x8000, 2);
+    assert_eq!(cartridge.borrow().banks.selected(1), 2);
+    assert_eq!(cartridge.borrow().mirroring(), None);
+}
+#[test]
+fn errors() {
+    assert_eq!(Rom::parse(b"NES"), Err(LoadError::BadHeader));
+    let rom = ines(0x00, 0x00, 1, &[]);
+    assert_eq!(Rom::parse(&rom[..100]), Err(LoadError::Truncated));
+    let rom = Rom::parse(&ines(0x40, 0x00, 1, &[])).unwrap();
+    assert_eq!(rom.cartridge().err(), Some(LoadError::UnsupportedMapper(4)));
+}
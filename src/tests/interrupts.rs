@@ -0,0 +1,72 @@
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+};
+
+const NOP: u8 = 0xEA;
+
+fn irq_entry(program: &[u8], stack: &[u8], p: P) -> (u16, P) {
+    let mut ram = [0; 65536];
+    ram[0x0200..0x0400].fill(NOP);
+    ram[0x0200..0x0200 + program.len()].copy_from_slice(program);
+    ram[0x01F1..0x01F1 + stack.len()].copy_from_slice(stack);
+
+    let core = Core {
+        a: 0,
+        p,
+        pc: 0x0200,
+        s: 0xF0,
+        x: 0,
+        y: 0,
+    };
+    let mut cpu = M6502::new(core);
+    let mut bus = Bus::new();
+    let mut pushed = Vec::new();
+
+    // The line goes up once the first opcode has been fetched, so the
+    // first instruction always starts before the IRQ is seen.
+    for i in 0..100 {
+        bus.set_irq(i != 0);
+        cpu.clock(&mut bus);
+        if bus.rw() {
+            if bus.addr == 0xFFFE {
+                let pc = u16::from_le_bytes([pushed[1], pushed[0]]);
+                return (pc, P(pushed[2]));
+            }
+            bus.data = ram[bus.addr as usize];
+        } else {
+            pushed.push(bus.data);
+            ram[bus.addr as usize] = bus.data;
+        }
+    }
+
+    panic!("IRQ was never taken");
+}
+
+#[test]
+fn cli_delays_irq_by_one_instruction() {
+    let (pc, _) = irq_entry(&[0x58], &[], P::new().with_i(true));
+    assert_eq!(pc, 0x0202);
+}
+#[test]
+fn sei_lets_one_irq_through() {
+    let (pc, p) = irq_entry(&[0x78], &[], P::new());
+    assert_eq!(pc, 0x0201);
+    assert!(p.i());
+}
+#[test]
+fn plp_clearing_i_delays_irq_by_one_instruction() {
+    let (pc, _) = irq_entry(&[0x28], &[0x00], P::new().with_i(true));
+    assert_eq!(pc, 0x0202);
+}
+#[test]
+fn plp_setting_i_lets_one_irq_through() {
+    let (pc, p) = irq_entry(&[0x28], &[0x04], P::new());
+    assert_eq!(pc, 0x0201);
+    assert!(p.i());
+}
+#[test]
+fn rti_clearing_i_takes_effect_immediately() {
+    let (pc, _) = irq_entry(&[0x40], &[0x00, 0x00, 0x03], P::new().with_i(true));
+    assert_eq!(pc, 0x0300);
+}
@@ -0,0 +1,48 @@
+use crate::{Bus, irq::IrqLine};
+
+#[test]
+fn line_is_wired_or_of_sources() {
+    let line = IrqLine::new();
+    let via = line.source("via");
+    let cia = line.source("cia");
+    assert!(!line.level());
+
+    via.assert();
+    cia.assert();
+    assert!(line.level());
+
+    via.release();
+    assert!(line.level());
+    cia.release();
+    assert!(!line.level());
+}
+#[test]
+fn active_sources_are_reported() {
+    let line = IrqLine::new();
+    let via = line.source("via");
+    let apu = line.source("apu frame counter");
+    let mapper = line.source("mapper");
+
+    apu.assert();
+    mapper.set(true);
+    via.set(false);
+
+    assert_eq!(line.active(), vec![apu.id(), mapper.id()]);
+    assert!(!line.is_active(via.id()));
+    assert_eq!(line.name(apu.id()), "apu frame counter");
+    assert!(mapper.is_asserted());
+}
+#[test]
+fn line_drives_bus() {
+    let line = IrqLine::new();
+    let source = line.source("device");
+    let mut bus = Bus::new();
+
+    source.assert();
+    line.drive(&mut bus);
+    assert!(bus.irq());
+
+    source.release();
+    line.drive(&mut bus);
+    assert!(!bus.irq());
+}
@@ -0,0 +1,114 @@
+use crate::{
+    core::{Core, P},
+    loader::{Image, LoadError, Segment},
+    memory::{Memory, MemoryMap},
+};
+
+#[test]
+fn prg_has_a_load_address() {
+    let image = Image::prg(&[0x01, 0x08, 0x0B, 0x08]).unwrap();
+    assert_eq!(
+        image.segments,
+        vec![Segment {
+            addr: 0x0801,
+            data: vec![0x0B, 0x08],
+        }]
+    );
+    assert_eq!(image.start, None);
+
+    assert_eq!(Image::prg(&[0x01]), Err(LoadError::Truncated));
+    assert_eq!(Image::prg(&[0xFF, 0xFF, 1, 2]), Err(LoadError::OutOfRange));
+}
+#[test]
+fn intel_hex_joins_records_and_sets_start() {
+    let text = "\
+        :05040000A9018D0002BE\n\
+        :010405006096\n\
+        :01030000EA12\n\
+        :0400000500000400F3\n\
+        :00000001FF\n\
+        :01030000FFFF\n";
+    let image = Image::intel_hex(text).unwrap();
+    assert_eq!(
+        image.segments,
+        vec![
+            Segment {
+                addr: 0x0400,
+                data: vec![0xA9, 0x01, 0x8D, 0x00, 0x02, 0x60],
+            },
+            Segment {
+                addr: 0x0300,
+                data: vec![0xEA],
+            },
+        ]
+    );
+    assert_eq!(image.start, Some(0x0400));
+}
+#[test]
+fn intel_hex_errors() {
+    assert_eq!(
+        Image::intel_hex(":010405006097"),
+        Err(LoadError::Checksum { line: 1 })
+    );
+    assert_eq!(
+        Image::intel_hex("\n:0104050060\n"),
+        Err(LoadError::Syntax { line: 2 })
+    );
+    assert_eq!(
+        Image::intel_hex(":020000040001F9\n:01030000EA12"),
+        Err(LoadError::OutOfRange)
+    );
+}
+#[test]
+fn srec_data_and_termination() {
+    let text = "S00600004844521B\nS106E000A2FF9ADE\nS106E0034C03E0E7\nS903E0001C\n";
+    let image = Image::srec(text).unwrap();
+    assert_eq!(
+        image.segments,
+        vec![Segment {
+            addr: 0xE000,
+            data: vec![0xA2, 0xFF, 0x9A, 0x4C, 0x03, 0xE0],
+        }]
+    );
+    assert_eq!(image.start, Some(0xE000));
+
+    let image = Image::srec("S20500F00042C8").unwrap();
+    assert_eq!(image.segments[0].addr, 0xF000);
+    assert_eq!(Image::srec("S70500010000F9"), Err(LoadError::OutOfRange));
+    assert_eq!(
+        Image::srec("S106E000A2FF9ADF"),
+        Err(LoadError::Checksum { line: 1 })
+    );
+    assert_eq!(Image::srec("X106E000"), Err(LoadError::Syntax { line: 1 }));
+}
+#[test]
+fn boot_writes_rom_and_sets_pc() {
+    let mut mem = MemoryMap::builder()
+        .ram(0x0000..=0x7FFF)
+        .rom(0xE000..=0xFFFF, vec![0; 0x2000])
+        .build();
+    let mut core = Core {
+        a: 0,
+        p: P::new(),
+        pc: 0,
+        s: 0,
+        x: 0,
+        y: 0,
+    };
+    let text = "S106E000A2FF9ADE\nS106E0034C03E0E7\nS903E0001C\n";
+    Image::srec(text).unwrap().boot(&mut mem, &mut core);
+
+    assert_eq!(core.pc, 0xE000);
+    assert_eq!(mem.read(0xE003), Some(0x4C));
+    mem.write(0xE003, 0);
+    assert_eq!(mem.read(0xE003), Some(0x4C));
+
+    Image::raw(&[1, 2], 0x1000).unwrap().install(&mut mem);
+    assert_eq!(mem.read(0x1001), Some(2));
+}
+#[test]
+fn install_segment_ending_at_ffff() {
+    let mut mem = Box::new([0; 65536]);
+    Image::raw(&[1, 2, 3, 4], 0xFFFC).unwrap().install(&mut mem);
+    assert_eq!(mem[0xFFFC..], [1, 2, 3, 4]);
+}
@@ -0,0 +1,105 @@
+use crate::{
+    mapper::{Cartridge, Mirroring},
+    memory::{Memory, MemoryMap},
+};
+
+fn prg(banks: usize, size: usize) -> Vec<u8> {
+    (0..banks).flat_map(|b| vec![b as u8; size]).collect()
+}
+
+#[test]
+fn nrom_16k_is_mirrored() {
+    let mut cart = Cartridge::nrom(prg(1, 0x4000), 0);
+    assert_eq!(cart.read(0x8000), Some(0));
+    assert_eq!(cart.read(0xC000), Some(0));
+    assert_eq!(cart.read(0x6000), None);
+}
+#[test]
+fn nrom_prg_ram() {
+    let mut cart = Cartridge::nrom(prg(2, 0x4000), 0x2000);
+    cart.write(0x6123, 0x99);
+    assert_eq!(cart.read(0x6123), Some(0x99));
+    assert_eq!(cart.read(0xC000), Some(1));
+}
+#[test]
+fn uxrom_switches_low_window() {
+    let mut cart = Cartridge::uxrom(prg(8, 0x4000), 0);
+    assert_eq!(cart.read(0x8000), Some(0));
+    assert_eq!(cart.read(0xC000), Some(7));
+
+    cart.write(0x8000, 5);
+    assert_eq!(cart.read(0x8000), Some(5));
+    assert_eq!(cart.read(0xFFFF), Some(7));
+}
+#[test]
+fn mmc1_serial_writes() {
+    let mut cart = Cartridge::mmc1(prg(8, 0x4000), 0x2000);
+    assert_eq!(cart.read(0xC000), Some(7));
+
+    for bit in 0..5 {
+        cart.write(0xE000, 3 >> bit);
+    }
+    assert_eq!(cart.read(0x8000), Some(3));
+    assert_eq!(cart.read(0xC000), Some(7));
+
+    for bit in 0..5 {
+        cart.write(0x8000, 0b01010 >> bit);
+    }
+    assert_eq!(cart.mirroring(), Some(Mirroring::Vertical));
+    assert_eq!(cart.read(0x8000), Some(0));
+    assert_eq!(cart.read(0xC000), Some(3));
+
+    for bit in 0..5 {
+        cart.write(0x8000, 0b00011 >> bit);
+    }
+    assert_eq!(cart.mirroring(), Some(Mirroring::Horizontal));
+    assert_eq!(cart.read(0x8000), Some(2));
+    assert_eq!(cart.read(0xC000), Some(3));
+}
+#[test]
+fn mmc1_reset_bit() {
+    let mut cart = Cartridge::mmc1(prg(8, 0x4000), 0);
+    cart.write(0x8000, 1);
+    cart.write(0x8000, 0x80);
+    assert_eq!(cart.mapper.control() & 0x0C, 0x0C);
+    for bit in 0..5 {
+        cart.write(0xE000, 4 >> bit);
+    }
+    assert_eq!(cart.read(0x8000), Some(4));
+}
+#[test]
+fn mmc1_prg_ram_enable() {
+    let mut cart = Cartridge::mmc1(prg(2, 0x4000), 0x2000);
+    cart.write(0x6000, 0x42);
+    assert_eq!(cart.read(0x6000), Some(0x42));
+
+    for bit in 0..5 {
+        cart.write(0xE000, 0x10 >> bit);
+    }
+    assert_eq!(cart.read(0x6000), None);
+    cart.write(0x6000, 0x99);
+
+    for _ in 0..5 {
+        cart.write(0xE000, 0);
+    }
+    assert_eq!(cart.read(0x6000), Some(0x42));
+}
+#[test]
+fn switcher_8k_windows() {
+    let mut cart = Cartridge::switcher(prg(16, 0x2000), 0x2000, 0);
+    assert_eq!(cart.read(0xA000), Some(1));
+    cart.write(0xA000, 9);
+    assert_eq!(cart.read(0xA000), Some(9));
+    assert_eq!(cart.read(0xE000), Some(3));
+}
+#[test]
+fn cartridge_plugs_into_memory_map() {
+    let cart = Cartridge::uxrom(prg(4, 0x4000), 0);
+    let mut map = MemoryMap::builder()
+        .ram(0x0000..=0x07FF)
+        .absolute_device(0x4020..=0xFFFF, cart)
+        .build();
+    map.write(0x8000, 2);
+    assert_eq!(map.read(0x8000), Some(2));
+    assert_eq!(map.read(0xC000), Some(3));
+}
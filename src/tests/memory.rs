@@ -0,0 +1,132 @@
+use crate::{
+    Bus,
+    memory::{Memory, MemoryMap},
+};
+
+#[test]
+fn ram_is_read_write() {
+    let mut map = MemoryMap::builder().ram(0x0000..=0x07FF).build();
+    map.write(0x0123, 0x45);
+    assert_eq!(map.read(0x0123), Some(0x45));
+}
+#[test]
+fn rom_ignores_writes() {
+    let mut map = MemoryMap::builder()
+        .rom(0xC000..=0xFFFF, vec![0xAA; 0x4000])
+        .build();
+    map.write(0xC000, 0x55);
+    assert_eq!(map.read(0xC000), Some(0xAA));
+}
+#[test]
+fn small_rom_repeats_over_region() {
+    let mut image = vec![0; 0x4000];
+    image[0x3FFC] = 0x34;
+    image[0x3FFD] = 0x12;
+    let mut map = MemoryMap::builder().rom(0x8000..=0xFFFF, image).build();
+    assert_eq!(map.read(0xBFFC), Some(0x34));
+    assert_eq!(map.read(0xFFFD), Some(0x12));
+}
+#[test]
+fn nes_ram_mirror() {
+    let mut map = MemoryMap::builder()
+        .ram(0x0000..=0x07FF)
+        .mirror(0x0800..=0x1FFF, 0x0000..=0x07FF)
+        .build();
+    map.write(0x1801, 0x77);
+    assert_eq!(map.read(0x0001), Some(0x77));
+    assert_eq!(map.read(0x0801), Some(0x77));
+    assert_eq!(map.read(0x1001), Some(0x77));
+}
+#[test]
+fn unmapped_reads_leave_open_bus() {
+    let mut map = MemoryMap::builder().ram(0x0000..=0x00FF).build();
+    let mut bus = Bus::new();
+
+    map.write(0x0010, 0x5A);
+    bus.read(0x0010);
+    map.access(&mut bus);
+    assert_eq!(bus.data, 0x5A);
+
+    bus.read(0x4000);
+    map.access(&mut bus);
+    assert_eq!(bus.data, 0x5A);
+}
+#[test]
+fn unmapped_handler_sees_open_bus() {
+    let mut map = MemoryMap::builder()
+        .ram(0x0000..=0x00FF)
+        .unmapped(0x4016..=0x4016, |_, open| Some(open & 0xE0 | 0x01))
+        .build();
+    map.write(0x0000, 0x40);
+    map.read(0x0000);
+    assert_eq!(map.read(0x4016), Some(0x41));
+}
+#[test]
+fn devices_see_offsets() {
+    struct Echo;
+    impl Memory for Echo {
+        fn read(&mut self, addr: u16) -> Option<u8> {
+            Some(addr as u8)
+        }
+        fn write(&mut self, _: u16, _: u8) {}
+    }
+
+    let mut map = MemoryMap::builder().device(0x6000..=0x600F, Echo).build();
+    assert_eq!(map.read(0x6003), Some(3));
+    assert_eq!(map.read(0x6010), None);
+}
+#[test]
+fn peek_leaves_devices_and_open_bus_alone() {
+    struct Counter(u8);
+    impl Memory for Counter {
+        fn read(&mut self, _: u16) -> Option<u8> {
+            self.0 += 1;
+            Some(self.0)
+        }
+        fn write(&mut self, _: u16, _: u8) {}
+        fn peek(&mut self, _: u16) -> Option<u8> {
+            Some(self.0)
+        }
+    }
+
+    let mut map = MemoryMap::builder()
+        .ram(0x0000..=0x07FF)
+        .mirror(0x0800..=0x1FFF, 0x0000..=0x07FF)
+        .device(0x6000..=0x6000, Counter(0))
+        .unmapped(0x4016..=0x4016, |_, _| panic!("handler called"))
+        .build();
+    map.write(0x0001, 0x77);
+    assert_eq!(map.peek(0x1801), Some(0x77));
+    assert_eq!(map.peek(0x6000), Some(0));
+    assert_eq!(map.peek(0x6000), Some(0));
+    assert_eq!(map.peek(0x4016), None);
+    assert_eq!(map.read(0x6000), Some(1));
+
+    map.write(0x0002, 0x12);
+    map.peek(0x0001);
+    assert_eq!(map.open_bus(), 0x12);
+}
+#[test]
+#[should_panic]
+fn overlapping_regions_are_rejected() {
+    MemoryMap::builder()
+        .ram(0x0000..=0x07FF)
+        .ram(0x0700..=0x0FFF)
+        .build();
+}
+#[test]
+#[should_panic(expected = "targets the mirror")]
+fn mirror_of_all_memory_is_rejected() {
+    MemoryMap::builder()
+        .mirror(0x8000..=0xFFFF, 0x0000..=0xFFFF)
+        .build();
+}
+#[test]
+#[should_panic(expected = "targets the mirror")]
+fn mirror_of_mirror_is_rejected() {
+    MemoryMap::builder()
+        .ram(0x0000..=0x07FF)
+        .mirror(0x0800..=0x0FFF, 0x1000..=0x17FF)
+        .mirror(0x1000..=0x17FF, 0x0000..=0x07FF)
+        .build();
+}
@@ -0,0 +1,89 @@
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+    microcode::{Micro, cycles, microcode},
+};
+
+#[test]
+fn cycle_counts() {
+    for (opcode, expected) in [
+        (0x00, 7), // BRK
+        (0x08, 3), // PHP
+        (0x0A, 2), // ASL A
+        (0x10, 2), // BPL
+        (0x1E, 7), // ASL a,x
+        (0x20, 6), // JSR
+        (0x40, 6), // RTI
+        (0x48, 3), // PHA
+        (0x4C, 3), // JMP a
+        (0x60, 6), // RTS
+        (0x68, 4), // PLA
+        (0x6C, 5), // JMP (a)
+        (0x81, 6), // STA (z,x)
+        (0x91, 6), // STA (z),y
+        (0x96, 4), // STX z,y
+        (0x9D, 5), // STA a,x
+        (0xA9, 2), // LDA #
+        (0xB1, 5), // LDA (z),y
+        (0xBD, 4), // LDA a,x
+        (0xC6, 5), // DEC z
+        (0xE3, 8), // ISC (z,x)
+        (0xEA, 2), // NOP
+    ] {
+        assert_eq!(cycles(opcode), expected, "opcode {opcode:02X}");
+    }
+}
+
+#[test]
+fn every_program_ends_in_sync() {
+    for opcode in 0..=255 {
+        let last = *microcode(opcode).last().unwrap();
+        assert!(
+            matches!(
+                last,
+                Micro::Sync
+                    | Micro::ExecSync
+                    | Micro::ExecAccumulator
+                    | Micro::Jump
+                    | Micro::JumpSubroutine
+                    | Micro::ReturnInterrupt
+                    | Micro::TakeBranch
+                    | Micro::VectorSync
+                    | Micro::Jam
+            ),
+            "opcode {opcode:02X} ends in {last:?}"
+        );
+    }
+}
+
+#[test]
+fn micro_follows_the_program() {
+    // LDA $1234
+    let mut cpu = M6502::new(Core {
+        a: 0,
+        p: P::new(),
+        pc: 0x0200,
+        s: 0xFF,
+        x: 0,
+        y: 0,
+    });
+    let mut bus = Bus::new();
+    let mut ops = Vec::new();
+    for data in [0xAD, 0x34, 0x12, 0x56] {
+        cpu.clock(&mut bus);
+        bus.respond(data);
+        ops.push(cpu.micro());
+    }
+    assert_eq!(
+        ops,
+        [
+            None,
+            Some(Micro::FetchHigh),
+            Some(Micro::ReadAbsolute),
+            Some(Micro::ExecSync),
+        ]
+    );
+    cpu.clock(&mut bus);
+    assert!(bus.sync());
+    assert_eq!(cpu.core().a, 0x56);
+}
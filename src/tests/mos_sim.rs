@@ -0,0 +1,59 @@
+use super::Output;
+use crate::{
+    M6502,
+    mos_sim::{MosSim, Outcome},
+};
+
+fn block(addr: u16, data: &[u8]) -> Vec<u8> {
+    let mut block = addr.to_le_bytes().to_vec();
+    block.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    block.extend_from_slice(data);
+    block
+}
+fn image(code: &[u8]) -> Vec<u8> {
+    let mut image = block(0x0200, code);
+    image.extend(block(0xFFFC, &[0x00, 0x02]));
+    image
+}
+
+#[test]
+fn putchar_clock_and_exit() {
+    #[rustfmt::skip]
+    let code = [
+        0xA9, b'h',       // LDA #'h'
+        0x8D, 0xF9, 0xFF, // STA putchar
+        0xA9, b'i',       // LDA #'i'
+        0x8D, 0xF9, 0xFF, // STA putchar
+        0xAD, 0xF0, 0xFF, // LDA clock
+        0x85, 0x10,       // STA $10
+        0xAD, 0xF1, 0xFF, // LDA clock+1
+        0x85, 0x11,       // STA $11
+        0xA9, 0x03,       // LDA #3
+        0x8D, 0xF8, 0xFF, // STA exit
+    ];
+    let output = Output::default();
+    let mut sim = MosSim::new().with_output(output.clone());
+    sim.load(&image(&code)).unwrap();
+
+    let mut cpu = M6502::start();
+    assert_eq!(sim.run(&mut cpu, 1000), Some(Outcome::Exit(3)));
+    assert_eq!(*output.0.borrow(), b"hi");
+
+    let latched = u16::from_le_bytes([sim.ram()[0x10], sim.ram()[0x11]]) as u64;
+    assert_eq!(latched, sim.cycles() - 16);
+}
+#[test]
+fn abort() {
+    let code = [0x8D, 0xF7, 0xFF];
+    let mut sim = MosSim::new();
+    sim.load(&image(&code)).unwrap();
+    assert_eq!(sim.run(&mut M6502::start(), 1000), Some(Outcome::Abort));
+}
+#[test]
+fn truncated_images_are_rejected() {
+    let mut image = image(&[0xEA, 0xEA]);
+    image.pop();
+    assert_eq!(MosSim::new().load(&image), None);
+    assert_eq!(MosSim::new().load(&image[..3]), None);
+    assert_eq!(MosSim::new().load(&block(0xFFFF, &[1, 2])), None);
+}
@@ -0,0 +1,103 @@
+use crate::{
+    loader::{LoadError, Segment},
+    o65::{O65, Placement},
+};
+use std::collections::HashMap;
+
+#[rustfmt::skip]
+const TEXT: [u8; 11] = [
+    0xA9, 0x20,       // LDA #<buf+$20
+    0xA2, 0x20,       // LDX #>buf+$20
+    0x20, 0x00, 0x00, // JSR putc
+    0x4C, 0x00, 0x10, // JMP main
+    0x60,             // RTS
+];
+
+fn object(mode: u16) -> Vec<u8> {
+    let mut file = b"\x01\x00o65\x00".to_vec();
+    file.extend_from_slice(&mode.to_le_bytes());
+    for word in [0x1000, 11, 0x2000, 2, 0x3000, 16, 0x0010, 4, 0] {
+        file.extend_from_slice(&u16::to_le_bytes(word));
+    }
+    file.extend_from_slice(&[6, 0, b't', b'.', b'o', 0, 0]);
+
+    file.extend_from_slice(&TEXT);
+    file.extend_from_slice(&[0x0A, 0x10]);
+
+    file.extend_from_slice(&[1, 0]);
+    file.extend_from_slice(b"putc\0");
+    file.extend_from_slice(&[2, 0x23, 2, 0x43, 0x20, 2, 0x80, 0, 0, 3, 0x82, 0]);
+    file.extend_from_slice(&[1, 0x82, 0]);
+
+    file.extend_from_slice(&[2, 0]);
+    file.extend_from_slice(b"main\0\x02\x00\x10");
+    file.extend_from_slice(b"buf\0\x04\x00\x30");
+    file
+}
+fn imports() -> HashMap<String, u16> {
+    HashMap::from([("putc".to_string(), 0xFFD2)])
+}
+
+#[test]
+fn header_and_segments() {
+    let o65 = O65::parse(&object(0)).unwrap();
+    assert_eq!(o65.placement.text, 0x1000);
+    assert_eq!(o65.text, TEXT);
+    assert_eq!(o65.data, [0x0A, 0x10]);
+    assert_eq!(o65.bss_len, 16);
+    assert_eq!(o65.zero_len, 4);
+    assert_eq!(o65.options, vec![(0, b"t.o\0".to_vec())]);
+    assert_eq!(o65.undefined, ["putc"]);
+    assert_eq!(o65.exports.len(), 2);
+}
+#[test]
+fn relocates_segments_and_symbols() {
+    let o65 = O65::parse(&object(0)).unwrap();
+    let to = Placement {
+        text: 0x8000,
+        data: 0x90F0,
+        bss: 0xA000,
+        zero: 0x80,
+    };
+    let relocated = o65.relocate(to, &imports()).unwrap();
+
+    assert_eq!(
+        relocated.image.segments,
+        vec![
+            Segment {
+                addr: 0x8000,
+                data: vec![
+                    0xA9, 0x10, 0xA2, 0x91, 0x20, 0xD2, 0xFF, 0x4C, 0x00, 0x80, 0x60
+                ],
+            },
+            Segment {
+                addr: 0x90F0,
+                data: vec![0x0A, 0x80],
+            },
+        ]
+    );
+    assert_eq!(relocated.exports["main"], 0x8000);
+    assert_eq!(relocated.exports["buf"], 0xA000);
+
+    let same = o65.relocate(o65.placement, &imports()).unwrap();
+    assert_eq!(same.image.segments[0].data[..4], TEXT[..4]);
+}
+#[test]
+fn bsszero_adds_a_cleared_segment() {
+    let o65 = O65::parse(&object(0x0200)).unwrap();
+    let relocated = o65.relocate(o65.placement, &imports()).unwrap();
+    assert_eq!(relocated.image.segments[2].addr, 0x3000);
+    assert_eq!(relocated.image.segments[2].data, vec![0; 16]);
+}
+#[test]
+fn errors() {
+    let o65 = O65::parse(&object(0)).unwrap();
+    assert_eq!(
+        o65.relocate(o65.placement, &HashMap::new()),
+        Err(LoadError::UndefinedSymbol("putc".to_string()))
+    );
+    assert_eq!(O65::parse(&object(0x8000)), Err(LoadError::BadHeader));
+    assert_eq!(O65::parse(b"\x01\x00o66"), Err(LoadError::BadHeader));
+    let file = object(0);
+    assert_eq!(O65::parse(&file[..40]), Err(LoadError::Truncated));
+}
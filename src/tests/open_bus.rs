@@ -0,0 +1,50 @@
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+    memory::{Memory, MemoryMap},
+};
+
+#[test]
+fn float_keeps_previous_data() {
+    let mut bus = Bus::new();
+    bus.read(0x1234);
+    bus.respond(0x12);
+    assert!(!bus.floating());
+
+    bus.read(0x5678);
+    assert!(bus.floating());
+    bus.float();
+    assert_eq!(bus.data, 0x12);
+}
+#[test]
+fn writes_drive_the_bus() {
+    let mut bus = Bus::new();
+    bus.read(0x1234);
+    bus.write(0x1234, 0x56);
+    assert!(!bus.floating());
+    assert_eq!(bus.data, 0x56);
+}
+#[test]
+fn unmapped_absolute_read_returns_address_high_byte() {
+    let mut map = MemoryMap::builder().ram(0x0000..=0x07FF).build();
+    for (i, byte) in [0xAD, 0x00, 0x40].into_iter().enumerate() {
+        map.write(0x0200 + i as u16, byte);
+    }
+
+    let core = Core {
+        a: 0,
+        p: P::new(),
+        pc: 0x0200,
+        s: 0xFD,
+        x: 0,
+        y: 0,
+    };
+    let mut cpu = M6502::new(core);
+    let mut bus = Bus::new();
+    for _ in 0..6 {
+        cpu.clock(&mut bus);
+        map.access(&mut bus);
+    }
+
+    assert_eq!(cpu.core().a, 0x40);
+}
@@ -0,0 +1,105 @@
+use crate::{Bus, devices::pia::Pia, irq::IrqLine, memory::Memory};
+
+const PRA: u16 = 0;
+const CRA: u16 = 1;
+const PRB: u16 = 2;
+const CRB: u16 = 3;
+
+#[test]
+fn control_bit_2_selects_ddr() {
+    let mut pia = Pia::new();
+    pia.write(PRA, 0x0F);
+    pia.write(CRA, 0x04);
+    pia.write(PRA, 0xA5);
+    pia.set_port_a(0x30);
+    assert_eq!(pia.port_a(), 0x35);
+    assert_eq!(pia.read(PRA), Some(0x35));
+
+    pia.write(CRA, 0x00);
+    assert_eq!(pia.read(PRA), Some(0x0F));
+}
+#[test]
+fn ca1_edge_sets_flag_and_irq() {
+    let line = IrqLine::new();
+    let mut pia = Pia::new().with_irq_a(line.source("pia a"));
+    pia.write(CRA, 0x05);
+
+    pia.set_ca1(false);
+    assert_eq!(pia.read(CRA), Some(0x85));
+    assert!(line.level());
+    pia.set_ca1(true);
+
+    pia.read(PRA);
+    assert_eq!(pia.read(CRA), Some(0x05));
+    assert!(!line.level());
+
+    pia.write(CRA, 0x07);
+    pia.set_ca1(false);
+    assert!(!pia.irq_a());
+    pia.set_ca1(true);
+    assert!(pia.irq_a());
+}
+#[test]
+fn flag_without_enable_does_not_interrupt() {
+    let mut pia = Pia::new();
+    pia.write(CRA, 0x04);
+    pia.set_ca1(false);
+    assert_eq!(pia.read(CRA), Some(0x84));
+    assert!(!pia.irq_a());
+}
+#[test]
+fn ca2_read_handshake() {
+    let mut pia = Pia::new();
+    pia.write(CRA, 0x24);
+    assert!(pia.ca2());
+
+    pia.read(PRA);
+    assert!(!pia.ca2());
+    pia.clock();
+    assert!(!pia.ca2());
+    pia.set_ca1(false);
+    assert!(pia.ca2());
+}
+#[test]
+fn ca2_pulse_lasts_one_cycle() {
+    let mut pia = Pia::new();
+    pia.write(CRA, 0x2C);
+    pia.read(PRA);
+    assert!(!pia.ca2());
+    pia.clock();
+    assert!(pia.ca2());
+}
+#[test]
+fn cb2_manual_and_write_handshake() {
+    let mut pia = Pia::new();
+    pia.write(CRB, 0x30);
+    assert!(!pia.cb2());
+    pia.write(CRB, 0x38);
+    assert!(pia.cb2());
+
+    pia.write(CRB, 0x24);
+    pia.read(PRB);
+    assert!(pia.cb2());
+    pia.write(PRB, 0x41);
+    assert!(!pia.cb2());
+    pia.set_cb1(false);
+    assert!(pia.cb2());
+}
+#[test]
+fn cb2_input_drives_nmi() {
+    let line = IrqLine::new();
+    let mut pia = Pia::new().with_irq_b(line.source("pia b"));
+    let mut bus = Bus::new();
+    pia.write(CRB, 0x1C);
+
+    pia.set_cb2(false);
+    assert!(!line.level());
+    pia.set_cb2(true);
+    line.drive_nmi(&mut bus);
+    assert!(bus.nmi());
+    assert_eq!(pia.read(CRB), Some(0x5C));
+
+    pia.read(PRB);
+    line.drive_nmi(&mut bus);
+    assert!(!bus.nmi());
+}
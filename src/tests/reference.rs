@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+
+use crate::core::{Core, P};
+
+// A second 6502, written apart from `M6502` to fuzz it against. It runs a
+// whole instruction at a time and logs the bus cycles the NMOS part makes
+// for it, dummy reads and writes included, as 64doc lists them. Opcodes
+// are decoded from their aaabbbcc bit fields rather than a table, and the
+// ALU is its own.
+
+const C: u8 = 0x01;
+const Z: u8 = 0x02;
+const I: u8 = 0x04;
+const D: u8 = 0x08;
+const B: u8 = 0x10;
+const U: u8 = 0x20;
+const V: u8 = 0x40;
+const N: u8 = 0x80;
+
+// What ANE and LXA OR into A first, which varies from chip to chip.
+const MAGIC: u8 = 0xEE;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub addr: u16,
+    pub data: u8,
+    pub write: bool,
+}
+
+// 64K that reads as a hash of the address until it is written, or as
+// zeros for seed 0.
+#[derive(Clone, Debug)]
+pub struct Ram {
+    seed: u64,
+    bytes: HashMap<u16, u8>,
+}
+impl Ram {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            bytes: HashMap::new(),
+        }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match self.bytes.get(&addr) {
+            Some(&data) => data,
+            None if self.seed == 0 => 0,
+            None => mix(self.seed ^ addr as u64) as u8,
+        }
+    }
+    pub fn write(&mut self, addr: u16, data: u8) {
+        self.bytes.insert(addr, data);
+    }
+    pub fn load(&mut self, addr: u16, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            self.write(addr.wrapping_add(i as u16), byte);
+        }
+    }
+}
+
+// splitmix64's output function.
+pub fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Immediate,
+    Zero,
+    ZeroX,
+    ZeroY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    IndirectX,
+    IndirectY,
+}
+
+#[derive(Copy, Clone)]
+enum Kind {
+    Read(fn(&mut Core, u8)),
+    Write(fn(&mut Core) -> u8),
+    Modify(fn(&mut Core, u8) -> u8),
+}
+
+pub struct Reference {
+    pub core: Core,
+    pub ram: Ram,
+    pub cycles: Vec<Cycle>,
+}
+impl Reference {
+    pub fn new(core: Core, ram: Ram) -> Self {
+        Self {
+            core,
+            ram,
+            cycles: Vec::new(),
+        }
+    }
+
+    pub fn jams(opcode: u8) -> bool {
+        opcode & 0x1F == 0x12 || matches!(opcode, 0x02 | 0x22 | 0x42 | 0x62)
+    }
+
+    // Runs the instruction at PC, leaving its cycles in `cycles`.
+    pub fn step(&mut self) {
+        use Mode::*;
+
+        self.cycles.clear();
+        let opcode = self.fetch();
+        assert!(!Self::jams(opcode), "JAM {opcode:02X}");
+        let (aaa, bbb, cc) = (opcode >> 5, opcode >> 2 & 7, opcode & 3);
+        match opcode {
+            0x00 => self.brk(),
+            0x20 => self.jsr(),
+            0x40 => self.rti(),
+            0x60 => self.rts(),
+            0x08 => self.push_op(|core| core.p.0 | B | U),
+            0x48 => self.push_op(|core| core.a),
+            0x28 => self.pull_op(|core, data| core.p = P((data | U) & !B)),
+            0x68 => self.pull_op(|core, data| core.a = nz(core, data)),
+            0x4C => {
+                let lo = self.fetch();
+                let hi = self.fetch();
+                self.core.pc = u16::from_le_bytes([lo, hi]);
+            }
+            0x6C => {
+                let lo = self.fetch();
+                let hi = self.fetch();
+                let pcl = self.read(u16::from_le_bytes([lo, hi]));
+                let pch = self.read(u16::from_le_bytes([lo.wrapping_add(1), hi]));
+                self.core.pc = u16::from_le_bytes([pcl, pch]);
+            }
+            0x93 => self.store_high(IndirectY, |core| core.a & core.x),
+            0x9B => {
+                self.core.s = self.core.a & self.core.x;
+                self.store_high(AbsoluteY, |core| core.s);
+            }
+            0x9C => self.store_high(AbsoluteX, |core| core.y),
+            0x9E => self.store_high(AbsoluteY, |core| core.x),
+            0x9F => self.store_high(AbsoluteY, |core| core.a & core.x),
+            _ if cc == 0 && bbb == 4 => {
+                let flag = [N, V, C, Z][aaa as usize >> 1];
+                let taken = (self.core.p.0 & flag != 0) == (aaa & 1 != 0);
+                self.branch(taken);
+            }
+            _ if cc == 0 && bbb == 6 => self.implied(match aaa {
+                0 => |core| set(core, C, false),
+                1 => |core| set(core, C, true),
+                2 => |core| set(core, I, false),
+                3 => |core| set(core, I, true),
+                4 => |core| core.a = nz(core, core.y),
+                5 => |core| set(core, V, false),
+                6 => |core| set(core, D, false),
+                _ => |core| set(core, D, true),
+            }),
+            0x88 => self.implied(|core| core.y = nz(core, core.y.wrapping_sub(1))),
+            0xA8 => self.implied(|core| core.y = nz(core, core.a)),
+            0xC8 => self.implied(|core| core.y = nz(core, core.y.wrapping_add(1))),
+            0xE8 => self.implied(|core| core.x = nz(core, core.x.wrapping_add(1))),
+            0x8A => self.implied(|core| core.a = nz(core, core.x)),
+            0xAA => self.implied(|core| core.x = nz(core, core.a)),
+            0xCA => self.implied(|core| core.x = nz(core, core.x.wrapping_sub(1))),
+            0x9A => self.implied(|core| core.s = core.x),
+            0xBA => self.implied(|core| core.x = nz(core, core.s)),
+            _ if cc == 2 && bbb == 6 || opcode == 0xEA => self.implied(|_| ()),
+            _ if cc == 2 && bbb == 2 => {
+                let shift: [fn(&mut Core, u8) -> u8; 4] = [asl, rol, lsr, ror];
+                self.read(self.core.pc);
+                let a = self.core.a;
+                self.core.a = shift[aaa as usize](&mut self.core, a);
+            }
+            _ => {
+                let (mode, kind) = decode(opcode);
+                self.operate(mode, kind);
+            }
+        }
+    }
+
+    fn operate(&mut self, mode: Mode, kind: Kind) {
+        if mode == Mode::Immediate {
+            let data = self.fetch();
+            let Kind::Read(op) = kind else { unreachable!() };
+            op(&mut self.core, data);
+            return;
+        }
+        let addr = self.address(mode, !matches!(kind, Kind::Read(_)));
+        match kind {
+            Kind::Read(op) => {
+                let data = self.read(addr);
+                op(&mut self.core, data);
+            }
+            Kind::Write(op) => {
+                let data = op(&mut self.core);
+                self.write(addr, data);
+            }
+            Kind::Modify(op) => {
+                let data = self.read(addr);
+                self.write(addr, data);
+                let data = op(&mut self.core, data);
+                self.write(addr, data);
+            }
+        }
+    }
+
+    // The effective address, after the cycles spent working it out.
+    fn address(&mut self, mode: Mode, always_fix: bool) -> u16 {
+        let (x, y) = (self.core.x, self.core.y);
+        match mode {
+            Mode::Zero => self.fetch() as u16,
+            Mode::ZeroX | Mode::ZeroY => {
+                let base = self.fetch();
+                self.read(base as u16);
+                let index = if mode == Mode::ZeroX { x } else { y };
+                base.wrapping_add(index) as u16
+            }
+            Mode::Absolute => {
+                let lo = self.fetch();
+                let hi = self.fetch();
+                u16::from_le_bytes([lo, hi])
+            }
+            Mode::AbsoluteX | Mode::AbsoluteY => {
+                let lo = self.fetch();
+                let hi = self.fetch();
+                let index = if mode == Mode::AbsoluteX { x } else { y };
+                self.index(lo, hi, index, always_fix)
+            }
+            Mode::IndirectX => {
+                let pointer = self.fetch();
+                self.read(pointer as u16);
+                let pointer = pointer.wrapping_add(x);
+                let lo = self.read(pointer as u16);
+                let hi = self.read(pointer.wrapping_add(1) as u16);
+                u16::from_le_bytes([lo, hi])
+            }
+            Mode::IndirectY => {
+                let pointer = self.fetch();
+                let lo = self.read(pointer as u16);
+                let hi = self.read(pointer.wrapping_add(1) as u16);
+                self.index(lo, hi, y, always_fix)
+            }
+            Mode::Immediate => unreachable!(),
+        }
+    }
+    // Indexing first reads with the carry not yet in the high byte. A read
+    // that did not cross a page is done then; anything else reads again.
+    fn index(&mut self, lo: u8, hi: u8, index: u8, always_fix: bool) -> u16 {
+        let (low, crossed) = lo.overflowing_add(index);
+        if crossed || always_fix {
+            self.read(u16::from_le_bytes([low, hi]));
+        }
+        u16::from_le_bytes([lo, hi]).wrapping_add(index as u16)
+    }
+
+    // SHA, SHX, SHY and TAS store a register ANDed with one more than the
+    // base address's high byte. When indexing crosses a page, the stored
+    // value also becomes the high byte of the address.
+    fn store_high(&mut self, mode: Mode, value: fn(&Core) -> u8) {
+        let (lo, hi) = if mode == Mode::IndirectY {
+            let pointer = self.fetch();
+            let lo = self.read(pointer as u16);
+            (lo, self.read(pointer.wrapping_add(1) as u16))
+        } else {
+            (self.fetch(), self.fetch())
+        };
+        let index = if mode == Mode::AbsoluteX {
+            self.core.x
+        } else {
+            self.core.y
+        };
+        let (low, crossed) = lo.overflowing_add(index);
+        self.read(u16::from_le_bytes([low, hi]));
+        let data = value(&self.core) & hi.wrapping_add(1);
+        let high = if crossed { data } else { hi };
+        self.write(u16::from_le_bytes([low, high]), data);
+    }
+
+    fn implied(&mut self, op: fn(&mut Core)) {
+        self.read(self.core.pc);
+        op(&mut self.core);
+    }
+    fn branch(&mut self, taken: bool) {
+        let offset = self.fetch() as i8;
+        if !taken {
+            return;
+        }
+        let pc = self.core.pc;
+        self.read(pc);
+        let target = pc.wrapping_add_signed(offset as i16);
+        if target & 0xFF00 != pc & 0xFF00 {
+            self.read(pc & 0xFF00 | target & 0x00FF);
+        }
+        self.core.pc = target;
+    }
+    fn push_op(&mut self, value: fn(&Core) -> u8) {
+        self.read(self.core.pc);
+        self.push(value(&self.core));
+    }
+    fn pull_op(&mut self, op: fn(&mut Core, u8)) {
+        self.read(self.core.pc);
+        self.read(0x100 | self.core.s as u16);
+        let data = self.pull();
+        op(&mut self.core, data);
+    }
+
+    fn brk(&mut self) {
+        self.fetch();
+        let [pcl, pch] = self.core.pc.to_le_bytes();
+        self.push(pch);
+        self.push(pcl);
+        self.push(self.core.p.0 | B | U);
+        let lo = self.read(0xFFFE);
+        let hi = self.read(0xFFFF);
+        self.core.p.0 |= I;
+        self.core.pc = u16::from_le_bytes([lo, hi]);
+    }
+    fn jsr(&mut self) {
+        let lo = self.fetch();
+        self.read(0x100 | self.core.s as u16);
+        let [pcl, pch] = self.core.pc.to_le_bytes();
+        self.push(pch);
+        self.push(pcl);
+        let hi = self.fetch();
+        self.core.pc = u16::from_le_bytes([lo, hi]);
+    }
+    fn rti(&mut self) {
+        self.read(self.core.pc);
+        self.read(0x100 | self.core.s as u16);
+        let p = self.pull();
+        self.core.p = P((p | U) & !B);
+        let lo = self.pull();
+        let hi = self.pull();
+        self.core.pc = u16::from_le_bytes([lo, hi]);
+    }
+    fn rts(&mut self) {
+        self.read(self.core.pc);
+        self.read(0x100 | self.core.s as u16);
+        let lo = self.pull();
+        let hi = self.pull();
+        self.core.pc = u16::from_le_bytes([lo, hi]);
+        self.fetch();
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = self.ram.read(addr);
+        self.cycles.push(Cycle {
+            addr,
+            data,
+            write: false,
+        });
+        data
+    }
+    fn write(&mut self, addr: u16, data: u8) {
+        self.ram.write(addr, data);
+        self.cycles.push(Cycle {
+            addr,
+            data,
+            write: true,
+        });
+    }
+    fn fetch(&mut self) -> u8 {
+        let data = self.read(self.core.pc);
+        self.core.pc = self.core.pc.wrapping_add(1);
+        data
+    }
+    fn push(&mut self, data: u8) {
+        self.write(0x100 | self.core.s as u16, data);
+        self.core.s = self.core.s.wrapping_sub(1);
+    }
+    fn pull(&mut self) -> u8 {
+        self.core.s = self.core.s.wrapping_add(1);
+        self.read(0x100 | self.core.s as u16)
+    }
+}
+
+// The addressing mode and operation of the opcodes left once the ones with
+// their own sequences are taken out.
+fn decode(opcode: u8) -> (Mode, Kind) {
+    use Kind::*;
+    use Mode::*;
+
+    let (aaa, bbb, cc) = (opcode >> 5, opcode >> 2 & 7, opcode & 3);
+    let mut mode = match (cc & 1, bbb) {
+        (1, 0) => IndirectX,
+        (1, 2) | (0, 0) => Immediate,
+        (1, 4) => IndirectY,
+        (1, 6) => AbsoluteY,
+        (_, 1) => Zero,
+        (_, 3) => Absolute,
+        (_, 5) => ZeroX,
+        (_, 7) => AbsoluteX,
+        _ => unreachable!("{opcode:02X}"),
+    };
+    // STX, LDX, SAX and LAX index with Y where the others use X.
+    if cc & 2 != 0 && (aaa == 4 || aaa == 5) {
+        mode = match mode {
+            ZeroX => ZeroY,
+            AbsoluteX => AbsoluteY,
+            mode => mode,
+        };
+    }
+
+    let modify: [fn(&mut Core, u8) -> u8; 4] = [asl, rol, lsr, ror];
+    let combined: [fn(&mut Core, u8) -> u8; 4] = [slo, rla, sre, rra];
+    let kind = match (cc, aaa) {
+        _ if opcode == 0x89 => Read(nop),
+        (1, 4) => Write(|core| core.a),
+        (1, _) => Read([ora, and, eor, adc, nop, lda, cmp, sbc][aaa as usize]),
+        (2, _) if mode == Immediate && aaa != 5 => Read(nop),
+        (2, 0..=3) => Modify(modify[aaa as usize]),
+        (2, 4) => Write(|core| core.x),
+        (2, 5) => Read(ldx),
+        (2, 6) => Modify(dec),
+        (2, _) => Modify(inc),
+        (0, 1) if bbb == 1 || bbb == 3 => Read(bit),
+        (0, 4) if bbb != 0 => Write(|core| core.y),
+        (0, 5) => Read(ldy),
+        (0, 6) if bbb <= 3 => Read(cpy),
+        (0, 7) if bbb <= 3 => Read(cpx),
+        (0, _) => Read(nop),
+        _ if bbb == 2 => Read([anc, anc, alr, arr, ane, lxa, sbx, sbc][aaa as usize]),
+        _ if opcode == 0xBB => Read(las),
+        (_, 0..=3) => Modify(combined[aaa as usize]),
+        (_, 4) => Write(|core| core.a & core.x),
+        (_, 5) => Read(lax),
+        (_, 6) => Modify(dcp),
+        _ => Modify(isc),
+    };
+    (mode, kind)
+}
+
+fn set(core: &mut Core, flag: u8, on: bool) {
+    core.p.0 = if on {
+        core.p.0 | flag
+    } else {
+        core.p.0 & !flag
+    };
+}
+fn nz(core: &mut Core, value: u8) -> u8 {
+    set(core, N, value & 0x80 != 0);
+    set(core, Z, value == 0);
+    value
+}
+
+fn nop(_: &mut Core, _: u8) {}
+fn lda(core: &mut Core, data: u8) {
+    core.a = nz(core, data);
+}
+fn ldx(core: &mut Core, data: u8) {
+    core.x = nz(core, data);
+}
+fn ldy(core: &mut Core, data: u8) {
+    core.y = nz(core, data);
+}
+fn lax(core: &mut Core, data: u8) {
+    lda(core, data);
+    core.x = data;
+}
+fn las(core: &mut Core, data: u8) {
+    let value = data & core.s;
+    lax(core, value);
+    core.s = value;
+}
+fn ora(core: &mut Core, data: u8) {
+    lda(core, core.a | data);
+}
+fn and(core: &mut Core, data: u8) {
+    lda(core, core.a & data);
+}
+fn eor(core: &mut Core, data: u8) {
+    lda(core, core.a ^ data);
+}
+fn adc(core: &mut Core, data: u8) {
+    let sum = core.a as u16 + data as u16 + (core.p.0 & C) as u16;
+    let result = sum as u8;
+    set(core, C, sum > 0xFF);
+    set(core, V, (core.a ^ result) & (data ^ result) & 0x80 != 0);
+    lda(core, result);
+}
+fn sbc(core: &mut Core, data: u8) {
+    adc(core, !data);
+}
+fn compare(core: &mut Core, register: u8, data: u8) {
+    set(core, C, register >= data);
+    nz(core, register.wrapping_sub(data));
+}
+fn cmp(core: &mut Core, data: u8) {
+    compare(core, core.a, data);
+}
+fn cpx(core: &mut Core, data: u8) {
+    compare(core, core.x, data);
+}
+fn cpy(core: &mut Core, data: u8) {
+    compare(core, core.y, data);
+}
+fn bit(core: &mut Core, data: u8) {
+    set(core, N, data & 0x80 != 0);
+    set(core, V, data & 0x40 != 0);
+    set(core, Z, core.a & data == 0);
+}
+
+fn asl(core: &mut Core, data: u8) -> u8 {
+    set(core, C, data & 0x80 != 0);
+    nz(core, data << 1)
+}
+fn lsr(core: &mut Core, data: u8) -> u8 {
+    set(core, C, data & 1 != 0);
+    nz(core, data >> 1)
+}
+fn rol(core: &mut Core, data: u8) -> u8 {
+    let carry = core.p.0 & C;
+    set(core, C, data & 0x80 != 0);
+    nz(core, data << 1 | carry)
+}
+fn ror(core: &mut Core, data: u8) -> u8 {
+    let carry = core.p.0 & C;
+    set(core, C, data & 1 != 0);
+    nz(core, data >> 1 | carry << 7)
+}
+fn inc(core: &mut Core, data: u8) -> u8 {
+    nz(core, data.wrapping_add(1))
+}
+fn dec(core: &mut Core, data: u8) -> u8 {
+    nz(core, data.wrapping_sub(1))
+}
+
+fn slo(core: &mut Core, data: u8) -> u8 {
+    let data = asl(core, data);
+    ora(core, data);
+    data
+}
+fn rla(core: &mut Core, data: u8) -> u8 {
+    let data = rol(core, data);
+    and(core, data);
+    data
+}
+fn sre(core: &mut Core, data: u8) -> u8 {
+    let data = lsr(core, data);
+    eor(core, data);
+    data
+}
+fn rra(core: &mut Core, data: u8) -> u8 {
+    let data = ror(core, data);
+    adc(core, data);
+    data
+}
+fn dcp(core: &mut Core, data: u8) -> u8 {
+    let data = dec(core, data);
+    cmp(core, data);
+    data
+}
+fn isc(core: &mut Core, data: u8) -> u8 {
+    let data = inc(core, data);
+    sbc(core, data);
+    data
+}
+
+fn anc(core: &mut Core, data: u8) {
+    and(core, data);
+    set(core, C, core.a & 0x80 != 0);
+}
+fn alr(core: &mut Core, data: u8) {
+    and(core, data);
+    core.a = lsr(core, core.a);
+}
+// AND then ROR, with C and V taken from bits 6 and 5 of the result.
+fn arr(core: &mut Core, data: u8) {
+    let carry = core.p.0 & C;
+    let result = (core.a & data) >> 1 | carry << 7;
+    lda(core, result);
+    set(core, C, result & 0x40 != 0);
+    set(core, V, (result ^ result << 1) & 0x40 != 0);
+}
+fn ane(core: &mut Core, data: u8) {
+    lda(core, (core.a | MAGIC) & core.x & data);
+}
+fn lxa(core: &mut Core, data: u8) {
+    lax(core, (core.a | MAGIC) & data);
+}
+fn sbx(core: &mut Core, data: u8) {
+    let value = core.a & core.x;
+    set(core, C, value >= data);
+    core.x = nz(core, value.wrapping_sub(data));
+}
@@ -0,0 +1,108 @@
+use crate::{
+    devices::riot::Riot,
+    irq::IrqLine,
+    memory::{Memory, MemoryMap},
+};
+use std::{cell::RefCell, rc::Rc};
+
+const RS: u16 = Riot::RS;
+const SWCHA: u16 = RS;
+const SWACNT: u16 = RS | 0x1;
+const INTIM: u16 = RS | 0x4;
+const INSTAT: u16 = RS | 0x5;
+const TIM1T: u16 = RS | 0x14;
+const TIM8T: u16 = RS | 0x15;
+const TIM64TI: u16 = RS | 0x1E;
+
+#[test]
+fn ram_is_selected_without_rs() {
+    let mut riot = Riot::new();
+    riot.write(0x85, 0x12);
+    assert_eq!(riot.read(0x05), Some(0x12));
+    assert_eq!(riot.ram()[5], 0x12);
+}
+#[test]
+fn timer_counts_at_interval() {
+    let mut riot = Riot::new();
+    riot.write(TIM8T, 3);
+
+    riot.clock();
+    assert_eq!(riot.read(INTIM), Some(2));
+    for _ in 0..8 {
+        riot.clock();
+    }
+    assert_eq!(riot.read(INTIM), Some(1));
+}
+#[test]
+fn timer_runs_at_one_cycle_after_expiry() {
+    let mut riot = Riot::new();
+    riot.write(TIM8T, 1);
+    for _ in 0..9 {
+        riot.clock();
+    }
+    assert_eq!(riot.read(INSTAT), Some(0x80));
+    assert_eq!(riot.read(INSTAT), Some(0x80));
+
+    riot.clock();
+    assert_eq!(riot.read(INTIM), Some(0xFE));
+    assert_eq!(riot.read(INSTAT), Some(0x00));
+}
+#[test]
+fn tim1t_expires() {
+    let mut riot = Riot::new();
+    riot.write(TIM1T, 2);
+    for _ in 0..3 {
+        riot.clock();
+    }
+    assert_eq!(riot.read(INSTAT), Some(0x80));
+}
+#[test]
+fn timer_interrupt() {
+    let line = IrqLine::new();
+    let mut riot = Riot::new().with_irq(line.source("riot"));
+    riot.write(TIM64TI, 1);
+    for _ in 0..64 {
+        riot.clock();
+    }
+    assert!(!line.level());
+    riot.clock();
+    assert!(line.level());
+
+    riot.read(INTIM);
+    assert!(!line.level());
+}
+#[test]
+fn pa7_edge_interrupt() {
+    let mut riot = Riot::new();
+    riot.write(RS | 0x07, 0);
+    riot.set_port_a(0x80);
+    assert!(!riot.irq());
+    riot.set_port_a(0x00);
+    assert!(!riot.irq());
+    riot.set_port_a(0x80);
+    assert!(riot.irq());
+
+    assert_eq!(riot.read(INSTAT), Some(0x40));
+    assert!(!riot.irq());
+    assert_eq!(riot.read(INSTAT), Some(0x00));
+}
+#[test]
+fn ports_honour_ddr() {
+    let mut riot = Riot::new();
+    riot.write(SWACNT, 0x0F);
+    riot.write(SWCHA, 0x05);
+    riot.set_port_a(0xA0);
+    assert_eq!(riot.read(SWCHA), Some(0xA5));
+}
+#[test]
+fn plugs_into_2600_map() {
+    let riot = Rc::new(RefCell::new(Riot::new()));
+    let mut map = MemoryMap::builder()
+        .absolute_device(0x0080..=0x00FF, riot.clone())
+        .absolute_device(0x0280..=0x029F, riot.clone())
+        .build();
+    map.write(0x00FF, 0x42);
+    map.write(0x0296, 10);
+    assert_eq!(riot.borrow().ram()[0x7F], 0x42);
+    assert_eq!(map.read(0x0284), Some(10));
+}
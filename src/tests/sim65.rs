@@ -0,0 +1,138 @@
+use super::Output;
+use crate::{
+    Bus,
+    memory::Memory,
+    sim65::{Header, Sim65},
+};
+
+const SP: u8 = 0x02;
+const C_STACK: u16 = 0xC000;
+
+fn image(code: &[u8]) -> Vec<u8> {
+    let mut image = b"sim65\x02\x00".to_vec();
+    image.push(SP);
+    image.extend_from_slice(&[0x00, 0x02, 0x00, 0x02]);
+    image.extend_from_slice(code);
+    image
+}
+fn push(ram: &mut [u8; 0x10000], words: &[u16]) {
+    let mut sp = u16::from_le_bytes([ram[SP as usize], ram[SP as usize + 1]]);
+    for &word in words {
+        sp -= 2;
+        ram[sp as usize..sp as usize + 2].copy_from_slice(&word.to_le_bytes());
+    }
+    ram[SP as usize..SP as usize + 2].copy_from_slice(&sp.to_le_bytes());
+}
+fn call(sim: &mut Sim65, ram: &mut [u8; 0x10000], hook: u16, a: u8, x: u8, y: u8) -> u16 {
+    let (_, mut cpu, _) = Sim65::load(&image(&[])).unwrap();
+    let core = cpu.core_mut();
+    (core.a, core.x, core.y) = (a, x, y);
+
+    let mut bus = Bus::new();
+    bus.read_sync(hook);
+    assert!(sim.trap(&mut cpu, &mut bus, ram));
+    assert_eq!(bus.data, 0x60);
+    u16::from_le_bytes([cpu.core().a, cpu.core().x])
+}
+
+#[test]
+fn header_requires_version_2() {
+    let image = image(&[0xEA]);
+    let (header, data) = Header::parse(&image).unwrap();
+    assert_eq!(header.sp, SP);
+    assert_eq!(header.load, 0x0200);
+    assert_eq!(data, &[0xEA]);
+
+    let mut old = image.clone();
+    old[5] = 1;
+    assert!(Header::parse(&old).is_none());
+    assert!(Header::parse(b"sim64\x02\x00\x02\x00\x02\x00\x02").is_none());
+}
+#[test]
+fn load_rejects_other_cpus_and_overlong_images() {
+    assert!(Sim65::load(&image(&[0xEA])).is_some());
+
+    let mut c02 = image(&[0xEA]);
+    c02[6] = 1;
+    assert!(Sim65::load(&c02).is_none());
+
+    let mut high = image(&[0xEA; 32]);
+    high[8..10].copy_from_slice(&[0xF0, 0xFF]);
+    assert!(Sim65::load(&high).is_none());
+    high.truncate(high.len() - 16);
+    assert!(Sim65::load(&high).is_some());
+}
+#[test]
+fn write_and_exit() {
+    #[rustfmt::skip]
+    let code = [
+        0xA9, 0x05,       // LDA #5
+        0xA2, 0x00,       // LDX #0
+        0x20, 0xF7, 0xFF, // JSR write
+        0x85, 0x10,       // STA $10
+        0xA9, 0x2A,       // LDA #42
+        0x20, 0xF9, 0xFF, // JSR exit
+    ];
+    let (sim, mut cpu, mut ram) = Sim65::load(&image(&code)).unwrap();
+    ram[0x0300..0x0305].copy_from_slice(b"hello");
+    ram[SP as usize..SP as usize + 2].copy_from_slice(&C_STACK.to_le_bytes());
+    push(&mut ram, &[1, 0x0300]);
+
+    let stdout = Output::default();
+    let mut sim = sim.with_stdout(stdout.clone());
+    assert_eq!(sim.run(&mut cpu, &mut *ram, 1000), Some(42));
+    assert_eq!(*stdout.0.borrow(), b"hello");
+    assert_eq!(ram[0x10], 5);
+    assert_eq!(ram[SP as usize..SP as usize + 2], C_STACK.to_le_bytes());
+}
+#[test]
+fn args_are_copied_below_the_c_stack() {
+    let mut sim = Sim65::new(SP).with_args(["prog", "a", "bc"]);
+    let mut ram = Box::new([0; 0x10000]);
+    ram.write(SP as u16, 0x00);
+    ram.write(SP as u16 + 1, 0xC0);
+
+    assert_eq!(call(&mut sim, &mut ram, Sim65::ARGS, 0x10, 0x00, 0), 3);
+    let argv = u16::from_le_bytes([ram[0x10], ram[0x11]]) as usize;
+    assert_eq!(argv, 0xC000 - 8);
+
+    let arg = |i: usize| {
+        let addr = u16::from_le_bytes([ram[argv + 2 * i], ram[argv + 2 * i + 1]]) as usize;
+        let len = ram[addr..].iter().position(|&b| b == 0).unwrap();
+        String::from_utf8(ram[addr..addr + len].to_vec()).unwrap()
+    };
+    assert_eq!([arg(0), arg(1), arg(2)], ["prog", "a", "bc"]);
+    assert_eq!(ram[argv + 6..argv + 8], [0, 0]);
+    let sp = u16::from_le_bytes([ram[SP as usize], ram[SP as usize + 1]]) as usize;
+    assert_eq!(sp, argv - "prog\0a\0bc\0".len());
+}
+#[test]
+fn files_round_trip() {
+    let path = std::env::temp_dir().join(format!("m6502-sim65-{}", std::process::id()));
+    let mut sim = Sim65::new(SP);
+    let mut ram = Box::new([0; 0x10000]);
+    ram[SP as usize..SP as usize + 2].copy_from_slice(&C_STACK.to_le_bytes());
+    let name = path.to_str().unwrap().as_bytes();
+    ram[0x0400..0x0400 + name.len()].copy_from_slice(name);
+    ram[0x0500..0x0503].copy_from_slice(b"abc");
+
+    // open(name, O_WRONLY | O_CREAT | O_TRUNC, 0644)
+    push(&mut ram, &[0x0400, 0x32, 0o644]);
+    let fd = call(&mut sim, &mut ram, Sim65::OPEN, 0, 0, 6);
+    assert_eq!(fd, 3);
+    push(&mut ram, &[fd, 0x0500]);
+    assert_eq!(call(&mut sim, &mut ram, Sim65::WRITE, 3, 0, 0), 3);
+    assert_eq!(call(&mut sim, &mut ram, Sim65::CLOSE, 3, 0, 0), 0);
+    assert_eq!(call(&mut sim, &mut ram, Sim65::CLOSE, 3, 0, 0), 0xFFFF);
+
+    // open(name, O_RDONLY)
+    push(&mut ram, &[0x0400, 0x01]);
+    let fd = call(&mut sim, &mut ram, Sim65::OPEN, 0, 0, 4);
+    assert_eq!(fd, 3);
+    push(&mut ram, &[fd, 0x0600]);
+    assert_eq!(call(&mut sim, &mut ram, Sim65::READ, 16, 0, 0), 3);
+    assert_eq!(&ram[0x0600..0x0603], b"abc");
+    assert_eq!(ram[SP as usize..SP as usize + 2], C_STACK.to_le_bytes());
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, fs};
+
+use super::reference::{Cycle, Ram};
+use crate::{
+    Bus, M6502,
+    core::{Core, P},
+};
+
+// A switch-level simulation of the NMOS 6502 as visual6502 and perfect6502
+// do it, from visual6502's netlist files: `segdefs.js` (which nodes are
+// pulled up), `transdefs.js` (each transistor's gate and channel nodes)
+// and `nodenames.js`. Nodes joined through transistors that are on form a
+// group, whose value comes from ground, power, a pull-up, an external pull
+// or else the charge left on it, ranked as perfect6502 ranks them.
+//
+// The comparison with `M6502` reads the netlist files from `netlist` next
+// to the manifest and fails without them. They are not checked in yet, so
+// it is ignored by default: `cargo test m6502_matches -- --ignored`.
+
+const CYCLES: usize = 300;
+const PROGRAMS: u64 = 8;
+const RECALC_LIMIT: usize = 100;
+
+#[derive(Clone, Default)]
+struct Node {
+    pullup: bool,
+    pulldown: bool,
+    state: bool,
+    gates: Vec<usize>,
+    channels: Vec<usize>,
+}
+
+#[derive(Clone)]
+struct Transistor {
+    c1: usize,
+    c2: usize,
+    on: bool,
+}
+
+struct Chip {
+    nodes: Vec<Node>,
+    transistors: Vec<Transistor>,
+    names: HashMap<String, usize>,
+    vss: usize,
+    vcc: usize,
+    in_group: Vec<bool>,
+}
+impl Chip {
+    fn parse(segdefs: &str, transdefs: &str, nodenames: &str) -> Self {
+        let names: HashMap<String, usize> = nodenames
+            .lines()
+            .filter_map(|line| {
+                let (name, number) = line.split_once(':')?;
+                let number = number.trim().trim_end_matches([',', '}', ';']).trim();
+                Some((name.trim().to_string(), number.parse().ok()?))
+            })
+            .collect();
+        let fields = |line: &str| -> Vec<String> {
+            let Some((_, record)) = line.split_once('[') else {
+                return Vec::new();
+            };
+            record
+                .split(',')
+                .map(|field| field.trim_matches([' ', '\'', '"', '[', ']']).to_string())
+                .collect()
+        };
+
+        let mut nodes = Vec::new();
+        let node = |nodes: &mut Vec<Node>, number: usize| {
+            if nodes.len() <= number {
+                nodes.resize(number + 1, Node::default());
+            }
+            number
+        };
+        for fields in segdefs.lines().map(fields) {
+            if let [number, pull, ..] = &fields[..]
+                && let Ok(number) = number.parse()
+            {
+                let number = node(&mut nodes, number);
+                nodes[number].pullup |= pull == "+";
+            }
+        }
+        let mut transistors = Vec::new();
+        for fields in transdefs.lines().map(fields) {
+            if let [_, gate, c1, c2, ..] = &fields[..]
+                && let (Ok(gate), Ok(c1), Ok(c2)) = (gate.parse(), c1.parse(), c2.parse())
+            {
+                let id = transistors.len();
+                let (gate, c1, c2) = (
+                    node(&mut nodes, gate),
+                    node(&mut nodes, c1),
+                    node(&mut nodes, c2),
+                );
+                nodes[gate].gates.push(id);
+                nodes[c1].channels.push(id);
+                nodes[c2].channels.push(id);
+                transistors.push(Transistor { c1, c2, on: false });
+            }
+        }
+
+        let (vss, vcc) = (names["vss"], names["vcc"]);
+        node(&mut nodes, vss.max(vcc));
+        nodes[vcc].state = true;
+        let mut chip = Self {
+            in_group: vec![false; nodes.len()],
+            nodes,
+            transistors,
+            names,
+            vss,
+            vcc,
+        };
+        chip.recalc((0..chip.nodes.len()).collect());
+        chip
+    }
+
+    fn node(&self, name: &str) -> usize {
+        *self
+            .names
+            .get(name)
+            .unwrap_or_else(|| panic!("no node {name}"))
+    }
+    fn is_high(&self, name: &str) -> bool {
+        self.nodes[self.node(name)].state
+    }
+    // Pulls named nodes up or down from outside, as pins are.
+    fn drive(&mut self, pins: &[(&str, bool)]) {
+        let mut list = Vec::new();
+        for &(name, high) in pins {
+            let node = self.node(name);
+            self.nodes[node].pullup = high;
+            self.nodes[node].pulldown = !high;
+            list.push(node);
+        }
+        self.recalc(list);
+    }
+    fn read_bits(&self, prefix: &str, bits: usize) -> u16 {
+        (0..bits)
+            .filter(|bit| self.is_high(&format!("{prefix}{bit}")))
+            .fold(0, |value, bit| value | 1 << bit)
+    }
+    fn drive_bits(&mut self, prefix: &str, bits: usize, value: u16) {
+        let names: Vec<_> = (0..bits).map(|bit| format!("{prefix}{bit}")).collect();
+        let pins: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(bit, name)| (name.as_str(), value & 1 << bit != 0))
+            .collect();
+        self.drive(&pins);
+    }
+
+    // Settles the nodes in `list` and everything their changes reach.
+    fn recalc(&mut self, mut list: Vec<usize>) {
+        let mut queued = vec![false; self.nodes.len()];
+        for _ in 0..RECALC_LIMIT {
+            if list.is_empty() {
+                return;
+            }
+            let mut next = Vec::new();
+            for node in list {
+                self.recalc_node(node, &mut next, &mut queued);
+            }
+            for &node in &next {
+                queued[node] = false;
+            }
+            list = next;
+        }
+    }
+    fn recalc_node(&mut self, node: usize, next: &mut Vec<usize>, queued: &mut [bool]) {
+        if node == self.vss || node == self.vcc {
+            return;
+        }
+        let group = self.group(node);
+        let state = self.value(&group);
+        for node in group {
+            if self.nodes[node].state == state {
+                continue;
+            }
+            self.nodes[node].state = state;
+            for i in 0..self.nodes[node].gates.len() {
+                let id = self.nodes[node].gates[i];
+                let transistor = &mut self.transistors[id];
+                if transistor.on == state {
+                    continue;
+                }
+                transistor.on = state;
+                let (c1, c2) = (transistor.c1, transistor.c2);
+                for node in [c1, c2] {
+                    if !queued[node] {
+                        queued[node] = true;
+                        next.push(node);
+                    }
+                }
+            }
+        }
+    }
+    // The nodes connected to `node` through transistors that are on. Power
+    // and ground join a group but connect nothing through themselves.
+    fn group(&mut self, node: usize) -> Vec<usize> {
+        let mut group = vec![node];
+        self.in_group[node] = true;
+        let mut i = 0;
+        while i < group.len() {
+            let node = group[i];
+            i += 1;
+            if node == self.vss || node == self.vcc {
+                continue;
+            }
+            for &id in &self.nodes[node].channels {
+                let transistor = &self.transistors[id];
+                if !transistor.on {
+                    continue;
+                }
+                let other = if transistor.c1 == node {
+                    transistor.c2
+                } else {
+                    transistor.c1
+                };
+                if !self.in_group[other] {
+                    self.in_group[other] = true;
+                    group.push(other);
+                }
+            }
+        }
+        for &node in &group {
+            self.in_group[node] = false;
+        }
+        group
+    }
+    // Ground beats power, which beats a pull-down, which beats a pull-up,
+    // which beats charge left on the group.
+    fn value(&self, group: &[usize]) -> bool {
+        let nodes = || group.iter().map(|&node| &self.nodes[node]);
+        if group.contains(&self.vss) {
+            false
+        } else if group.contains(&self.vcc) {
+            true
+        } else if nodes().any(|node| node.pulldown) {
+            false
+        } else {
+            nodes().any(|node| node.pullup || node.state)
+        }
+    }
+}
+
+// The chip wired to memory and clocked the way perfect6502 does it.
+struct Simulated {
+    chip: Chip,
+    ram: Ram,
+}
+impl Simulated {
+    fn reset(chip: Chip, ram: Ram) -> Self {
+        let mut sim = Self { chip, ram };
+        sim.chip.drive(&[
+            ("res", false),
+            ("clk0", true),
+            ("rdy", true),
+            ("so", false),
+            ("irq", true),
+            ("nmi", true),
+        ]);
+        for _ in 0..8 {
+            sim.cycle();
+        }
+        sim.chip.drive(&[("res", true)]);
+        sim
+    }
+
+    fn half_step(&mut self) -> Option<Cycle> {
+        let clock = self.chip.is_high("clk0");
+        self.chip.drive(&[("clk0", !clock)]);
+        if clock {
+            return None;
+        }
+        let addr = self.chip.read_bits("ab", 16);
+        let data = if self.chip.is_high("rw") {
+            let data = self.ram.read(addr);
+            self.chip.drive_bits("db", 8, data as u16);
+            data
+        } else {
+            let data = self.chip.read_bits("db", 8) as u8;
+            self.ram.write(addr, data);
+            data
+        };
+        Some(Cycle {
+            addr,
+            data,
+            write: !self.chip.is_high("rw"),
+        })
+    }
+    fn cycle(&mut self) -> Cycle {
+        loop {
+            if let Some(cycle) = self.half_step() {
+                return cycle;
+            }
+        }
+    }
+
+    fn core(&self, pc: u16) -> Core {
+        let register = |name| self.chip.read_bits(name, 8) as u8;
+        Core {
+            a: register("a"),
+            p: P::from_pull_byte(register("p")),
+            pc,
+            s: register("s"),
+            x: register("x"),
+            y: register("y"),
+        }
+    }
+}
+
+// From the first opcode fetch after reset, compares every bus cycle of
+// random programs with `M6502` started from the registers the chip has
+// then.
+#[test]
+#[ignore = "needs the visual6502 netlist in netlist/"]
+fn m6502_matches_transistors() {
+    let read = |name: &str| {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/netlist/").to_string() + name;
+        fs::read_to_string(&path).unwrap_or_else(|err| panic!("{path}: {err}"))
+    };
+    let (segdefs, transdefs, nodenames) = (
+        read("segdefs.js"),
+        read("transdefs.js"),
+        read("nodenames.js"),
+    );
+
+    for seed in 1..=PROGRAMS {
+        let mut ram = Ram::new(seed);
+        ram.load(0xFFFC, &[0x00, 0x02]);
+        let chip = Chip::parse(&segdefs, &transdefs, &nodenames);
+        let mut sim = Simulated::reset(chip, ram.clone());
+        let mut fetch = sim.cycle();
+        while !sim.chip.is_high("sync") {
+            fetch = sim.cycle();
+        }
+
+        let mut cpu = M6502::new(sim.core(fetch.addr));
+        let mut bus = Bus::new();
+        ram = sim.ram.clone();
+        let mut expected = vec![fetch];
+        let mut actual = Vec::new();
+        for i in 0..CYCLES {
+            cpu.clock(&mut bus);
+            if bus.rw() {
+                bus.respond(ram.read(bus.addr));
+            } else {
+                ram.write(bus.addr, bus.data);
+            }
+            actual.push(Cycle {
+                addr: bus.addr,
+                data: bus.data,
+                write: !bus.rw(),
+            });
+            if i > 0 {
+                expected.push(sim.cycle());
+            }
+            assert_eq!(
+                actual,
+                expected,
+                "program {seed} disagrees at cycle {i}, last {} cycles shown",
+                actual.len()
+            );
+        }
+    }
+}
+
+// An NMOS inverter: a pulled-up output and a transistor from it to ground.
+const INVERTER_SEGDEFS: &str = "[1,'-',0]\n[2,'-',0]\n[3,'-',0]\n[4,'+',0]";
+const INVERTER_TRANSDEFS: &str = "['t1',3,4,1,[],[],false]";
+const INVERTER_NAMES: &str = "vss: 1,\nvcc: 2,\nin: 3,\nout: 4,";
+
+#[test]
+fn inverter_inverts() {
+    let mut chip = Chip::parse(INVERTER_SEGDEFS, INVERTER_TRANSDEFS, INVERTER_NAMES);
+    chip.drive(&[("in", true)]);
+    assert!(!chip.is_high("out"));
+    chip.drive(&[("in", false)]);
+    assert!(chip.is_high("out"));
+}
+
+#[test]
+fn isolated_node_keeps_its_charge() {
+    // `store` sits behind a pass transistor gated by `enable`.
+    let segdefs = "[1,'-',0]\n[2,'-',0]\n[3,'-',0]\n[4,'-',0]\n[5,'-',0]";
+    let transdefs = "['t1',4,3,5,[],[],false]";
+    let names = "vss: 1,\nvcc: 2,\ndata: 3,\nenable: 4,\nstore: 5,";
+    let mut chip = Chip::parse(segdefs, transdefs, names);
+
+    chip.drive(&[("data", true), ("enable", true)]);
+    assert!(chip.is_high("store"));
+    chip.drive(&[("enable", false)]);
+    chip.drive(&[("data", false)]);
+    assert!(chip.is_high("store"));
+    chip.drive(&[("enable", true)]);
+    assert!(!chip.is_high("store"));
+}
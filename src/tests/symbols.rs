@@ -0,0 +1,68 @@
+use crate::{
+    loader::LoadError,
+    symbols::{Symbol, SymbolKind, SymbolTable, parse_labels, parse_vice},
+};
+
+fn symbol(name: &str, addr: u16, size: u16, kind: SymbolKind) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        addr,
+        size,
+        kind,
+    }
+}
+
+#[test]
+fn lookup_respects_sizes() {
+    let mut table = SymbolTable::new();
+    table.extend([
+        symbol("reset", 0xE000, 0, SymbolKind::Label),
+        symbol("main", 0x0800, 0x10, SymbolKind::Function),
+        symbol(".loop", 0x0800, 0, SymbolKind::Label),
+    ]);
+
+    assert_eq!(table.describe(0x0800).as_deref(), Some("main"));
+    assert_eq!(table.describe(0x080F).as_deref(), Some("main+15"));
+    assert_eq!(table.describe(0x0810), None);
+    assert_eq!(table.describe(0xFFFF).as_deref(), Some("reset+8191"));
+    assert_eq!(table.describe(0x0000), None);
+    assert_eq!(table.at(0x0800).len(), 2);
+}
+#[test]
+fn redefinition_moves_a_name() {
+    let mut table = SymbolTable::new();
+    table.insert(symbol("irq", 0xE100, 0, SymbolKind::Label));
+    table.insert(symbol("irq", 0xE200, 0, SymbolKind::Label));
+
+    assert_eq!(table.len(), 1);
+    assert_eq!(table.get("irq").unwrap().addr, 0xE200);
+    assert!(table.at(0xE100).is_empty());
+    assert_eq!(table.iter().count(), 1);
+}
+#[test]
+fn vice_labels() {
+    let table = parse_vice("al C:0801 .start\nal C:080d .loop\n\nal 00fffc .reset\n").unwrap();
+
+    assert_eq!(table.len(), 3);
+    assert_eq!(table.get("start").unwrap().addr, 0x0801);
+    assert_eq!(table.describe(0x0810).as_deref(), Some("loop+3"));
+    assert_eq!(table.get("reset").unwrap().addr, 0xFFFC);
+    assert_eq!(
+        parse_vice("al C:0801 .start\nbreak 0801\n"),
+        Err(LoadError::Syntax { line: 2 })
+    );
+}
+#[test]
+fn label_lists() {
+    let text = "; zero page\nptr = $FB\nCHROUT=0xFFD2 ; kernal\nbuffer = 512\n";
+    let table = parse_labels(text).unwrap();
+
+    assert_eq!(table.get("ptr").unwrap().addr, 0x00FB);
+    assert_eq!(table.get("CHROUT").unwrap().addr, 0xFFD2);
+    assert_eq!(table.get("buffer").unwrap().addr, 0x0200);
+    assert_eq!(
+        parse_labels("ptr = $FB\nlong = $10000\n"),
+        Err(LoadError::Syntax { line: 2 })
+    );
+    assert_eq!(parse_labels("= 1"), Err(LoadError::Syntax { line: 1 }));
+}
@@ -0,0 +1,169 @@
+use crate::{devices::via::Via, irq::IrqLine, memory::Memory};
+
+const ORB: u16 = 0x0;
+const ORA: u16 = 0x1;
+const DDRB: u16 = 0x2;
+const DDRA: u16 = 0x3;
+const T1CL: u16 = 0x4;
+const T1CH: u16 = 0x5;
+const T2CL: u16 = 0x8;
+const T2CH: u16 = 0x9;
+const SR: u16 = 0xA;
+const ACR: u16 = 0xB;
+const PCR: u16 = 0xC;
+const IFR: u16 = 0xD;
+const IER: u16 = 0xE;
+
+fn cycles_until_irq(via: &mut Via, limit: usize) -> usize {
+    for i in 1..=limit {
+        via.clock();
+        if via.irq() {
+            return i;
+        }
+    }
+    panic!("no interrupt within {limit} cycles");
+}
+
+#[test]
+fn t1_one_shot() {
+    let mut via = Via::new();
+    via.write(IER, 0xC0);
+    via.write(T1CL, 10);
+    via.write(T1CH, 0);
+
+    via.clock();
+    assert_eq!(via.read(T1CL), Some(10));
+    via.write(T1CH, 0);
+    assert_eq!(cycles_until_irq(&mut via, 100), 12);
+
+    assert_eq!(via.read(IFR), Some(0xC0));
+    via.read(T1CL);
+    assert!(!via.irq());
+
+    for _ in 0..100 {
+        via.clock();
+    }
+    assert!(!via.irq());
+}
+#[test]
+fn t1_free_run_toggles_pb7() {
+    let mut via = Via::new();
+    via.write(ACR, 0xC0);
+    via.write(IER, 0xC0);
+    via.write(T1CL, 4);
+    via.write(T1CH, 0);
+    assert_eq!(via.port_b() & 0x80, 0);
+
+    assert_eq!(cycles_until_irq(&mut via, 100), 6);
+    assert_eq!(via.port_b() & 0x80, 0x80);
+    via.write(IFR, 0x40);
+
+    assert_eq!(cycles_until_irq(&mut via, 100), 6);
+    assert_eq!(via.port_b() & 0x80, 0);
+}
+#[test]
+fn t2_one_shot_and_pulse_count() {
+    let mut via = Via::new();
+    via.write(IER, 0xA0);
+    via.write(T2CL, 3);
+    via.write(T2CH, 0);
+    assert_eq!(cycles_until_irq(&mut via, 100), 5);
+    assert_eq!(via.read(T2CL), Some(0xFF));
+    assert!(!via.irq());
+
+    via.write(ACR, 0x20);
+    via.write(T2CL, 2);
+    via.write(T2CH, 0);
+    for _ in 0..100 {
+        via.clock();
+    }
+    assert!(!via.irq());
+    for _ in 0..3 {
+        via.set_port_b(0x00);
+        via.set_port_b(0x40);
+    }
+    assert!(via.irq());
+}
+#[test]
+fn ier_masks_ifr() {
+    let line = IrqLine::new();
+    let mut via = Via::new().with_irq(line.source("via"));
+    via.set_ca1(false);
+    assert_eq!(via.read(IFR), Some(0x02));
+    assert!(!line.level());
+
+    via.write(IER, 0x82);
+    assert!(line.level());
+    assert_eq!(via.read(IFR), Some(0x82));
+    assert_eq!(via.read(IER), Some(0x82));
+
+    via.write(IER, 0x02);
+    assert!(!line.level());
+}
+#[test]
+fn ca1_latches_port_a_and_handshakes_ca2() {
+    let mut via = Via::new();
+    via.write(ACR, 0x01);
+    via.write(PCR, 0x09);
+    via.set_ca1(false);
+
+    via.set_port_a(0x5A);
+    via.set_ca1(true);
+    via.set_port_a(0x00);
+    assert!(via.ca2());
+    assert_eq!(via.read(IFR), Some(0x02));
+
+    assert_eq!(via.read(ORA), Some(0x5A));
+    assert!(!via.ca2());
+    assert_eq!(via.read(IFR), Some(0x00));
+}
+#[test]
+fn ports_honour_ddr() {
+    let mut via = Via::new();
+    via.write(DDRB, 0xF0);
+    via.write(ORB, 0xAA);
+    via.set_port_b(0x05);
+    assert_eq!(via.read(ORB), Some(0xA5));
+
+    via.write(DDRA, 0xFF);
+    via.write(ORA, 0x3C);
+    assert_eq!(via.port_a(), 0x3C);
+}
+#[test]
+fn cb2_pulse_output() {
+    let mut via = Via::new();
+    via.write(PCR, 0xA0);
+    via.write(ORB, 0x00);
+    assert!(!via.cb2());
+    via.clock();
+    assert!(via.cb2());
+}
+#[test]
+fn sr_shift_out_under_phi2() {
+    let mut via = Via::new();
+    via.write(IER, 0x84);
+    via.write(ACR, 0x18);
+    via.write(SR, 0b1011_0010);
+
+    let mut bits = Vec::new();
+    for _ in 0..8 {
+        via.clock();
+        bits.push(via.cb2() as u8);
+    }
+    assert_eq!(bits, vec![1, 0, 1, 1, 0, 0, 1, 0]);
+    assert!(via.irq());
+}
+#[test]
+fn sr_shift_in_under_cb1() {
+    let mut via = Via::new();
+    via.write(ACR, 0x0C);
+    via.read(SR);
+
+    for bit in [0, 1, 1, 0, 1, 0, 0, 1] {
+        via.set_cb2(bit != 0);
+        via.set_cb1(false);
+        via.set_cb1(true);
+    }
+    assert_eq!(via.read(IFR).unwrap() & 0x04, 0x04);
+    assert_eq!(via.read(SR), Some(0b0110_1001));
+}
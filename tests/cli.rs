@@ -0,0 +1,91 @@
+// Runs the m6502 command-line runner on programs written to a scratch
+// directory.
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+fn scratch(name: &str, data: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, data).unwrap();
+    path
+}
+
+// A 16K ROM at $C000 that stores A and reaches its exit address through
+// the reset vector.
+#[test]
+fn runs_raw_rom_with_vectors() {
+    let mut rom = vec![0xEA; 0x4000];
+    // LDA #$2A; STA $0200; JMP $C008
+    rom[..8].copy_from_slice(&[0xA9, 0x2A, 0x8D, 0x00, 0x02, 0x4C, 0x08, 0xC0]);
+    rom[0x3FFC..].copy_from_slice(&[0x00, 0xC0, 0x00, 0xC0]);
+    let path = scratch("rom.bin", &rom);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m6502"))
+        .args(["-f", "raw", "-l", "0xC000", "-e", "0xC008"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("A=2A"), "{stderr}");
+}
+
+#[test]
+fn monitor_assembles_hex_operands() {
+    let path = scratch("blank.bin", &[0xEA]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_m6502"))
+        .args(["-m", "-f", "raw", "-l", "0x0200"])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let commands =
+        "a 0200 jmp 2000\na 0203 lda #10\na 0205 sta (fb),y\na 0207 asl a\nm 0200 0207\nx\n";
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("JMP $2000"), "{stdout}");
+    assert!(stdout.contains("LDA #$10"), "{stdout}");
+    assert!(stdout.contains("STA ($FB),Y"), "{stdout}");
+    assert!(stdout.contains("4C 00 20 A9 10 91 FB 0A"), "{stdout}");
+}
+
+#[test]
+fn monitor_loads_empty_files() {
+    let path = scratch("start.bin", &[0xEA]);
+    let empty = scratch("empty.bin", &[]);
+    let prg = scratch("empty.prg", &[0x00, 0x00]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_m6502"))
+        .args(["-m", "-f", "raw", "-l", "0x0200"])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let commands = format!("l {} 0000\nl {}\nx\n", empty.display(), prg.display());
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert_eq!(
+        stdout.matches("loaded nothing at $0000").count(),
+        2,
+        "{stdout}"
+    );
+}
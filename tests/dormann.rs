@@ -0,0 +1,193 @@
+// Klaus Dormann's 6502 test programs
+// (https://github.com/Klaus2m5/6502_65C02_functional_tests), run from the
+// images in `bin_files`: 64K binaries loaded at $0000 with their as65
+// listings beside them. They are looked for in $DORMANN_TESTS, or else in
+// a clone of that repository next to this crate's manifest. The tests are
+// ignored by default, and fail when run without their images:
+// `DORMANN_TESTS=path/to/bin_files cargo test -- --ignored`.
+//
+// Each program ends in a loop on itself: at the success address if every
+// test passed, else at the check that failed, with the number of the test
+// it belongs to in `test_case`. The success address is taken from the
+// listing when there is one, since it moves when a program is assembled
+// with another configuration.
+
+use std::{env, fs, path::PathBuf};
+
+use m6502::{
+    Bus, M6502,
+    core::{Core, P},
+    memory::Memory,
+};
+
+// The 65C02 stop instruction the decimal test ends on.
+const STP: u8 = 0xDB;
+const CYCLE_LIMIT: u64 = 200_000_000;
+const SUCCESS: &str = "test passed, no errors";
+
+// The interrupt test's feedback register: writing bit 0 raises IRQ and
+// bit 1 raises NMI.
+const FEEDBACK: u16 = 0xBFFC;
+
+struct Program {
+    name: &'static str,
+    start: u16,
+    // Where the prebuilt image in `bin_files` succeeds.
+    success: u16,
+    test_case: Option<u16>,
+    feedback: bool,
+}
+
+struct Finished {
+    ram: Box<[u8; 65536]>,
+    core: Core,
+    pc: u16,
+}
+
+fn image_dir() -> PathBuf {
+    match env::var_os("DORMANN_TESTS") {
+        Some(dir) => dir.into(),
+        None => [
+            env!("CARGO_MANIFEST_DIR"),
+            "6502_65C02_functional_tests",
+            "bin_files",
+        ]
+        .iter()
+        .collect(),
+    }
+}
+
+// Runs a program until it loops on itself or stops.
+fn run(name: &str, start: u16, feedback: bool) -> Finished {
+    let path = image_dir().join(format!("{name}.bin"));
+    let image = fs::read(&path)
+        .unwrap_or_else(|err| panic!("{name}: no image at {}: {err}", path.display()));
+    let mut ram = Box::new([0; 65536]);
+    let len = image.len().min(ram.len());
+    ram[..len].copy_from_slice(&image[..len]);
+
+    let core = Core {
+        a: 0,
+        p: P::new().with_i(true),
+        pc: start,
+        s: 0xFF,
+        x: 0,
+        y: 0,
+    };
+    let mut cpu = M6502::new(core);
+    let mut bus = Bus::new();
+    let mut last_pc = None;
+    for _ in 0..CYCLE_LIMIT {
+        cpu.clock(&mut bus);
+        if bus.sync() {
+            let pc = bus.addr;
+            if last_pc == Some(pc) || ram[pc as usize] == STP {
+                let mut core = cpu.core();
+                core.pc = pc;
+                return Finished { ram, core, pc };
+            }
+            last_pc = Some(pc);
+        }
+        ram.access(&mut bus);
+        if feedback {
+            let port = ram[FEEDBACK as usize];
+            bus.set_irq(port & 1 != 0);
+            bus.set_nmi(port & 2 != 0);
+        }
+    }
+    panic!("{name} still running after {CYCLE_LIMIT} cycles");
+}
+
+fn listing(name: &str) -> Option<String> {
+    fs::read_to_string(image_dir().join(format!("{name}.lst"))).ok()
+}
+// The listing lines for code at `addr`.
+fn lines_at(listing: &str, addr: u16) -> impl Iterator<Item = &str> {
+    listing
+        .lines()
+        .filter(move |line| line_addr(line) == Some(addr))
+}
+fn line_addr(line: &str) -> Option<u16> {
+    let (addr, _) = line.split_once(" : ")?;
+    u16::from_str_radix(addr.split_whitespace().last()?, 16).ok()
+}
+
+fn check(program: Program) {
+    let finished = run(program.name, program.start, program.feedback);
+    let listing = listing(program.name);
+    let success = listing
+        .as_deref()
+        .and_then(|listing| listing.lines().find(|line| line.contains(SUCCESS)))
+        .and_then(line_addr)
+        .unwrap_or(program.success);
+    if finished.pc == success {
+        return;
+    }
+
+    let mut report = format!("{} trapped at ${:04X}", program.name, finished.pc);
+    if let Some(addr) = program.test_case {
+        report += &format!(" in test case {}", finished.ram[addr as usize]);
+    }
+    report += &format!("\n{:X?}", finished.core);
+    for line in listing
+        .iter()
+        .flat_map(|listing| lines_at(listing, finished.pc))
+    {
+        report += &format!("\n{line}");
+    }
+    panic!("{report}");
+}
+
+// M6502 has no decimal mode, so this needs the image assembled with
+// `disable_decimal = 1`; the prebuilt one fails in the decimal tests.
+#[test]
+#[ignore = "needs $DORMANN_TESTS"]
+fn functional_test() {
+    check(Program {
+        name: "6502_functional_test",
+        start: 0x0400,
+        success: 0x3469,
+        test_case: Some(0x0200),
+        feedback: false,
+    });
+}
+
+#[test]
+#[ignore = "needs $DORMANN_TESTS"]
+fn interrupt_test() {
+    check(Program {
+        name: "6502_interrupt_test",
+        start: 0x0400,
+        success: 0x06F5,
+        test_case: None,
+        feedback: true,
+    });
+}
+
+#[test]
+#[ignore = "M6502 is an NMOS core and has none of the 65C02 opcodes"]
+fn extended_opcodes_test() {
+    check(Program {
+        name: "65C02_extended_opcodes_test",
+        start: 0x0400,
+        success: 0x24F1,
+        test_case: Some(0x0202),
+        feedback: false,
+    });
+}
+
+// There is no prebuilt image of the decimal test: assemble it with the
+// default configuration, which starts it at $0200 and leaves 0 in ERROR
+// ($0B) when it passes. It ends with STP rather than a loop.
+#[test]
+#[ignore = "M6502 has no decimal mode, like the 2A03 the opcode tests are for"]
+fn decimal_test() {
+    let finished = run("6502_decimal_test", 0x0200, false);
+    let (n1, n2, error) = (finished.ram[0], finished.ram[1], finished.ram[0x0B]);
+    assert!(
+        error == 0,
+        "6502_decimal_test failed at ${:04X} on N1={n1:02X} N2={n2:02X} carry loop Y={:02X}",
+        finished.pc,
+        finished.core.y,
+    );
+}